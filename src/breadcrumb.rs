@@ -0,0 +1,143 @@
+use ab_glyph::{FontArc, PxScale};
+use image::{Rgba, RgbaImage};
+use imageproc::drawing::{draw_text_mut, text_size};
+use std::path::{Path, PathBuf};
+
+const SCALE: f32 = 15.0;
+/// Left margin before the first component, in pixels
+const MARGIN: u32 = 10;
+const SEPARATOR: &str = " > ";
+/// Stands in for the components elided from the middle of long paths
+const ELLIPSIS: &str = "...";
+
+/// One component of the bar, `width` pixels wide starting `x` pixels in.
+struct Crumb {
+    // None for the ellipsis, which goes nowhere
+    target: Option<PathBuf>,
+    label: String,
+    x: u32,
+    width: u32,
+}
+
+/// The grid's folder as a row of clickable ancestors, root first.
+pub struct Breadcrumb {
+    folder: PathBuf,
+    width: u32,
+    crumbs: Vec<Crumb>,
+}
+
+impl Breadcrumb {
+    /// Lays out `folder` to fit `width` pixels. When it doesn't, components are
+    /// dropped from the middle, keeping the root and as many of the innermost
+    /// folders as fit.
+    pub fn new(folder: &Path, width: u32, font: &FontArc) -> Self {
+        let measure = |text: &str| text_size(PxScale::from(SCALE), font, text).0;
+        let mut components: Vec<(PathBuf, String)> = folder
+            .ancestors()
+            .map(|ancestor| {
+                let label = ancestor
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| ancestor.to_string_lossy().into_owned());
+                (ancestor.to_path_buf(), label)
+            })
+            .filter(|(_, label)| !label.is_empty())
+            .collect();
+        components.reverse();
+
+        let separator = measure(SEPARATOR);
+        let widths: Vec<u32> = components.iter().map(|(_, label)| measure(label)).collect();
+        let mut used: u32 = MARGIN + widths.first().copied().unwrap_or(0);
+        // Index of the first component after the root that is still shown
+        let mut first_kept = components.len();
+        while first_kept > 1 {
+            let next = widths[first_kept - 1] + separator;
+            let elided = if first_kept - 1 > 1 {
+                measure(ELLIPSIS) + separator
+            } else {
+                0
+            };
+            if first_kept < components.len() && used + next + elided > width {
+                break;
+            }
+            used += next;
+            first_kept -= 1;
+        }
+
+        let mut crumbs = Vec::new();
+        let mut x = MARGIN;
+        let mut push = |target: Option<PathBuf>, label: String, label_width: u32| {
+            crumbs.push(Crumb {
+                target,
+                label,
+                x,
+                width: label_width,
+            });
+            x += label_width + separator;
+        };
+        for (i, (path, label)) in components.into_iter().enumerate() {
+            if i == 1 && first_kept > 1 {
+                push(None, ELLIPSIS.to_string(), measure(ELLIPSIS));
+            }
+            if i == 0 || i >= first_kept {
+                push(Some(path), label, widths[i]);
+            }
+        }
+
+        Breadcrumb {
+            folder: folder.to_path_buf(),
+            width,
+            crumbs,
+        }
+    }
+
+    /// Whether this was laid out for `folder` in a bar `width` pixels wide.
+    pub fn is_for(&self, folder: &Path, width: u32) -> bool {
+        self.folder == folder && self.width == width
+    }
+
+    /// Folder of the component under `x`, if it is one to go to.
+    pub fn target_at(&self, x: f32) -> Option<&Path> {
+        let crumb = self
+            .crumbs
+            .iter()
+            .find(|crumb| (crumb.x as f32..(crumb.x + crumb.width) as f32).contains(&x))?;
+        crumb.target.as_deref().filter(|&path| path != self.folder)
+    }
+
+    /// The bar as an image `height` pixels tall; the current folder stands out.
+    pub fn draw(&self, height: u32, font: &FontArc) -> RgbaImage {
+        let mut bar = RgbaImage::from_pixel(self.width.max(1), height, Rgba([0, 0, 0, 200]));
+        let y = (height.saturating_sub(SCALE as u32) / 2) as i32;
+        let scale = PxScale::from(SCALE);
+        for (i, crumb) in self.crumbs.iter().enumerate() {
+            if i > 0 {
+                let x = crumb.x - text_size(scale, font, SEPARATOR).0;
+                draw_text_mut(
+                    &mut bar,
+                    Rgba([120, 120, 120, 255]),
+                    x as i32,
+                    y,
+                    scale,
+                    font,
+                    SEPARATOR,
+                );
+            }
+            let color = if i + 1 == self.crumbs.len() {
+                Rgba([255, 255, 255, 255])
+            } else {
+                Rgba([170, 190, 220, 255])
+            };
+            draw_text_mut(
+                &mut bar,
+                color,
+                crumb.x as i32,
+                y,
+                scale,
+                font,
+                &crumb.label,
+            );
+        }
+        bar
+    }
+}
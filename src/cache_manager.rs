@@ -1,26 +1,78 @@
+use crate::loader::ThumbnailShape;
+use crate::renderer::FitMode;
 use image::RgbaImage;
 use serde::{Deserialize, Serialize};
 use sled::Db;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Default cap on thumbnail data, overridable with FASTVIEW_CACHE_MB
+const DEFAULT_MAX_CACHE_BYTES: u64 = 500 * 1024 * 1024;
+
+/// Thumbnail writes between explicit flushes. sled also flushes in the background
+/// every 500 ms, so a crash loses at most this batch or that interval.
+const FLUSH_BATCH: u64 = 64;
+
+/// Keys holding settings rather than thumbnails; these are never evicted
+const SETTINGS_KEYS: &[&str] = &["window_settings", "last_folder"];
+
+/// Folders whose view state is remembered; the least recently left go first
+const MAX_REMEMBERED_FOLDERS: usize = 500;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct CacheEntry {
+    // For a folder montage, the signature of the images it was made from
     pub mtime: u64,
     pub size: u64,
+    pub width: u32,
+    pub height: u32,
     pub thumbnail_data: Vec<u8>,
 }
 
+/// Windowed geometry, kept as it was before the window was maximized.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct WindowSettings {
     pub x: i32,
     pub y: i32,
     pub width: u32,
     pub height: u32,
+    pub maximized: bool,
+}
+
+/// What a folder looked like when it was last left.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FolderState {
+    // File name of the image last viewed in the folder
+    pub last_image: Option<PathBuf>,
+    pub fit_mode: FitMode,
+}
+
+/// Directory holding the sled cache and user config files.
+pub fn app_dir() -> PathBuf {
+    if let Ok(local_appdata) = std::env::var("LOCALAPPDATA") {
+        PathBuf::from(local_appdata).join("fastview")
+    } else {
+        let mut exe_path = std::env::current_exe().unwrap_or_else(|_| PathBuf::from("."));
+        exe_path.pop();
+        exe_path
+    }
 }
 
 #[derive(Clone)]
 pub struct CacheManager {
     db: Db,
+    // Last access time in ms since the epoch, per thumbnail key
+    access: sled::Tree,
+    // Per-folder view state with the time it was saved, keyed by folder path
+    folders: sled::Tree,
+    // Quarter turns the user added to images that weren't rotated on disk, keyed by path
+    rotations: sled::Tree,
+    total_bytes: Arc<AtomicU64>,
+    // Thumbnail writes since the last flush, shared by all loader threads
+    unflushed: Arc<AtomicU64>,
+    max_bytes: u64,
 }
 
 impl CacheManager {
@@ -28,59 +80,293 @@ impl CacheManager {
         self.clone()
     }
     pub fn new() -> Self {
-        let cache_dir = if let Ok(local_appdata) = std::env::var("LOCALAPPDATA") {
-            PathBuf::from(local_appdata).join("fastview")
-        } else {
-            let mut exe_path = std::env::current_exe().unwrap_or_else(|_| PathBuf::from("."));
-            exe_path.pop();
-            exe_path
-        };
+        let cache_dir = app_dir();
 
         let db_path = cache_dir.join("fastview_cache");
         std::fs::create_dir_all(&cache_dir).ok();
 
         let db = sled::open(db_path).expect("Failed to open cache database");
-        CacheManager { db }
+        Self::with_db(db)
+    }
+
+    /// A cache in memory only, dropped with the last handle.
+    #[cfg(test)]
+    pub fn temporary() -> Self {
+        let db = sled::Config::new()
+            .temporary(true)
+            .open()
+            .expect("Failed to open temporary cache database");
+        Self::with_db(db)
+    }
+
+    fn with_db(db: Db) -> Self {
+        let access = db
+            .open_tree("access_times")
+            .expect("Failed to open cache access tree");
+        let folders = db
+            .open_tree("folder_states")
+            .expect("Failed to open cache folder tree");
+        let rotations = db
+            .open_tree("user_rotations")
+            .expect("Failed to open cache rotation tree");
+        let max_bytes = std::env::var("FASTVIEW_CACHE_MB")
+            .ok()
+            .and_then(|mb| mb.trim().parse::<u64>().ok())
+            .map(|mb| mb * 1024 * 1024)
+            .unwrap_or(DEFAULT_MAX_CACHE_BYTES);
+
+        CacheManager {
+            db,
+            access,
+            folders,
+            rotations,
+            total_bytes: Arc::new(AtomicU64::new(0)),
+            unflushed: Arc::new(AtomicU64::new(0)),
+            max_bytes,
+        }
+    }
+
+    pub fn flush(&self) {
+        self.unflushed.store(0, Ordering::Relaxed);
+        let _ = self.db.flush();
+    }
+
+    pub fn max_bytes(&self) -> u64 {
+        self.max_bytes
+    }
+
+    fn now_millis() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0)
+    }
+
+    fn touch(&self, key: &str) {
+        let _ = self
+            .access
+            .insert(key, &Self::now_millis().to_be_bytes()[..]);
+    }
+
+    fn is_settings_key(key: &[u8]) -> bool {
+        SETTINGS_KEYS.iter().any(|k| k.as_bytes() == key)
+    }
+
+    /// Evicts least recently used thumbnails until they total at most `max_bytes`.
+    /// Also resynchronizes the running byte total with what is on disk.
+    pub fn prune(&self, max_bytes: u64) {
+        let mut entries: Vec<(u64, sled::IVec, u64)> = self
+            .db
+            .iter()
+            .flatten()
+            .filter(|(key, _)| !Self::is_settings_key(key))
+            .map(|(key, value)| {
+                let last_access = self
+                    .access
+                    .get(&key)
+                    .ok()
+                    .flatten()
+                    .and_then(|v| v.as_ref().try_into().ok())
+                    .map(u64::from_be_bytes)
+                    .unwrap_or(0);
+                (last_access, key, value.len() as u64)
+            })
+            .collect();
+
+        let mut total: u64 = entries.iter().map(|(_, _, size)| size).sum();
+        if total > max_bytes {
+            entries.sort_by_key(|(last_access, _, _)| *last_access);
+            for (_, key, size) in entries {
+                if total <= max_bytes {
+                    break;
+                }
+                let _ = self.db.remove(&key);
+                let _ = self.access.remove(&key);
+                total -= size;
+            }
+            let _ = self.db.flush();
+        }
+        self.total_bytes.store(total, Ordering::Relaxed);
+    }
+
+    /// Removes every thumbnail entry, keeping settings. Returns how many were removed.
+    pub fn clear_thumbnails(&self) -> usize {
+        let keys: Vec<sled::IVec> = self
+            .db
+            .iter()
+            .keys()
+            .flatten()
+            .filter(|key| !Self::is_settings_key(key))
+            .collect();
+        for key in &keys {
+            let _ = self.db.remove(key);
+        }
+        let _ = self.access.clear();
+        let _ = self.db.flush();
+        self.total_bytes.store(0, Ordering::Relaxed);
+        keys.len()
     }
 
-    fn get_key(path: &Path) -> String {
-        path.to_string_lossy().to_string()
+    fn file_stamp(path: &Path) -> Option<(u64, u64)> {
+        let meta = std::fs::metadata(path).ok()?;
+        // Filesystems without mtime support report 0 so only the size is compared
+        let mtime = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        Some((mtime, meta.len()))
     }
 
-    pub fn get(&self, path: &Path) -> Option<CacheEntry> {
-        let key = Self::get_key(path);
-        let result = self.db.get(key).ok()??;
+    /// Thumbnails of different sizes or shapes for one file are stored independently.
+    fn get_key(path: &Path, thumb_size: u32, shape: ThumbnailShape) -> String {
+        match shape {
+            ThumbnailShape::Crop => format!("{}|{}", path.to_string_lossy(), thumb_size),
+            ThumbnailShape::Fit => format!("{}|{}|fit", path.to_string_lossy(), thumb_size),
+        }
+    }
+
+    pub fn get(&self, path: &Path, thumb_size: u32, shape: ThumbnailShape) -> Option<CacheEntry> {
+        let key = Self::get_key(path, thumb_size, shape);
+        let result = self.db.get(&key).ok()??;
+        self.touch(&key);
         bincode::deserialize(&result).ok()
     }
 
-    pub fn set(&self, path: &Path, entry: CacheEntry) {
-        let key = Self::get_key(path);
+    pub fn set(&self, path: &Path, thumb_size: u32, shape: ThumbnailShape, entry: CacheEntry) {
+        let key = Self::get_key(path, thumb_size, shape);
         if let Ok(data) = bincode::serialize(&entry) {
-            let _ = self.db.insert(key, data);
-            let _ = self.db.flush();
+            let added = data.len() as u64;
+            let mut replaced = self
+                .db
+                .insert(&key, data)
+                .ok()
+                .flatten()
+                .map_or(0, |old| old.len() as u64);
+            // Entries from before sizes were part of the key are dropped as files are revisited
+            let legacy_key = path.to_string_lossy();
+            if !Self::is_settings_key(legacy_key.as_bytes()) {
+                replaced += self
+                    .db
+                    .remove(legacy_key.as_bytes())
+                    .ok()
+                    .flatten()
+                    .map_or(0, |old| old.len() as u64);
+                let _ = self.access.remove(legacy_key.as_bytes());
+            }
+            self.touch(&key);
+            // Flushing every insert serialized the loader threads on disk syncs
+            if self.unflushed.fetch_add(1, Ordering::Relaxed) + 1 >= FLUSH_BATCH {
+                self.flush();
+            }
+
+            let update = |total: u64| (total + added).saturating_sub(replaced);
+            let previous = self
+                .total_bytes
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |t| Some(update(t)))
+                .unwrap_or(0);
+            if update(previous) > self.max_bytes {
+                // Leave some headroom so the next few inserts don't prune again
+                self.prune(self.max_bytes / 10 * 9);
+            }
         }
     }
 
-    pub fn get_thumbnail(&self, path: &Path) -> Option<RgbaImage> {
-        let entry = self.get(path)?;
-        image::load_from_memory(&entry.thumbnail_data)
-            .ok()?
-            .to_rgba8()
-            .into()
+    /// Cached thumbnail of `path` generated for a `thumb_size` pixel box.
+    pub fn get_thumbnail(
+        &self,
+        path: &Path,
+        thumb_size: u32,
+        shape: ThumbnailShape,
+    ) -> Option<RgbaImage> {
+        let entry = self.get(path, thumb_size, shape)?;
+        let (mtime, size) = Self::file_stamp(path)?;
+        if entry.size != size || (entry.mtime != 0 && mtime != 0 && entry.mtime != mtime) {
+            return None;
+        }
+        RgbaImage::from_raw(entry.width, entry.height, entry.thumbnail_data)
     }
 
-    pub fn set_thumbnail(&self, path: &Path, img: &RgbaImage) {
+    pub fn set_thumbnail(
+        &self,
+        path: &Path,
+        thumb_size: u32,
+        shape: ThumbnailShape,
+        img: &RgbaImage,
+    ) {
+        let (mtime, size) = Self::file_stamp(path).unwrap_or((0, 0));
         let entry = CacheEntry {
-            mtime: 0,
+            mtime,
+            size,
+            width: img.width(),
+            height: img.height(),
+            thumbnail_data: img.to_vec(),
+        };
+        self.set(path, thumb_size, shape, entry);
+    }
+
+    /// Montage cached for the folder at `path`, if it was made from images
+    /// matching `signature`. Folders have no thumbnail of their own, so the
+    /// montage takes that key.
+    pub fn get_folder_thumbnail(
+        &self,
+        path: &Path,
+        thumb_size: u32,
+        signature: u64,
+    ) -> Option<RgbaImage> {
+        let entry = self.get(path, thumb_size, ThumbnailShape::Crop)?;
+        if entry.mtime != signature {
+            return None;
+        }
+        RgbaImage::from_raw(entry.width, entry.height, entry.thumbnail_data)
+    }
+
+    pub fn set_folder_thumbnail(
+        &self,
+        path: &Path,
+        thumb_size: u32,
+        signature: u64,
+        img: &RgbaImage,
+    ) {
+        let entry = CacheEntry {
+            mtime: signature,
             size: 0,
+            width: img.width(),
+            height: img.height(),
             thumbnail_data: img.to_vec(),
         };
-        self.set(path, entry);
+        self.set(path, thumb_size, ThumbnailShape::Crop, entry);
+    }
+
+    /// Drops both the cropped and the fitted thumbnail of `path`.
+    pub fn remove_thumbnail(&self, path: &Path, thumb_size: u32) {
+        for shape in [ThumbnailShape::Crop, ThumbnailShape::Fit] {
+            let key = Self::get_key(path, thumb_size, shape);
+            if let Ok(Some(old)) = self.db.remove(&key) {
+                let _ = self.access.remove(&key);
+                let _ =
+                    self.total_bytes
+                        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |total| {
+                            Some(total.saturating_sub(old.len() as u64))
+                        });
+            }
+        }
     }
 
     pub fn get_window_settings(&self) -> Option<WindowSettings> {
         let result = self.db.get("window_settings").ok()??;
-        bincode::deserialize(&result).ok()
+        bincode::deserialize(&result).ok().or_else(|| {
+            // Saved before the maximized flag existed
+            let (x, y, width, height) = bincode::deserialize(&result).ok()?;
+            Some(WindowSettings {
+                x,
+                y,
+                width,
+                height,
+                maximized: false,
+            })
+        })
     }
 
     pub fn set_window_settings(&self, settings: &WindowSettings) {
@@ -89,4 +375,273 @@ impl CacheManager {
             let _ = self.db.flush();
         }
     }
+
+    pub fn get_last_folder(&self) -> Option<PathBuf> {
+        let result = self.db.get("last_folder").ok()??;
+        bincode::deserialize(&result).ok()
+    }
+
+    /// Not flushed; the flush on exit makes it durable.
+    pub fn set_last_folder(&self, path: &Path) {
+        if let Ok(data) = bincode::serialize(path) {
+            let _ = self.db.insert("last_folder", data);
+        }
+    }
+
+    /// Clockwise quarter turns the user applied to `path`, on top of its EXIF orientation.
+    pub fn get_rotation(&self, path: &Path) -> u8 {
+        self.rotations
+            .get(path.to_string_lossy().as_bytes())
+            .ok()
+            .flatten()
+            .and_then(|value| value.first().copied())
+            .map_or(0, |turns| turns % 4)
+    }
+
+    /// Remembers `quarter_turns` for `path`; zero forgets it.
+    pub fn set_rotation(&self, path: &Path, quarter_turns: u8) {
+        let key = path.to_string_lossy();
+        let _ = match quarter_turns % 4 {
+            0 => self.rotations.remove(key.as_bytes()),
+            turns => self.rotations.insert(key.as_bytes(), &[turns]),
+        };
+        let _ = self.rotations.flush();
+    }
+
+    /// Forgets every remembered rotation, along with the `thumb_size` thumbnails
+    /// drawn with it; returns how many there were.
+    pub fn clear_rotations(&self, thumb_size: u32) -> usize {
+        let count = self.rotations.len();
+        for key in self.rotations.iter().keys().flatten() {
+            let path = PathBuf::from(String::from_utf8_lossy(&key).into_owned());
+            self.remove_thumbnail(&path, thumb_size);
+        }
+        let _ = self.rotations.clear();
+        let _ = self.rotations.flush();
+        count
+    }
+
+    pub fn get_folder_state(&self, folder: &Path) -> Option<FolderState> {
+        let result = self
+            .folders
+            .get(folder.to_string_lossy().as_bytes())
+            .ok()??;
+        let (_, state): (u64, FolderState) = bincode::deserialize(&result).ok()?;
+        Some(state)
+    }
+
+    /// Stores `state` for `folder`, forgetting the least recently saved folders
+    /// beyond `MAX_REMEMBERED_FOLDERS`.
+    pub fn set_folder_state(&self, folder: &Path, state: &FolderState) {
+        let Ok(data) = bincode::serialize(&(Self::now_millis(), state)) else {
+            return;
+        };
+        let _ = self
+            .folders
+            .insert(folder.to_string_lossy().as_bytes(), data);
+
+        if self.folders.len() > MAX_REMEMBERED_FOLDERS {
+            let mut saved: Vec<(u64, sled::IVec)> = self
+                .folders
+                .iter()
+                .flatten()
+                .map(|(key, value)| {
+                    let stamp = bincode::deserialize::<(u64, FolderState)>(&value)
+                        .map_or(0, |(stamp, _)| stamp);
+                    (stamp, key)
+                })
+                .collect();
+            saved.sort_by_key(|(stamp, _)| *stamp);
+            let excess = saved.len().saturating_sub(MAX_REMEMBERED_FOLDERS);
+            for (_, key) in saved.into_iter().take(excess) {
+                let _ = self.folders.remove(key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn thumbnails_round_trip_unchanged() {
+        let dir = TempDir::new("thumbnail-round-trip");
+        let photo = dir.join("photo.png");
+        std::fs::write(&photo, b"stand-in for an image").unwrap();
+        let thumb = RgbaImage::from_fn(7, 3, |x, y| image::Rgba([x as u8, y as u8, 9, 200]));
+
+        let cache = CacheManager::temporary();
+        cache.set_thumbnail(&photo, 256, ThumbnailShape::Crop, &thumb);
+        assert_eq!(
+            cache.get_thumbnail(&photo, 256, ThumbnailShape::Crop),
+            Some(thumb)
+        );
+        assert_eq!(cache.get_thumbnail(&photo, 256, ThumbnailShape::Fit), None);
+        assert_eq!(cache.get_thumbnail(&photo, 128, ThumbnailShape::Crop), None);
+    }
+
+    #[test]
+    fn rotations_round_trip_through_their_tree() {
+        let cache = CacheManager::temporary();
+        let photo = Path::new("/photos/a.png");
+        assert_eq!(cache.get_rotation(photo), 0);
+
+        cache.set_rotation(photo, 3);
+        assert_eq!(cache.get_rotation(photo), 3);
+        assert_eq!(cache.rotations.len(), 1);
+        // A full turn is no rotation and leaves nothing stored
+        cache.set_rotation(photo, 4);
+        assert_eq!(cache.get_rotation(photo), 0);
+        assert!(cache.rotations.is_empty());
+
+        cache.set_rotation(photo, 1);
+        cache.set_rotation(Path::new("/photos/b.png"), 2);
+        assert_eq!(cache.clear_rotations(256), 2);
+        assert_eq!(cache.get_rotation(photo), 0);
+    }
+
+    #[test]
+    fn thumbnail_of_a_changed_file_is_a_miss() {
+        let dir = TempDir::new("thumbnail-stale");
+        let photo = dir.join("photo.png");
+        std::fs::write(&photo, b"first version").unwrap();
+        let thumb = RgbaImage::new(2, 2);
+        let cache = CacheManager::temporary();
+
+        cache.set_thumbnail(&photo, 256, ThumbnailShape::Crop, &thumb);
+        let newer = SystemTime::now() + std::time::Duration::from_secs(60);
+        std::fs::File::options()
+            .write(true)
+            .open(&photo)
+            .unwrap()
+            .set_modified(newer)
+            .unwrap();
+        assert_eq!(cache.get_thumbnail(&photo, 256, ThumbnailShape::Crop), None);
+
+        // Same for a rewrite of a different length, whatever its timestamp
+        cache.set_thumbnail(&photo, 256, ThumbnailShape::Crop, &thumb);
+        std::fs::write(&photo, b"second, longer version").unwrap();
+        assert_eq!(cache.get_thumbnail(&photo, 256, ThumbnailShape::Crop), None);
+    }
+
+    #[test]
+    fn prune_evicts_least_recently_used_first() {
+        let dir = TempDir::new("thumbnail-lru");
+        let photos: Vec<PathBuf> = ["a.png", "b.png", "c.png"]
+            .iter()
+            .map(|name| dir.join(name))
+            .collect();
+        let thumb = RgbaImage::new(8, 8);
+        let cache = CacheManager::temporary();
+        let pause = || std::thread::sleep(std::time::Duration::from_millis(5));
+        for photo in &photos {
+            std::fs::write(photo, b"stand-in").unwrap();
+            cache.set_thumbnail(photo, 256, ThumbnailShape::Crop, &thumb);
+            pause();
+        }
+        // Looking at the oldest makes the middle one least recently used
+        assert!(cache
+            .get_thumbnail(&photos[0], 256, ThumbnailShape::Crop)
+            .is_some());
+        pause();
+
+        let entry_bytes = cache.total_bytes.load(Ordering::Relaxed) / 3;
+        cache.prune(entry_bytes * 2);
+        assert!(cache
+            .get_thumbnail(&photos[0], 256, ThumbnailShape::Crop)
+            .is_some());
+        assert_eq!(
+            cache.get_thumbnail(&photos[1], 256, ThumbnailShape::Crop),
+            None
+        );
+        assert!(cache
+            .get_thumbnail(&photos[2], 256, ThumbnailShape::Crop)
+            .is_some());
+        assert_eq!(cache.total_bytes.load(Ordering::Relaxed), entry_bytes * 2);
+    }
+
+    #[test]
+    fn going_over_the_limit_prunes_on_insert() {
+        let dir = TempDir::new("thumbnail-limit");
+        let thumb = RgbaImage::new(8, 8);
+        let mut cache = CacheManager::temporary();
+        let first = dir.join("first.png");
+        std::fs::write(&first, b"stand-in").unwrap();
+        cache.set_thumbnail(&first, 256, ThumbnailShape::Crop, &thumb);
+        cache.max_bytes = cache.total_bytes.load(Ordering::Relaxed) * 3 / 2;
+        std::thread::sleep(std::time::Duration::from_millis(5));
+
+        let second = dir.join("second.png");
+        std::fs::write(&second, b"stand-in").unwrap();
+        cache.set_thumbnail(&second, 256, ThumbnailShape::Crop, &thumb);
+        assert_eq!(cache.get_thumbnail(&first, 256, ThumbnailShape::Crop), None);
+        assert!(cache
+            .get_thumbnail(&second, 256, ThumbnailShape::Crop)
+            .is_some());
+        assert!(cache.total_bytes.load(Ordering::Relaxed) <= cache.max_bytes());
+    }
+
+    #[test]
+    fn each_thumbnail_size_has_its_own_entry() {
+        let dir = TempDir::new("thumbnail-sizes");
+        let photo = dir.join("photo.png");
+        std::fs::write(&photo, b"stand-in").unwrap();
+        let small = RgbaImage::from_pixel(4, 4, image::Rgba([1, 2, 3, 255]));
+        let large = RgbaImage::from_pixel(8, 8, image::Rgba([4, 5, 6, 255]));
+        let cache = CacheManager::temporary();
+
+        cache.set_thumbnail(&photo, 128, ThumbnailShape::Crop, &small);
+        cache.set_thumbnail(&photo, 256, ThumbnailShape::Crop, &large);
+        assert_eq!(
+            cache.get_thumbnail(&photo, 128, ThumbnailShape::Crop),
+            Some(small)
+        );
+        assert_eq!(
+            cache.get_thumbnail(&photo, 256, ThumbnailShape::Crop),
+            Some(large.clone())
+        );
+
+        // Dropping one size leaves the other
+        cache.remove_thumbnail(&photo, 128);
+        assert_eq!(cache.get_thumbnail(&photo, 128, ThumbnailShape::Crop), None);
+        assert_eq!(
+            cache.get_thumbnail(&photo, 256, ThumbnailShape::Crop),
+            Some(large)
+        );
+    }
+
+    #[test]
+    fn folder_state_round_trips_per_folder() {
+        let cache = CacheManager::temporary();
+        let holidays = Path::new("/photos/holidays");
+        assert_eq!(cache.get_folder_state(holidays), None);
+
+        let state = FolderState {
+            last_image: Some(PathBuf::from("beach.jpg")),
+            fit_mode: FitMode::FitWidth,
+        };
+        cache.set_folder_state(holidays, &state);
+        assert_eq!(cache.get_folder_state(holidays), Some(state));
+        assert_eq!(cache.get_folder_state(Path::new("/photos")), None);
+
+        // Leaving the folder again replaces what was saved
+        let later = FolderState {
+            last_image: None,
+            fit_mode: FitMode::FillWindow,
+        };
+        cache.set_folder_state(holidays, &later);
+        assert_eq!(cache.get_folder_state(holidays), Some(later));
+        assert_eq!(cache.folders.len(), 1);
+    }
+
+    #[test]
+    fn last_folder_round_trips_without_a_flush() {
+        let cache = CacheManager::temporary();
+        assert_eq!(cache.get_last_folder(), None);
+        cache.set_last_folder(Path::new("/photos/holidays"));
+        cache.set_last_folder(Path::new("/photos/work"));
+        assert_eq!(cache.get_last_folder(), Some(PathBuf::from("/photos/work")));
+    }
 }
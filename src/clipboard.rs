@@ -0,0 +1,72 @@
+use arboard::{Clipboard, ImageData};
+use image::RgbaImage;
+use std::borrow::Cow;
+
+/// Keeps the system clipboard open for the lifetime of the app. On X11 and
+/// Wayland the copied data is only served while the handle is alive.
+#[derive(Default)]
+pub struct SystemClipboard {
+    inner: Option<Clipboard>,
+}
+
+impl SystemClipboard {
+    fn handle(&mut self) -> Option<&mut Clipboard> {
+        if self.inner.is_none() {
+            match Clipboard::new() {
+                Ok(c) => self.inner = Some(c),
+                Err(e) => log::warn!("Clipboard unavailable: {}", e),
+            }
+        }
+        self.inner.as_mut()
+    }
+
+    pub fn set_image(&mut self, img: &RgbaImage) -> bool {
+        let Some(clipboard) = self.handle() else {
+            return false;
+        };
+        match clipboard.set_image(to_image_data(img)) {
+            Ok(()) => true,
+            Err(e) => {
+                log::warn!("Failed to copy image to clipboard: {}", e);
+                false
+            }
+        }
+    }
+
+    pub fn set_text(&mut self, text: &str) -> bool {
+        let Some(clipboard) = self.handle() else {
+            return false;
+        };
+        match clipboard.set_text(text) {
+            Ok(()) => true,
+            Err(e) => {
+                log::warn!("Failed to copy text to clipboard: {}", e);
+                false
+            }
+        }
+    }
+}
+
+/// Borrows an RGBA buffer in the layout arboard expects.
+pub fn to_image_data(img: &RgbaImage) -> ImageData<'_> {
+    ImageData {
+        width: img.width() as usize,
+        height: img.height() as usize,
+        bytes: Cow::Borrowed(img.as_raw()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_data_borrows_the_rgba_rows() {
+        let img = RgbaImage::from_fn(3, 2, |x, y| image::Rgba([x as u8, y as u8, 7, 255]));
+        let data = to_image_data(&img);
+        assert_eq!((data.width, data.height), (3, 2));
+        assert!(matches!(data.bytes, Cow::Borrowed(_)));
+        // Row-major, four bytes per pixel: (1, 1) starts at (1 * 3 + 1) * 4
+        assert_eq!(&data.bytes[16..20], &[1, 1, 7, 255]);
+    }
+}
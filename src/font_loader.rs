@@ -0,0 +1,52 @@
+use ab_glyph::FontArc;
+use std::path::PathBuf;
+
+#[cfg(target_os = "windows")]
+const FONT_CANDIDATES: &[&str] = &[
+    "Fonts\\arial.ttf",
+    "Fonts\\segoeui.ttf",
+    "Fonts\\tahoma.ttf",
+];
+
+#[cfg(target_os = "macos")]
+const FONT_CANDIDATES: &[&str] = &[
+    "/System/Library/Fonts/Supplemental/Arial.ttf",
+    "/Library/Fonts/Arial.ttf",
+    "/System/Library/Fonts/Helvetica.ttc",
+    "/System/Library/Fonts/SFNS.ttf",
+];
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const FONT_CANDIDATES: &[&str] = &[
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/dejavu-sans-fonts/DejaVuSans.ttf",
+    "/usr/share/fonts/truetype/liberation/LiberationSans-Regular.ttf",
+    "/usr/share/fonts/liberation-sans/LiberationSans-Regular.ttf",
+    "/usr/share/fonts/truetype/noto/NotoSans-Regular.ttf",
+    "/usr/share/fonts/noto/NotoSans-Regular.ttf",
+    "/usr/local/share/fonts/DejaVuSans.ttf",
+];
+
+fn candidate_paths() -> Vec<PathBuf> {
+    if cfg!(target_os = "windows") {
+        // Fonts live under %WINDIR%, which is not always C:\Windows
+        let windir = std::env::var("WINDIR").unwrap_or_else(|_| "C:\\Windows".to_string());
+        FONT_CANDIDATES
+            .iter()
+            .map(|f| PathBuf::from(&windir).join(f))
+            .collect()
+    } else {
+        FONT_CANDIDATES.iter().map(PathBuf::from).collect()
+    }
+}
+
+/// Returns the first usable UI font found on this platform.
+pub fn load_font() -> Option<FontArc> {
+    candidate_paths().into_iter().find_map(|path| {
+        std::fs::read(&path)
+            .ok()
+            .and_then(|data| FontArc::try_from_vec(data).ok())
+    })
+}
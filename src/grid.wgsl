@@ -0,0 +1,131 @@
+// Grid view: every thumbnail held in a layer of one array texture is drawn
+// as an instance of a single quad.
+
+struct GridView {
+    window_size: vec2<f32>,
+    _pad: vec2<f32>,
+};
+
+@group(1) @binding(0)
+var<uniform> view: GridView;
+
+struct GridInstance {
+    @location(0) origin: vec2<f32>, // Top-left of the cell in pixels
+    @location(1) image_size: vec2<f32>,
+    @location(2) uv_scale: vec2<f32>, // Part of the layer the thumbnail covers
+    @location(3) cell: f32,
+    @location(4) selected: f32, // Flags, see fs_grid
+    @location(5) layer: u32,
+    @location(6) status: u32, // 0 loaded, 1 loading, 2 failed to decode
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) quad_uv: vec2<f32>,
+    @location(2) @interpolate(flat) uv_scale: vec2<f32>,
+    @location(3) @interpolate(flat) cell: f32,
+    @location(4) @interpolate(flat) selected: f32,
+    @location(5) @interpolate(flat) layer: u32,
+    @location(6) @interpolate(flat) status: u32,
+};
+
+@vertex
+fn vs_grid(@builtin(vertex_index) vertex_index: u32, instance: GridInstance) -> VertexOutput {
+    var out: VertexOutput;
+
+    let corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(0.0, 1.0),
+        vec2<f32>(0.0, 1.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(1.0, 1.0)
+    );
+    let base_uv = corners[vertex_index];
+    out.quad_uv = base_uv;
+
+    let pixel_pos = instance.origin + base_uv * instance.cell;
+    let clip_x = (pixel_pos.x / view.window_size.x) * 2.0 - 1.0;
+    let clip_y = 1.0 - (pixel_pos.y / view.window_size.y) * 2.0;
+    out.clip_position = vec4<f32>(clip_x, clip_y, 0.0, 1.0);
+
+    // Letterbox inside the square cell, as in the single-texture path: the long
+    // side spans the cell and the short one is centered, leaving uv outside 0..1
+    let size = instance.image_size;
+    var uv = base_uv;
+    if (size.x > size.y) {
+        uv.y = (uv.y - 0.5) * (size.x / size.y) + 0.5;
+    } else if (size.x < size.y) {
+        uv.x = (uv.x - 0.5) * (size.y / size.x) + 0.5;
+    }
+    out.uv = uv;
+
+    out.uv_scale = instance.uv_scale;
+    out.cell = instance.cell;
+    out.selected = instance.selected;
+    out.layer = instance.layer;
+    out.status = instance.status;
+    return out;
+}
+
+@group(0) @binding(0)
+var t_layers: texture_2d_array<f32>;
+@group(0) @binding(1)
+var s_layers: sampler;
+
+// Gray card with three dots, for thumbnails still being decoded.
+fn loading_card(p: vec2<f32>) -> vec4<f32> {
+    var color = vec3<f32>(0.12, 0.12, 0.13);
+    for (var i = -1; i <= 1; i++) {
+        let dot_center = vec2<f32>(0.5 + f32(i) * 0.08, 0.5);
+        if (distance(p, dot_center) < 0.025) {
+            color = vec3<f32>(0.35, 0.35, 0.37);
+        }
+    }
+    return vec4<f32>(color, 1.0);
+}
+
+// Reddish card with a cracked picture frame, for files that failed to decode.
+fn failed_card(p: vec2<f32>) -> vec4<f32> {
+    let glyph = vec3<f32>(0.75, 0.35, 0.35);
+    let frame = abs(p - vec2<f32>(0.5, 0.5));
+    let border = max(frame.x, frame.y);
+    if (border > 0.14 && border < 0.16) {
+        return vec4<f32>(glyph, 1.0);
+    }
+    // Crack from the bottom-left to the top-right corner of the frame
+    if (border < 0.14 && abs((p.x - 0.5) + (p.y - 0.5)) < 0.012) {
+        return vec4<f32>(glyph, 1.0);
+    }
+    return vec4<f32>(0.16, 0.10, 0.10, 1.0);
+}
+
+@fragment
+fn fs_grid(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Sampled before any branch, where derivatives for mip selection are still valid
+    let color = textureSample(t_layers, s_layers, in.uv * in.uv_scale, in.layer);
+
+    // Bit 0 is the keyboard cursor, bit 1 membership of the multi-selection
+    let flags = u32(in.selected + 0.5);
+    let edge = min(min(in.quad_uv.x, 1.0 - in.quad_uv.x), min(in.quad_uv.y, 1.0 - in.quad_uv.y)) * in.cell;
+    if ((flags & 1u) != 0u && edge < 2.0) {
+        return vec4<f32>(1.0, 0.8, 0.1, 1.0); // Vibrant orange for selection
+    }
+    if ((flags & 2u) != 0u && edge < 4.0) {
+        return vec4<f32>(0.25, 0.6, 1.0, 1.0); // Blue for multi-selected items
+    }
+
+    if (in.status == 1u) {
+        return loading_card(in.quad_uv);
+    }
+    if (in.status == 2u) {
+        return failed_card(in.quad_uv);
+    }
+
+    if (in.uv.x < 0.0 || in.uv.x > 1.0 || in.uv.y < 0.0 || in.uv.y > 1.0) {
+        return vec4<f32>(0.05, 0.05, 0.06, 1.0);
+    }
+
+    return color;
+}
@@ -1,7 +1,24 @@
-use crate::metadata::{apply_orientation, ImageMetadata};
-use image::{DynamicImage, RgbaImage};
-use std::fs;
+use crate::cache_manager::CacheManager;
+use crate::metadata::{
+    apply_orientation, read_orientation_only, write_jpeg_orientation, write_rotated_pixels,
+    ExifOrientation, ImageMetadata,
+};
+use crate::prefetch::PrefetchCache;
+use crate::profile;
+use image::codecs::gif::GifDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::{AnimationDecoder, DynamicImage, Frames, RgbaImage};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader};
 use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+// Browsers treat near-zero GIF delays as 100ms; match that so such files don't spin
+const MIN_FRAME_DELAY: Duration = Duration::from_millis(20);
+const DEFAULT_FRAME_DELAY: Duration = Duration::from_millis(100);
 
 #[derive(Clone, Debug)]
 pub enum FileItem {
@@ -9,11 +26,88 @@ pub enum FileItem {
     Directory(PathBuf),
 }
 
+/// Why an image could not be opened.
+#[derive(Debug)]
+pub enum LoadError {
+    /// Not one of the images listed for the current folder, e.g. an unsupported file type
+    NotInFolder,
+    /// The file could not be read at all
+    Io(io::Error),
+    /// The file was opened, but its format is unsupported or its data is corrupt
+    Decode(image::ImageError),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::NotInFolder => write!(f, "not a supported image"),
+            LoadError::Io(e) => write!(f, "couldn't read the file ({})", e),
+            LoadError::Decode(image::ImageError::Unsupported(e)) => {
+                write!(f, "unsupported image ({})", e)
+            }
+            LoadError::Decode(e) => write!(f, "corrupt image data ({})", e),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SortMode {
+    NameAsc,
+    NameDesc,
+    DateNewest,
+    DateOldest,
+    SizeAsc,
+    SizeDesc,
+}
+
+impl SortMode {
+    pub fn next(self) -> Self {
+        match self {
+            SortMode::NameAsc => SortMode::NameDesc,
+            SortMode::NameDesc => SortMode::DateNewest,
+            SortMode::DateNewest => SortMode::DateOldest,
+            SortMode::DateOldest => SortMode::SizeAsc,
+            SortMode::SizeAsc => SortMode::SizeDesc,
+            SortMode::SizeDesc => SortMode::NameAsc,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortMode::NameAsc => "Name A-Z",
+            SortMode::NameDesc => "Name Z-A",
+            SortMode::DateNewest => "Newest first",
+            SortMode::DateOldest => "Oldest first",
+            SortMode::SizeAsc => "Smallest first",
+            SortMode::SizeDesc => "Largest first",
+        }
+    }
+}
+
+// Stat results gathered once per refresh so sorting doesn't hit the filesystem
+#[derive(Clone, Copy, Default)]
+struct FileInfo {
+    modified: Option<SystemTime>,
+    size: u64,
+}
+
 pub struct ImageLoader {
     folder_path: PathBuf,
     items: Vec<FileItem>,
+    // Filename search narrowing `items`; navigation still uses all of `image_files`
+    filter: Option<String>,
+    filtered_items: Vec<FileItem>,
     image_files: Vec<PathBuf>,
+    file_info: HashMap<PathBuf, FileInfo>,
+    // Bytes of all images in the folder, summed from `file_info`
+    total_size: u64,
+    sort_mode: SortMode,
     current_index: usize,
+    // Quarter turns clockwise applied on top of the file's own orientation,
+    // read from and saved to `rotation_store` once there is one
+    user_rotation: HashMap<PathBuf, u8>,
+    rotation_store: Option<CacheManager>,
+    prefetch: PrefetchCache,
 }
 
 impl ImageLoader {
@@ -24,8 +118,16 @@ impl ImageLoader {
         let mut slf = ImageLoader {
             folder_path,
             items: Vec::new(),
+            filter: None,
+            filtered_items: Vec::new(),
             image_files: Vec::new(),
+            file_info: HashMap::new(),
+            total_size: 0,
+            sort_mode: SortMode::DateNewest,
             current_index: 0,
+            user_rotation: HashMap::new(),
+            rotation_store: None,
+            prefetch: PrefetchCache::default(),
         };
         slf.refresh();
         slf
@@ -34,6 +136,7 @@ impl ImageLoader {
     pub fn refresh(&mut self) {
         self.items.clear();
         self.image_files.clear();
+        self.file_info.clear();
 
         if let Ok(entries) = fs::read_dir(&self.folder_path) {
             for entry in entries.filter_map(|e| e.ok()) {
@@ -42,59 +145,171 @@ impl ImageLoader {
                 if path.is_dir() {
                     self.items.push(FileItem::Directory(path));
                 } else if is_image_file(&path) {
+                    let info = entry
+                        .metadata()
+                        .map(|m| FileInfo {
+                            modified: m.modified().ok(),
+                            size: m.len(),
+                        })
+                        .unwrap_or_default();
+                    self.file_info.insert(path.clone(), info);
                     self.items.push(FileItem::Image(path.clone()));
                     self.image_files.push(path);
                 }
             }
         }
 
+        self.total_size = self.file_info.values().map(|info| info.size).sum();
+        self.sort();
+        self.current_index = 0;
+    }
+
+    fn sort(&mut self) {
+        let mode = self.sort_mode;
+        let info = &self.file_info;
+
         self.items.sort_by(|a, b| match (a, b) {
-            (FileItem::Directory(_), FileItem::Image(_)) => std::cmp::Ordering::Less,
-            (FileItem::Image(_), FileItem::Directory(_)) => std::cmp::Ordering::Greater,
-            (FileItem::Directory(pa), FileItem::Directory(pb)) => pa.cmp(pb),
-            (FileItem::Image(pa), FileItem::Image(pb)) => {
-                let meta_a = fs::metadata(pa).ok().and_then(|m| m.modified().ok());
-                let meta_b = fs::metadata(pb).ok().and_then(|m| m.modified().ok());
-                match (meta_a, meta_b) {
-                    (Some(ta), Some(tb)) => tb.cmp(&ta),
-                    _ => pa.cmp(pb),
-                }
-            }
+            (FileItem::Directory(_), FileItem::Image(_)) => Ordering::Less,
+            (FileItem::Image(_), FileItem::Directory(_)) => Ordering::Greater,
+            (FileItem::Directory(pa), FileItem::Directory(pb)) => match mode {
+                SortMode::NameDesc => compare_names(pb, pa),
+                _ => compare_names(pa, pb),
+            },
+            (FileItem::Image(pa), FileItem::Image(pb)) => compare_images(mode, info, pa, pb),
         });
+        self.image_files
+            .sort_by(|a, b| compare_images(mode, info, a, b));
+        self.apply_filter();
+    }
+
+    /// Limits `get_items` to entries whose file name contains `query`, ignoring case.
+    pub fn set_filter(&mut self, query: Option<&str>) {
+        self.filter = query.map(str::to_string);
+        self.apply_filter();
+    }
 
-        self.image_files.sort_by(|a, b| {
-            let meta_a = fs::metadata(a).ok().and_then(|m| m.modified().ok());
-            let meta_b = fs::metadata(b).ok().and_then(|m| m.modified().ok());
-            match (meta_a, meta_b) {
-                (Some(ta), Some(tb)) => tb.cmp(&ta),
-                _ => a.cmp(b),
+    pub fn get_filter(&self) -> Option<&str> {
+        self.filter.as_deref()
+    }
+
+    fn apply_filter(&mut self) {
+        self.filtered_items.clear();
+        let Some(query) = &self.filter else {
+            return;
+        };
+        let query = query.to_lowercase();
+        self.filtered_items = self
+            .items
+            .iter()
+            .filter(|item| {
+                let (FileItem::Image(p) | FileItem::Directory(p)) = item;
+                p.file_name()
+                    .is_some_and(|n| n.to_string_lossy().to_lowercase().contains(&query))
+            })
+            .cloned()
+            .collect();
+    }
+
+    pub fn get_sort_mode(&self) -> SortMode {
+        self.sort_mode
+    }
+
+    /// Re-sorts the listing in place, keeping the current image selected.
+    pub fn set_sort_mode(&mut self, mode: SortMode) {
+        let current = self.get_current_path().cloned();
+        self.sort_mode = mode;
+        self.sort();
+
+        if let Some(current) = current {
+            if let Some(pos) = self.image_files.iter().position(|p| p == &current) {
+                self.current_index = pos;
             }
-        });
-        self.current_index = 0;
+        }
     }
 
     pub fn set_path(&mut self, mut new_path: PathBuf) {
         new_path = fs::canonicalize(&new_path).unwrap_or(new_path);
         self.folder_path = new_path;
+        self.filter = None;
+        self.prefetch.clear();
         self.refresh();
     }
 
+    /// Keeps rotations that couldn't be written to the file in `cache`, so they
+    /// survive a restart.
+    pub fn set_rotation_store(&mut self, cache: CacheManager) {
+        self.user_rotation.clear();
+        self.rotation_store = Some(cache);
+    }
+
+    /// Quarter turns the user added to `path`, remembered from earlier runs if need be.
+    fn user_turns(&self, path: &Path) -> u8 {
+        match self.user_rotation.get(path) {
+            Some(&turns) => turns,
+            None => self
+                .rotation_store
+                .as_ref()
+                .map_or(0, |store| store.get_rotation(path)),
+        }
+    }
+
+    fn set_user_turns(&mut self, path: PathBuf, turns: u8) {
+        if let Some(store) = &self.rotation_store {
+            store.set_rotation(&path, turns);
+        }
+        self.user_rotation.insert(path, turns);
+    }
+
+    /// Handle for the loader thread to fill with decoded neighbours.
+    pub fn prefetch_handle(&self) -> PrefetchCache {
+        self.prefetch.clone()
+    }
+
+    /// Images next to the current one, the likeliest to be viewed next first.
+    pub fn neighbor_paths(&self) -> Vec<PathBuf> {
+        let count = self.image_files.len();
+        if count < 2 {
+            return Vec::new();
+        }
+        let mut paths = vec![self.image_files[(self.current_index + 1) % count].clone()];
+        let prev = &self.image_files[(self.current_index + count - 1) % count];
+        if !paths.contains(prev) {
+            paths.push(prev.clone());
+        }
+        paths
+    }
+
     pub fn get_path(&self) -> &Path {
         &self.folder_path
     }
 
+    /// Folder entries for the grid, narrowed to the filter when one is set.
     pub fn get_items(&self) -> &[FileItem] {
-        &self.items
+        if self.filter.is_some() {
+            &self.filtered_items
+        } else {
+            &self.items
+        }
     }
 
     pub fn get_image_count(&self) -> usize {
         self.image_files.len()
     }
 
+    pub fn get_total_size(&self) -> u64 {
+        self.total_size
+    }
+
     pub fn get_current_index(&self) -> usize {
         self.current_index
     }
 
+    pub fn set_current_index(&mut self, index: usize) {
+        if !self.image_files.is_empty() {
+            self.current_index = index.min(self.image_files.len() - 1);
+        }
+    }
+
     pub fn get_current_path(&self) -> Option<&PathBuf> {
         if self.image_files.is_empty() {
             return None;
@@ -103,28 +318,153 @@ impl ImageLoader {
     }
 
     pub fn load_current_image(&self) -> Option<RgbaImage> {
-        if self.image_files.is_empty() {
-            return None;
+        self.load_image_at(self.current_index)
+    }
+
+    pub fn try_load_current_image(&self) -> Result<RgbaImage, LoadError> {
+        self.try_load_image_at(self.current_index)
+    }
+
+    pub fn get_image_path(&self, index: usize) -> Option<&PathBuf> {
+        self.image_files.get(index)
+    }
+
+    /// Loads the image at `index` in the image list, with any session rotation applied.
+    pub fn load_image_at(&self, index: usize) -> Option<RgbaImage> {
+        self.try_load_image_at(index).ok()
+    }
+
+    pub fn try_load_image_at(&self, index: usize) -> Result<RgbaImage, LoadError> {
+        let path = self.image_files.get(index).ok_or(LoadError::NotInFolder)?;
+        let img = match self.prefetch.get(path) {
+            Some(img) => img,
+            // Also decodes files the loader thread failed on, to get the reason
+            None => {
+                let img = Self::try_load_dynamic_image_path_with_metadata(path)?.to_rgba8();
+                self.prefetch.insert(path.clone(), img.clone());
+                img
+            }
+        };
+
+        match self.user_turns(path) {
+            0 => Ok(img),
+            turns => {
+                let rotation = ExifOrientation::Normal.rotated_cw(turns);
+                Ok(apply_orientation(&DynamicImage::ImageRgba8(img), rotation).to_rgba8())
+            }
         }
+    }
 
-        let path = &self.image_files[self.current_index];
+    /// Rotates the current image by `quarter_turns` clockwise. JPEGs get the new
+    /// orientation written to their EXIF tag, which leaves the pixels untouched;
+    /// returns true if the change was saved to disk. Other files, and JPEGs that
+    /// couldn't be written, keep the rotation in the rotation store, or for this
+    /// session without one, until `save_current_rotation`.
+    pub fn rotate_current(&mut self, quarter_turns: u8) -> bool {
+        let Some(path) = self.get_current_path().cloned() else {
+            return false;
+        };
+        let pending = self.user_turns(&path);
+        let turns = (pending + quarter_turns) % 4;
+
+        if has_extension(&path, &["jpg", "jpeg"]) {
+            match write_jpeg_orientation(&path, read_orientation_only(&path).rotated_cw(turns)) {
+                Ok(()) => {
+                    self.set_user_turns(path.clone(), 0);
+                    self.prefetch.remove(&path);
+                    return true;
+                }
+                Err(e) => {
+                    log::warn!("Keeping rotation in memory for {}: {}", path.display(), e)
+                }
+            }
+        }
+
+        self.set_user_turns(path, turns);
+        false
+    }
 
-        if let Some(img) = Self::load_dynamic_image_path_with_metadata(path) {
-            Some(img.to_rgba8())
+    /// Writes the current image's stored rotation into the file: JPEGs get their
+    /// EXIF tag, PNGs and lossless WebPs are re-encoded rotated. Ok(false) when
+    /// there is no rotation to save.
+    pub fn save_current_rotation(&mut self) -> io::Result<bool> {
+        let Some(path) = self.get_current_path().cloned() else {
+            return Ok(false);
+        };
+        let turns = self.user_turns(&path);
+        if turns == 0 {
+            return Ok(false);
+        }
+
+        if has_extension(&path, &["jpg", "jpeg"]) {
+            write_jpeg_orientation(&path, read_orientation_only(&path).rotated_cw(turns))?;
+        } else if has_extension(&path, &["png", "webp"]) {
+            write_rotated_pixels(&path, ExifOrientation::Normal.rotated_cw(turns))?;
         } else {
-            None
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "only JPEG, PNG and lossless WebP files can be rotated on disk",
+            ));
         }
+        self.set_user_turns(path.clone(), 0);
+        self.prefetch.remove(&path);
+        Ok(true)
     }
 
     pub fn load_dynamic_image_path_with_metadata(path: &Path) -> Option<DynamicImage> {
-        let metadata = ImageMetadata::from_path(path);
-        let img = image::open(path).ok()?;
+        Self::try_load_dynamic_image_path_with_metadata(path).ok()
+    }
 
-        if metadata.orientation.needs_rotation() {
-            Some(apply_orientation(&img, metadata.orientation))
+    /// Decodes `path` and applies its EXIF orientation.
+    pub fn try_load_dynamic_image_path_with_metadata(
+        path: &Path,
+    ) -> Result<DynamicImage, LoadError> {
+        let orientation = read_orientation_only(path);
+        let img = profile::time("Decoded", path, || Self::try_load_dynamic_image_path(path))?;
+
+        if orientation.needs_rotation() {
+            Ok(apply_orientation(&img, orientation))
         } else {
-            Some(img)
+            Ok(img)
+        }
+    }
+
+    /// Formats that may hold several frames; still WebPs load as a single frame.
+    pub fn is_animated_format(path: &Path) -> bool {
+        has_extension(path, &["gif", "webp"])
+    }
+
+    fn animation_frames(path: &Path) -> Option<Frames<'static>> {
+        let reader = BufReader::new(File::open(path).ok()?);
+        if has_extension(path, &["webp"]) {
+            let decoder = WebPDecoder::new(reader).ok()?;
+            return decoder.has_animation().then(|| decoder.into_frames());
         }
+        Some(GifDecoder::new(reader).ok()?.into_frames())
+    }
+
+    /// Decodes every frame of an animated image along with its display delay.
+    /// Empty for still images, which the regular loading path already covers.
+    pub fn load_frames(path: &Path) -> Vec<(RgbaImage, Duration)> {
+        if !Self::is_animated_format(path) {
+            return Vec::new();
+        }
+        let Some(frames) =
+            Self::animation_frames(path).and_then(|frames| frames.collect_frames().ok())
+        else {
+            return Vec::new();
+        };
+
+        frames
+            .into_iter()
+            .map(|frame| {
+                let mut delay = Duration::from(frame.delay());
+                if delay < MIN_FRAME_DELAY {
+                    delay = DEFAULT_FRAME_DELAY;
+                }
+                (frame.into_buffer(), delay)
+            })
+            .collect()
     }
 
     pub fn get_current_metadata(&self) -> Option<ImageMetadata> {
@@ -135,30 +475,48 @@ impl ImageLoader {
         Some(ImageMetadata::from_path(path))
     }
 
-    pub fn load_image_path(path: &Path) -> Option<RgbaImage> {
-        if let Ok(img) = image::open(path) {
-            Some(img.to_rgba8())
-        } else {
-            None
-        }
-    }
+    /// Decodes `path`; when every decoder fails, the error is the one from `image`.
+    pub fn try_load_dynamic_image_path(path: &Path) -> Result<DynamicImage, LoadError> {
+        let img = image::ImageReader::open(path)
+            .map_err(LoadError::Io)?
+            .decode();
+
+        #[cfg(feature = "icc")]
+        let img = img.map(|img| convert_to_srgb(path, img));
+
+        let img = img.or_else(|e| {
+            // Animated WebPs the still decoder rejects can still show their first frame
+            if !has_extension(path, &["webp"]) {
+                return Err(e);
+            }
+            match Self::animation_frames(path).and_then(|mut frames| frames.next()) {
+                Some(Ok(frame)) => Ok(DynamicImage::ImageRgba8(frame.into_buffer())),
+                _ => Err(e),
+            }
+        });
 
-    pub fn load_dynamic_image_path(path: &Path) -> Option<DynamicImage> {
-        image::open(path).ok()
+        #[cfg(feature = "heif")]
+        let img = img.or_else(|e| decode_heif(path).ok_or(e));
+
+        img.map_err(|e| match e {
+            // Running out of data is a truncated file, not a failed read
+            image::ImageError::IoError(e) if e.kind() != io::ErrorKind::UnexpectedEof => {
+                LoadError::Io(e)
+            }
+            e => LoadError::Decode(e),
+        })
     }
 
-    pub fn next_image(&mut self) -> Option<RgbaImage> {
-        if self.image_files.is_empty() {
-            return None;
+    /// Moves to the next image, wrapping around; decoding is left to the caller.
+    pub fn select_next(&mut self) {
+        if !self.image_files.is_empty() {
+            self.current_index = (self.current_index + 1) % self.image_files.len();
         }
-
-        self.current_index = (self.current_index + 1) % self.image_files.len();
-        self.load_current_image()
     }
 
-    pub fn prev_image(&mut self) -> Option<RgbaImage> {
+    pub fn select_prev(&mut self) {
         if self.image_files.is_empty() {
-            return None;
+            return;
         }
 
         if self.current_index == 0 {
@@ -166,27 +524,634 @@ impl ImageLoader {
         } else {
             self.current_index -= 1;
         }
-
-        self.load_current_image()
     }
 
-    pub fn open_image(&mut self, path: &Path) -> Option<RgbaImage> {
+    /// Makes `path` the current image; false if it isn't in this folder.
+    pub fn select_image(&mut self, path: &Path) -> bool {
         // Ensure path to match is also canonicalized for reliable matching
         let target = fs::canonicalize(path).unwrap_or(path.to_path_buf());
         if let Some(pos) = self.image_files.iter().position(|p| p == &target) {
             self.current_index = pos;
-            self.load_current_image()
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Makes `path` the current image and decodes it.
+    pub fn open_image(&mut self, path: &Path) -> Result<RgbaImage, LoadError> {
+        if self.select_image(path) {
+            self.try_load_current_image()
+        } else {
+            Err(LoadError::NotInFolder)
+        }
+    }
+
+    /// Drops the decoded copy of `path`, so the next load reads the file again.
+    pub fn forget_decoded(&self, path: &Path) {
+        self.prefetch.remove(path);
+    }
+
+    /// Whether `path` was already decoded (or found undecodable) in the background.
+    pub fn is_prefetched(&self, path: &Path) -> bool {
+        self.prefetch.contains(path)
+    }
+
+    pub fn has_user_rotation(&self, path: &Path) -> bool {
+        self.user_turns(path) != 0
+    }
+
+    /// Up to `count` images directly inside `folder`, in name order.
+    pub fn first_images(folder: &Path, count: usize) -> Vec<PathBuf> {
+        let Ok(entries) = fs::read_dir(folder) else {
+            return Vec::new();
+        };
+        let mut images: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| is_image_file(p) && p.is_file())
+            .collect();
+        images.sort_by(|a, b| compare_names(a, b));
+        images.truncate(count);
+        images
+    }
+}
+
+fn compare_names(a: &Path, b: &Path) -> Ordering {
+    let name_a = a.file_name().unwrap_or_default().to_string_lossy();
+    let name_b = b.file_name().unwrap_or_default().to_string_lossy();
+    natural_cmp(&name_a, &name_b).then_with(|| a.cmp(b))
+}
+
+/// Case-insensitive comparison that orders digit runs by numeric value,
+/// so `img2` sorts before `img10`.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+
+    loop {
+        let (ca, cb) = match (a_chars.peek().copied(), b_chars.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(ca), Some(cb)) => (ca, cb),
+        };
+
+        let ord = if ca.is_ascii_digit() && cb.is_ascii_digit() {
+            let run_a = take_digits(&mut a_chars);
+            let run_b = take_digits(&mut b_chars);
+            let trimmed_a = run_a.trim_start_matches('0');
+            let trimmed_b = run_b.trim_start_matches('0');
+            // Compare by magnitude first, then digits, then fewer leading zeros first
+            trimmed_a
+                .len()
+                .cmp(&trimmed_b.len())
+                .then_with(|| trimmed_a.cmp(trimmed_b))
+                .then_with(|| run_a.len().cmp(&run_b.len()))
         } else {
-            None
+            a_chars.next();
+            b_chars.next();
+            ca.to_lowercase().cmp(cb.to_lowercase())
+        };
+
+        if ord != Ordering::Equal {
+            return ord;
         }
     }
 }
 
-fn is_image_file(path: &Path) -> bool {
+fn take_digits(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut run = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+        run.push(c);
+    }
+    run
+}
+
+fn compare_images(
+    mode: SortMode,
+    info: &HashMap<PathBuf, FileInfo>,
+    a: &Path,
+    b: &Path,
+) -> Ordering {
+    let info_a = info.get(a).copied().unwrap_or_default();
+    let info_b = info.get(b).copied().unwrap_or_default();
+
+    // Ties (and files we couldn't stat) fall back to name order for stability
+    match mode {
+        SortMode::NameAsc => compare_names(a, b),
+        SortMode::NameDesc => compare_names(b, a),
+        SortMode::DateNewest => info_b
+            .modified
+            .cmp(&info_a.modified)
+            .then_with(|| compare_names(a, b)),
+        SortMode::DateOldest => info_a
+            .modified
+            .cmp(&info_b.modified)
+            .then_with(|| compare_names(a, b)),
+        SortMode::SizeAsc => info_a
+            .size
+            .cmp(&info_b.size)
+            .then_with(|| compare_names(a, b)),
+        SortMode::SizeDesc => info_b
+            .size
+            .cmp(&info_a.size)
+            .then_with(|| compare_names(a, b)),
+    }
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
     path.extension()
-        .map(|ext| match ext.to_string_lossy().to_lowercase().as_str() {
-            "jpg" | "jpeg" | "png" | "webp" => true,
-            _ => false,
-        })
+        .map(|ext| extensions.contains(&ext.to_string_lossy().to_lowercase().as_str()))
         .unwrap_or(false)
 }
+
+// ICO files hold several sizes; the decoder picks the deepest, largest entry
+fn is_image_file(path: &Path) -> bool {
+    has_extension(
+        path,
+        &[
+            "jpg", "jpeg", "png", "webp", "gif", "bmp", "tif", "tiff", "ico", "qoi",
+        ],
+    ) || (cfg!(feature = "heif") && has_extension(path, HEIF_EXTENSIONS))
+}
+
+const HEIF_EXTENSIONS: &[&str] = &["heic", "heif", "avif"];
+
+/// Converts `img` from the ICC profile embedded in `path` to the sRGB the
+/// textures are displayed as. Images without an RGB profile are returned as is.
+#[cfg(feature = "icc")]
+fn convert_to_srgb(path: &Path, img: DynamicImage) -> DynamicImage {
+    use image::{ImageDecoder, ImageReader};
+    use moxcms::{ColorProfile, DataColorSpace, Layout, TransformOptions};
+
+    // Only the headers are read again, to get at the profile
+    let icc = ImageReader::open(path)
+        .ok()
+        .and_then(|reader| reader.into_decoder().ok())
+        .and_then(|mut decoder| decoder.icc_profile().ok().flatten());
+    let Some(icc) = icc else {
+        return img;
+    };
+    let source = match ColorProfile::new_from_slice(&icc) {
+        Ok(profile) if profile.color_space == DataColorSpace::Rgb => profile,
+        Ok(_) => return img,
+        Err(e) => {
+            log::warn!(
+                "Ignoring unreadable ICC profile in {}: {:?}",
+                path.display(),
+                e
+            );
+            return img;
+        }
+    };
+    let transform = match source.create_transform_8bit(
+        Layout::Rgba,
+        &ColorProfile::new_srgb(),
+        Layout::Rgba,
+        TransformOptions::default(),
+    ) {
+        Ok(transform) => transform,
+        Err(e) => {
+            log::warn!("Can't convert {} to sRGB: {:?}", path.display(), e);
+            return img;
+        }
+    };
+
+    let rgba = img.to_rgba8();
+    let mut converted = RgbaImage::new(rgba.width(), rgba.height());
+    match transform.transform(&rgba, &mut converted) {
+        Ok(()) => DynamicImage::ImageRgba8(converted),
+        Err(e) => {
+            log::warn!("Can't convert {} to sRGB: {:?}", path.display(), e);
+            DynamicImage::ImageRgba8(rgba)
+        }
+    }
+}
+
+/// Decodes HEIC/AVIF through libheif. libheif already applies the container's
+/// rotation and mirroring, so the result needs no further orientation fix-up.
+#[cfg(feature = "heif")]
+fn decode_heif(path: &Path) -> Option<DynamicImage> {
+    use libheif_rs::{ColorSpace, HeifContext, LibHeif, RgbChroma};
+
+    if !has_extension(path, HEIF_EXTENSIONS) {
+        return None;
+    }
+
+    let lib_heif = LibHeif::new();
+    let ctx = HeifContext::read_from_file(path.to_str()?).ok()?;
+    let handle = ctx.primary_image_handle().ok()?;
+    let image = lib_heif
+        .decode(&handle, ColorSpace::Rgb(RgbChroma::Rgba), None)
+        .ok()?;
+
+    let plane = image.planes().interleaved?;
+    let row_bytes = plane.width as usize * 4;
+    let mut pixels = Vec::with_capacity(row_bytes * plane.height as usize);
+    for row in plane.data.chunks(plane.stride).take(plane.height as usize) {
+        pixels.extend_from_slice(&row[..row_bytes]);
+    }
+    RgbaImage::from_raw(plane.width, plane.height, pixels).map(DynamicImage::ImageRgba8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn write_png(path: &Path) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        RgbaImage::new(8, 8)
+            .write_to(&mut io::Cursor::new(&mut encoded), image::ImageFormat::Png)
+            .unwrap();
+        fs::write(path, &encoded).unwrap();
+        encoded
+    }
+
+    #[test]
+    fn file_outside_the_folder_list_is_not_in_folder() {
+        let dir = TempDir::new("load-not-in-folder");
+        let notes = dir.join("notes.txt");
+        fs::write(&notes, "not an image").unwrap();
+        let mut loader = ImageLoader::new(dir.path().to_path_buf());
+        assert!(matches!(
+            loader.open_image(&notes),
+            Err(LoadError::NotInFolder)
+        ));
+    }
+
+    #[test]
+    fn file_deleted_after_listing_is_an_io_error() {
+        let dir = TempDir::new("load-missing");
+        let path = dir.join("gone.png");
+        write_png(&path);
+        let loader = ImageLoader::new(dir.path().to_path_buf());
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            loader.try_load_image_at(0),
+            Err(LoadError::Io(e)) if e.kind() == io::ErrorKind::NotFound
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unreadable_file_is_a_permission_error() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new("load-unreadable");
+        let path = dir.join("locked.png");
+        write_png(&path);
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o000)).unwrap();
+        // Root reads the file regardless of its mode, so there is nothing to check
+        if File::open(&path).is_ok() {
+            return;
+        }
+        let mut loader = ImageLoader::new(dir.path().to_path_buf());
+        assert!(matches!(
+            loader.open_image(&path),
+            Err(LoadError::Io(e)) if e.kind() == io::ErrorKind::PermissionDenied
+        ));
+    }
+
+    #[test]
+    fn truncated_file_is_a_decode_error() {
+        let dir = TempDir::new("load-truncated");
+        let path = dir.join("cut.png");
+        let encoded = write_png(&path);
+        fs::write(&path, &encoded[..encoded.len() / 2]).unwrap();
+        let mut loader = ImageLoader::new(dir.path().to_path_buf());
+        let error = loader.open_image(&path).unwrap_err();
+        assert!(
+            matches!(&error, LoadError::Decode(e) if !matches!(e, image::ImageError::Unsupported(_))),
+            "{error:?}"
+        );
+        assert!(error.to_string().starts_with("corrupt image data"));
+    }
+
+    #[test]
+    fn unknown_format_is_unsupported() {
+        let dir = TempDir::new("load-unsupported");
+        let path = dir.join("drawing.xyz");
+        fs::write(&path, "not an image").unwrap();
+        let error = ImageLoader::try_load_dynamic_image_path(&path).unwrap_err();
+        assert!(
+            matches!(&error, LoadError::Decode(image::ImageError::Unsupported(_))),
+            "{error:?}"
+        );
+        assert!(error.to_string().starts_with("unsupported image"));
+    }
+
+    fn sorted(names: &[&str]) -> Vec<String> {
+        let mut names: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+        names.sort_by(|a, b| natural_cmp(a, b));
+        names
+    }
+
+    #[test]
+    fn digit_runs_sort_by_value() {
+        assert_eq!(
+            sorted(&["img10.png", "img2.png", "img1.png", "img100.png"]),
+            ["img1.png", "img2.png", "img10.png", "img100.png"]
+        );
+        assert_eq!(
+            sorted(&["a2b10", "a2b9", "a10b1"]),
+            ["a2b9", "a2b10", "a10b1"]
+        );
+    }
+
+    #[test]
+    fn natural_order_ignores_case_and_breaks_ties_on_leading_zeros() {
+        assert_eq!(natural_cmp("Photo.jpg", "photo.jpg"), Ordering::Equal);
+        assert_eq!(natural_cmp("apple", "Banana"), Ordering::Less);
+        assert_eq!(natural_cmp("img7", "img007"), Ordering::Less);
+        assert_eq!(natural_cmp("img007", "img8"), Ordering::Less);
+        assert_eq!(natural_cmp("img", "img1"), Ordering::Less);
+    }
+
+    #[test]
+    fn sort_modes_order_by_their_key_and_fall_back_to_names() {
+        let epoch = SystemTime::UNIX_EPOCH;
+        let files = [
+            ("b.png", Some(epoch + Duration::from_secs(20)), 300),
+            ("a.png", Some(epoch + Duration::from_secs(10)), 300),
+            ("c.png", None, 100),
+        ];
+        let info: HashMap<PathBuf, FileInfo> = files
+            .iter()
+            .map(|&(name, modified, size)| (PathBuf::from(name), FileInfo { modified, size }))
+            .collect();
+        let order = |mode| {
+            let mut paths: Vec<PathBuf> = info.keys().cloned().collect();
+            paths.sort_by(|a, b| compare_images(mode, &info, a, b));
+            paths
+                .iter()
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(order(SortMode::NameAsc), ["a.png", "b.png", "c.png"]);
+        assert_eq!(order(SortMode::NameDesc), ["c.png", "b.png", "a.png"]);
+        // A file without a modification time counts as the oldest
+        assert_eq!(order(SortMode::DateNewest), ["b.png", "a.png", "c.png"]);
+        assert_eq!(order(SortMode::DateOldest), ["c.png", "a.png", "b.png"]);
+        // a.png and b.png are the same size, so they stay in name order
+        assert_eq!(order(SortMode::SizeAsc), ["c.png", "a.png", "b.png"]);
+        assert_eq!(order(SortMode::SizeDesc), ["a.png", "b.png", "c.png"]);
+    }
+
+    #[test]
+    fn gif_frames_come_back_with_their_delays() {
+        use image::codecs::gif::GifEncoder;
+        use image::{Delay, Frame};
+
+        let dir = TempDir::new("gif-frames");
+        let path = dir.join("spinner.gif");
+        let frames = [(255, 50), (128, 250), (0, 0)].map(|(red, millis)| {
+            let pixels = RgbaImage::from_pixel(4, 4, image::Rgba([red, 0, 0, 255]));
+            Frame::from_parts(pixels, 0, 0, Delay::from_numer_denom_ms(millis, 1))
+        });
+        GifEncoder::new(File::create(&path).unwrap())
+            .encode_frames(frames)
+            .unwrap();
+
+        let loaded = ImageLoader::load_frames(&path);
+        let delays: Vec<Duration> = loaded.iter().map(|(_, delay)| *delay).collect();
+        // A zero delay, which browsers also speed up, plays at the default rate
+        assert_eq!(
+            delays,
+            [
+                Duration::from_millis(50),
+                Duration::from_millis(250),
+                DEFAULT_FRAME_DELAY
+            ]
+        );
+        assert_eq!(loaded[1].0.get_pixel(0, 0), &image::Rgba([128, 0, 0, 255]));
+    }
+
+    #[test]
+    fn current_path_follows_next_and_prev() {
+        let dir = TempDir::new("navigation");
+        // Widths tell the decoded images apart
+        for (name, width) in [("a.png", 1), ("b.png", 2), ("c.png", 3)] {
+            RgbaImage::new(width, 1).save(dir.join(name)).unwrap();
+        }
+        let mut loader = ImageLoader::new(dir.path().to_path_buf());
+        loader.set_sort_mode(SortMode::NameAsc);
+        let current = |loader: &ImageLoader| {
+            let name = loader.get_current_path().unwrap().file_name().unwrap();
+            let width = loader.try_load_current_image().unwrap().width();
+            (name.to_string_lossy().into_owned(), width)
+        };
+
+        assert_eq!(current(&loader), ("a.png".to_string(), 1));
+        loader.select_next();
+        loader.select_next();
+        assert_eq!(current(&loader), ("c.png".to_string(), 3));
+        // Both directions wrap around
+        loader.select_next();
+        assert_eq!(current(&loader), ("a.png".to_string(), 1));
+        loader.select_prev();
+        loader.select_prev();
+        assert_eq!(current(&loader), ("b.png".to_string(), 2));
+    }
+
+    /// Saves a 5x3 image as `name`, in the format its extension names, and loads it back.
+    fn save_and_load(dir: &TempDir, name: &str) -> RgbaImage {
+        let path = dir.join(name);
+        let img = RgbaImage::from_fn(5, 3, |x, y| {
+            image::Rgba([x as u8 * 50, y as u8 * 80, 0, 255])
+        });
+        img.save(&path).unwrap();
+        assert!(is_image_file(&path));
+        let loaded = ImageLoader::try_load_dynamic_image_path(&path)
+            .unwrap()
+            .to_rgba8();
+        assert_eq!(loaded, img, "{name}");
+        loaded
+    }
+
+    #[test]
+    fn bmp_tiff_and_ico_load_as_rgba() {
+        let dir = TempDir::new("formats");
+        for name in ["sample.bmp", "sample.tif", "sample.tiff", "sample.ico"] {
+            save_and_load(&dir, name);
+        }
+    }
+
+    #[test]
+    fn qoi_loads_as_rgba() {
+        let dir = TempDir::new("formats-qoi");
+        save_and_load(&dir, "sample.qoi");
+    }
+
+    /// An animated 4x4 WebP with one solid red frame per `(red, millis)`
+    fn animated_webp(frames: &[(u8, u32)]) -> Vec<u8> {
+        fn chunk(fourcc: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+            let mut chunk = [&fourcc[..], &(payload.len() as u32).to_le_bytes()].concat();
+            chunk.extend_from_slice(payload);
+            if payload.len() % 2 == 1 {
+                chunk.push(0);
+            }
+            chunk
+        }
+        let u24 = |v: u32| v.to_le_bytes()[..3].to_vec();
+
+        // Canvas 4x4 with animation and alpha, then a background color and endless looping
+        let mut body = b"WEBP".to_vec();
+        body.extend(chunk(
+            b"VP8X",
+            &[[0x12, 0, 0, 0].to_vec(), u24(3), u24(3)].concat(),
+        ));
+        body.extend(chunk(b"ANIM", &[0; 6]));
+        for &(red, millis) in frames {
+            let pixels = RgbaImage::from_pixel(4, 4, image::Rgba([red, 0, 0, 255]));
+            let mut still = Vec::new();
+            image::codecs::webp::WebPEncoder::new_lossless(&mut still)
+                .encode(&pixels, 4, 4, image::ExtendedColorType::Rgba8)
+                .unwrap();
+            assert_eq!(&still[12..16], b"VP8L");
+            // Placed at the origin, full size, replacing what was there
+            let header = [u24(0), u24(0), u24(3), u24(3), u24(millis), vec![0x02]].concat();
+            body.extend(chunk(b"ANMF", &[header, still[12..].to_vec()].concat()));
+        }
+        chunk(b"RIFF", &body)
+    }
+
+    #[test]
+    fn animated_webp_shows_its_first_frame_and_plays() {
+        let dir = TempDir::new("webp-frames");
+        let path = dir.join("spinner.webp");
+        std::fs::write(&path, animated_webp(&[(255, 80), (64, 120)])).unwrap();
+
+        let still = ImageLoader::try_load_dynamic_image_path(&path).unwrap();
+        assert_eq!(
+            still.to_rgba8().get_pixel(0, 0),
+            &image::Rgba([255, 0, 0, 255])
+        );
+        let frames = ImageLoader::load_frames(&path);
+        let delays: Vec<Duration> = frames.iter().map(|(_, delay)| *delay).collect();
+        assert_eq!(
+            delays,
+            [Duration::from_millis(80), Duration::from_millis(120)]
+        );
+        assert_eq!(frames[1].0.get_pixel(3, 3), &image::Rgba([64, 0, 0, 255]));
+    }
+
+    #[test]
+    fn still_webp_has_no_frames_to_play() {
+        let dir = TempDir::new("webp-still");
+        let path = dir.join("still.webp");
+        RgbaImage::new(4, 4).save(&path).unwrap();
+        assert!(ImageLoader::load_frames(&path).is_empty());
+    }
+
+    #[cfg(feature = "icc")]
+    #[test]
+    fn display_p3_pixels_are_converted_to_srgb() {
+        use img_parts::ImageICC;
+
+        let dir = TempDir::new("icc-p3");
+        let path = dir.join("p3.png");
+        // sRGB red as Display P3 encodes it, next to a neutral grey
+        let mut swatch = RgbaImage::from_pixel(2, 1, image::Rgba([128, 128, 128, 255]));
+        swatch.put_pixel(0, 0, image::Rgba([234, 51, 35, 255]));
+        let mut encoded = Vec::new();
+        swatch
+            .write_to(
+                &mut std::io::Cursor::new(&mut encoded),
+                image::ImageFormat::Png,
+            )
+            .unwrap();
+        let mut png = img_parts::png::Png::from_bytes(encoded.into()).unwrap();
+        let profile = moxcms::ColorProfile::new_display_p3().encode().unwrap();
+        png.set_icc_profile(Some(profile.into()));
+        png.encoder()
+            .write_to(File::create(&path).unwrap())
+            .unwrap();
+
+        let loaded = ImageLoader::try_load_dynamic_image_path(&path)
+            .unwrap()
+            .to_rgba8();
+        let close = |actual: &image::Rgba<u8>, expected: [u8; 4]| {
+            actual
+                .0
+                .iter()
+                .zip(expected)
+                .all(|(&a, e)| a.abs_diff(e) <= 2)
+        };
+        let red = loaded.get_pixel(0, 0);
+        assert!(close(red, [255, 0, 0, 255]), "{red:?}");
+        let grey = loaded.get_pixel(1, 0);
+        assert!(close(grey, [128, 128, 128, 255]), "{grey:?}");
+    }
+
+    #[test]
+    fn png_rotation_leaves_the_file_until_saved() {
+        let dir = TempDir::new("rotate-png");
+        let path = dir.join("wide.png");
+        RgbaImage::new(3, 1).save(&path).unwrap();
+        let original = fs::read(&path).unwrap();
+        let mut loader = ImageLoader::new(dir.path().to_path_buf());
+
+        assert!(!loader.rotate_current(1));
+        assert_eq!(fs::read(&path).unwrap(), original);
+        assert_eq!(
+            loader.try_load_current_image().unwrap().dimensions(),
+            (1, 3)
+        );
+
+        assert!(loader.save_current_rotation().unwrap());
+        assert_eq!(image::open(&path).unwrap().to_rgba8().dimensions(), (1, 3));
+        // The turn now lives in the file and isn't applied a second time
+        assert_eq!(
+            loader.try_load_current_image().unwrap().dimensions(),
+            (1, 3)
+        );
+        assert!(!loader.save_current_rotation().unwrap());
+    }
+
+    #[cfg(feature = "heif")]
+    #[test]
+    fn heic_decodes_through_libheif() {
+        use libheif_rs::{
+            Channel, ColorSpace, CompressionFormat, EncoderQuality, HeifContext, Image, LibHeif,
+            RgbChroma,
+        };
+
+        // A 64x32 in one orange, encoded losslessly so only YUV rounding shifts it
+        let (width, height, color) = (64, 32, [230u8, 120, 40]);
+        let mut image = Image::new(width, height, ColorSpace::Rgb(RgbChroma::Rgb)).unwrap();
+        image
+            .create_plane(Channel::Interleaved, width, height, 24)
+            .unwrap();
+        let plane = image.planes_mut().interleaved.unwrap();
+        for row in plane.data.chunks_mut(plane.stride).take(height as usize) {
+            for pixel in row[..width as usize * 3].chunks_exact_mut(3) {
+                pixel.copy_from_slice(&color);
+            }
+        }
+        let lib_heif = LibHeif::new();
+        let mut encoder = lib_heif
+            .encoder_for_format(CompressionFormat::Hevc)
+            .unwrap();
+        encoder.set_quality(EncoderQuality::LossLess).unwrap();
+        let mut context = HeifContext::new().unwrap();
+        context.encode_image(&image, &mut encoder, None).unwrap();
+
+        let dir = TempDir::new("heic");
+        let path = dir.join("photo.heic");
+        fs::write(&path, context.write_to_bytes().unwrap()).unwrap();
+        assert!(is_image_file(&path));
+
+        let loaded = ImageLoader::try_load_dynamic_image_path(&path)
+            .unwrap()
+            .to_rgba8();
+        assert_eq!(loaded.dimensions(), (width, height));
+        let pixel = loaded.get_pixel(width / 2, height / 2);
+        for (channel, expected) in pixel.0[..3].iter().zip(color) {
+            assert!(channel.abs_diff(expected) <= 4, "{pixel:?}");
+        }
+        assert_eq!(pixel[3], 255);
+    }
+}
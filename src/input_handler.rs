@@ -1,17 +1,40 @@
+use crate::keymap::Keymap;
+use std::time::{Duration, Instant};
 use winit::{
-    event::{MouseScrollDelta, WindowEvent},
-    keyboard::{Key, NamedKey},
+    event::{MouseScrollDelta, TouchPhase, WindowEvent},
+    keyboard::{Key, ModifiersState, NamedKey},
 };
 
+#[derive(Clone, Copy)]
 pub enum InputAction {
     None,
     NextImage,
     PrevImage,
-    Zoom(f32),
+    Zoom(f32, f64, f64),
+    // Ctrl+wheel: thumbnail size in grid, otherwise like Zoom
+    WheelResize(f32, f64, f64),
+    // Shift+wheel: a page per notch in grid, otherwise like Zoom
+    WheelPage(f32, f64, f64),
+    // Trackpad scroll in pixels (dx, dy); true once the fingers lift
+    TouchScroll(f32, f32, bool),
+    ZoomIn,
+    ZoomOut,
     Pan(f32, f32),
     Click(f64, f64),
+    DoubleClick(f64, f64),
+    // Ctrl+click: add the item to or remove it from the grid's multi-selection
+    ToggleSelectClick(f64, f64),
+    // Shift+click: multi-select every item from the anchor to this one
+    RangeSelectClick(f64, f64),
+    // Backspace: leaves compare, then single view; it never changes folder
     Back,
+    // Alt+Up: the parent folder's grid, from either view
+    ParentDir,
     ActualSize,
+    // 0: back to the fit zoom, centered
+    FitToWindow,
+    // Number key: an absolute zoom level, or None for the fit mode
+    ZoomPreset(Option<f32>),
     SelectUp,
     SelectDown,
     SelectLeft,
@@ -19,63 +42,352 @@ pub enum InputAction {
     OpenSelected,
     PageUp,
     PageDown,
+    First,
+    Last,
+    // Typed number then Enter: 1-based position in the grid or folder
+    GoTo(usize),
+    // Escape: quits from the grid; single view and compare treat it as Back
     Exit,
     ToggleMetadata,
+    ToggleFullscreen,
+    ToggleSlideshow,
+    CycleSortMode,
+    // F5: read the open image, or the folder listing in grid, from disk again
+    Reload,
+    Delete,
+    // Ctrl+M: selected images go to the sorting bin folder
+    MoveToSortBin,
+    Copy,
+    CopyPath,
+    RevealInExplorer,
+    // E: the open image in an external editor
+    OpenWith,
+    // Ctrl+B: the open image becomes the desktop background
+    SetWallpaper,
+    RotateCW,
+    RotateCCW,
+    // Ctrl+S: a rotation kept outside the file is written into it
+    SaveRotation,
+    ToggleSmoothMotion,
+    ToggleVsync,
+    CycleBackground,
+    ToggleCompare,
+    ToggleCompareSync,
+    CycleFitMode,
+    ToggleUpscale,
+    // Nearest-neighbour sampling at any zoom, not just actual size
+    ToggleFilter,
+    ToggleStatusBar,
+    // K: grid thumbnails keep their aspect ratio instead of filling the cell
+    ToggleThumbnailShape,
+}
+
+/// Maximum delay between two clicks for them to count as a double-click
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+/// Maximum cursor travel in pixels between the two clicks of a double-click
+const DOUBLE_CLICK_DISTANCE: f64 = 4.0;
+/// Cursor travel in pixels after which a press counts as a drag, not a click
+const CLICK_DRAG_DISTANCE: f64 = 5.0;
+/// Longest image number that can be typed for GoTo
+const MAX_PENDING_DIGITS: usize = 9;
+/// How long a lone digit waits for a second one before applying its zoom preset
+const DIGIT_PRESET_DELAY: Duration = Duration::from_millis(400);
+/// Trackpad pixels that count as one mouse wheel notch
+pub const PIXELS_PER_WHEEL_STEP: f32 = 20.0;
+
+/// Zoom for the number keys in single view, indexed by digit - 1; None fits
+/// the image with the current fit mode. 1 is the actual-size toggle, keeping
+/// its nearest-neighbour filtering. The rest double (2, 4, 8, 9) or halve
+/// (5, 6, 7) from there:
+/// 1: 100%, 2: 200%, 3: fit, 4: 400%, 5: 50%, 6: 25%, 7: 12.5%, 8: 800%, 9: 1600%
+const ZOOM_PRESETS: [Option<f32>; 9] = [
+    Some(1.0),
+    Some(2.0),
+    None,
+    Some(4.0),
+    Some(0.5),
+    Some(0.25),
+    Some(0.125),
+    Some(8.0),
+    Some(16.0),
+];
+
+/// The zoom action a lone digit stands for.
+fn digit_action(digit: &str) -> Option<InputAction> {
+    match digit {
+        "1" => Some(InputAction::ActualSize),
+        "0" => Some(InputAction::FitToWindow),
+        _ => digit
+            .parse::<usize>()
+            .ok()
+            .and_then(|digit| ZOOM_PRESETS.get(digit.checked_sub(1)?))
+            .map(|preset| InputAction::ZoomPreset(*preset)),
+    }
 }
 
 pub struct InputHandler {
     pub mouse_down: bool,
+    // Middle button, which only ever pans
+    pub middle_down: bool,
+    // Where the left button went down, until the drag passes CLICK_DRAG_DISTANCE
+    press_position: Option<(f64, f64)>,
     pub last_mouse_x: f64,
     pub last_mouse_y: f64,
+    pub modifiers: ModifiersState,
+    last_click: Option<(Instant, f64, f64)>,
+    // Digits typed so far for GoTo
+    pending_number: String,
+    // When the first digit was typed, so a lone one can time out into its zoom preset
+    pending_since: Option<Instant>,
+    // Filename search typed after '/'; its keys bypass the usual bindings
+    search: Option<String>,
+    keymap: Keymap,
 }
 
 impl InputHandler {
     pub fn new() -> Self {
         InputHandler {
             mouse_down: false,
+            middle_down: false,
+            press_position: None,
             last_mouse_x: 0.0,
             last_mouse_y: 0.0,
+            modifiers: ModifiersState::empty(),
+            last_click: None,
+            pending_number: String::new(),
+            pending_since: None,
+            search: None,
+            keymap: Keymap::load(),
+        }
+    }
+
+    /// The image number being typed, if any.
+    pub fn pending_number(&self) -> Option<&str> {
+        Some(self.pending_number.as_str()).filter(|n| !n.is_empty())
+    }
+
+    /// When a lone pending digit turns into its zoom preset, if one is waiting.
+    pub fn digit_deadline(&self) -> Option<Instant> {
+        self.pending_since
+            .filter(|_| self.pending_number.len() == 1)
+            .map(|since| since + DIGIT_PRESET_DELAY)
+    }
+
+    /// The zoom preset of a lone digit that nothing followed in time, clearing it.
+    pub fn take_expired_digit(&mut self, now: Instant) -> Option<InputAction> {
+        if self.digit_deadline()? > now {
+            return None;
+        }
+        let action = digit_action(&self.pending_number);
+        self.pending_number.clear();
+        self.pending_since = None;
+        action
+    }
+
+    /// The filename search being typed, if search is active.
+    pub fn search_query(&self) -> Option<&str> {
+        self.search.as_deref()
+    }
+
+    pub fn end_search(&mut self) {
+        self.search = None;
+    }
+
+    /// Forgets the pending first click, so the next click starts a new pair.
+    pub fn cancel_double_click(&mut self) {
+        self.last_click = None;
+    }
+
+    fn register_click(&mut self, x: f64, y: f64) -> InputAction {
+        // Selection clicks never pair up into a double-click
+        if self.modifiers.control_key() || self.modifiers.super_key() {
+            self.last_click = None;
+            return InputAction::ToggleSelectClick(x, y);
+        }
+        if self.modifiers.shift_key() {
+            self.last_click = None;
+            return InputAction::RangeSelectClick(x, y);
+        }
+
+        let now = Instant::now();
+        let is_double = self.last_click.is_some_and(|(at, px, py)| {
+            now.duration_since(at) <= DOUBLE_CLICK_INTERVAL
+                && (x - px).hypot(y - py) <= DOUBLE_CLICK_DISTANCE
+        });
+
+        if is_double {
+            self.last_click = None;
+            InputAction::DoubleClick(x, y)
+        } else {
+            self.last_click = Some((now, x, y));
+            InputAction::Click(x, y)
         }
     }
 
     pub fn handle_window_event(&mut self, event: &WindowEvent) -> InputAction {
         match event {
-            WindowEvent::KeyboardInput { event, .. } => {
-                if event.state == winit::event::ElementState::Pressed {
-                    return self.handle_keyboard_input(event);
-                }
+            WindowEvent::KeyboardInput { event, .. }
+                if event.state == winit::event::ElementState::Pressed =>
+            {
+                return self.handle_keyboard_input(event);
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
             }
-            WindowEvent::MouseWheel { delta, .. } => {
-                let amount = match delta {
-                    MouseScrollDelta::LineDelta(_, y) => *y,
-                    MouseScrollDelta::PixelDelta(pos) => pos.y as f32 / 20.0,
+            WindowEvent::MouseWheel { delta, phase, .. } => {
+                let (amount_x, amount) = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => (*x, *y),
+                    MouseScrollDelta::PixelDelta(pos) if self.modifiers.is_empty() => {
+                        let ended = matches!(phase, TouchPhase::Ended | TouchPhase::Cancelled);
+                        return InputAction::TouchScroll(pos.x as f32, pos.y as f32, ended);
+                    }
+                    MouseScrollDelta::PixelDelta(pos) => (
+                        pos.x as f32 / PIXELS_PER_WHEEL_STEP,
+                        pos.y as f32 / PIXELS_PER_WHEEL_STEP,
+                    ),
                 };
-                return InputAction::Zoom(amount);
+                let (x, y) = (self.last_mouse_x, self.last_mouse_y);
+                if self.modifiers.control_key() {
+                    return InputAction::WheelResize(amount, x, y);
+                }
+                if self.modifiers.shift_key() {
+                    // Some platforms turn Shift+wheel into horizontal scrolling
+                    let amount = if amount != 0.0 { amount } else { amount_x };
+                    return InputAction::WheelPage(amount, x, y);
+                }
+                return InputAction::Zoom(amount, x, y);
             }
             WindowEvent::CursorMoved { position, .. } => {
                 let dx = (position.x - self.last_mouse_x) as f32;
                 let dy = (position.y - self.last_mouse_y) as f32;
                 self.last_mouse_x = position.x;
                 self.last_mouse_y = position.y;
-                if self.mouse_down {
+                if let Some((px, py)) = self.press_position {
+                    if (position.x - px).hypot(position.y - py) > CLICK_DRAG_DISTANCE {
+                        self.press_position = None;
+                    }
+                }
+                if self.mouse_down || self.middle_down {
                     return InputAction::Pan(dx, dy);
                 }
             }
-            WindowEvent::MouseInput { state, button, .. } => {
-                if *button == winit::event::MouseButton::Left {
-                    self.mouse_down = *state == winit::event::ElementState::Pressed;
-                    if !self.mouse_down {
-                        // Click on release
-                        return InputAction::Click(self.last_mouse_x, self.last_mouse_y);
-                    }
+            WindowEvent::MouseInput { state, button, .. }
+                if *button == winit::event::MouseButton::Left =>
+            {
+                self.mouse_down = *state == winit::event::ElementState::Pressed;
+                if self.mouse_down {
+                    self.press_position = Some((self.last_mouse_x, self.last_mouse_y));
+                } else if self.press_position.take().is_some() {
+                    // Click on release, unless the press turned into a drag
+                    return self.register_click(self.last_mouse_x, self.last_mouse_y);
+                } else {
+                    self.cancel_double_click();
                 }
             }
+            WindowEvent::MouseInput { state, button, .. }
+                if *button == winit::event::MouseButton::Middle =>
+            {
+                self.middle_down = *state == winit::event::ElementState::Pressed;
+            }
             _ => {}
         }
         InputAction::None
     }
 
+    /// While searching, text edits the query, Enter opens the selection and
+    /// Escape ends the search. Other keys such as arrows keep working.
+    fn handle_search_input(&mut self, key: &Key) -> Option<InputAction> {
+        let query = self.search.as_mut()?;
+        match key {
+            Key::Character(c) => query.push_str(c),
+            Key::Named(NamedKey::Space) => query.push(' '),
+            Key::Named(NamedKey::Backspace) => {
+                query.pop();
+            }
+            Key::Named(NamedKey::Escape) => self.search = None,
+            Key::Named(NamedKey::Enter) => {
+                self.search = None;
+                return Some(InputAction::OpenSelected);
+            }
+            _ => return None,
+        }
+        Some(InputAction::None)
+    }
+
+    /// Digits build up an image number that Enter jumps to; Backspace edits it
+    /// and Escape or any other key drops it. A lone digit becomes its zoom preset
+    /// only once DIGIT_PRESET_DELAY passes without a second digit or Enter.
+    fn handle_number_input(&mut self, key: &Key) -> Option<InputAction> {
+        match key {
+            Key::Character(c) if c.chars().all(|d| d.is_ascii_digit()) => {
+                if self.pending_number.len() < MAX_PENDING_DIGITS {
+                    self.pending_number.push_str(c);
+                }
+                self.pending_since = (self.pending_number.len() == 1).then(Instant::now);
+                Some(InputAction::None)
+            }
+            Key::Named(NamedKey::Enter) if !self.pending_number.is_empty() => {
+                let number = self.pending_number.parse().unwrap_or(usize::MAX);
+                self.pending_number.clear();
+                Some(InputAction::GoTo(number))
+            }
+            Key::Named(NamedKey::Backspace) if !self.pending_number.is_empty() => {
+                self.pending_number.pop();
+                Some(InputAction::None)
+            }
+            Key::Named(NamedKey::Escape) if !self.pending_number.is_empty() => {
+                self.pending_number.clear();
+                Some(InputAction::None)
+            }
+            // Holding a modifier doesn't interrupt typing
+            Key::Named(NamedKey::Shift | NamedKey::Control | NamedKey::Alt | NamedKey::Super) => {
+                None
+            }
+            _ => {
+                self.pending_number.clear();
+                None
+            }
+        }
+    }
+
     fn handle_keyboard_input(&mut self, event: &winit::event::KeyEvent) -> InputAction {
+        // Cmd on macOS, Ctrl elsewhere
+        let command = self.modifiers.control_key() || self.modifiers.super_key();
+        if command {
+            if let Key::Character(c) = &event.logical_key {
+                if c.eq_ignore_ascii_case("c") {
+                    if self.modifiers.shift_key() {
+                        return InputAction::CopyPath;
+                    }
+                    return InputAction::Copy;
+                }
+                if c.eq_ignore_ascii_case("e") {
+                    return InputAction::RevealInExplorer;
+                }
+                if c.eq_ignore_ascii_case("b") {
+                    return InputAction::SetWallpaper;
+                }
+                if c.eq_ignore_ascii_case("m") {
+                    return InputAction::MoveToSortBin;
+                }
+                if c.eq_ignore_ascii_case("s") {
+                    return InputAction::SaveRotation;
+                }
+            }
+        } else if let Some(action) = self.handle_search_input(&event.logical_key) {
+            return action;
+        } else if self.modifiers.alt_key() && event.logical_key == Key::Named(NamedKey::ArrowUp) {
+            self.pending_number.clear();
+            return InputAction::ParentDir;
+        } else if let Some(action) = self.keymap.lookup(&event.logical_key) {
+            self.pending_number.clear();
+            return action;
+        }
+
+        if let Some(action) = self.handle_number_input(&event.logical_key) {
+            return action;
+        }
+
         match &event.logical_key {
             Key::Named(key) => match key {
                 NamedKey::ArrowRight => return InputAction::SelectRight,
@@ -89,18 +401,158 @@ impl InputHandler {
                 NamedKey::Enter => return InputAction::OpenSelected,
                 NamedKey::PageUp => return InputAction::PageUp,
                 NamedKey::PageDown => return InputAction::PageDown,
+                NamedKey::Home => return InputAction::First,
+                NamedKey::End => return InputAction::Last,
+                NamedKey::F5 => return InputAction::Reload,
+                NamedKey::F11 => return InputAction::ToggleFullscreen,
+                NamedKey::Space => return InputAction::ToggleSlideshow,
+                NamedKey::Delete => return InputAction::Delete,
                 _ => {}
             },
             Key::Character(c) => {
-                if c == "1" {
-                    return InputAction::ActualSize;
-                }
-                if c == "m" || c == "M" {
+                if c == "m" || c == "M" || c == "i" || c == "I" {
                     return InputAction::ToggleMetadata;
                 }
+                if c == "]" {
+                    return InputAction::RotateCW;
+                }
+                if c == "[" {
+                    return InputAction::RotateCCW;
+                }
+                if c == "a" || c == "A" {
+                    return InputAction::ToggleSmoothMotion;
+                }
+                if c == "u" || c == "U" {
+                    return InputAction::ToggleUpscale;
+                }
+                if c == "e" || c == "E" {
+                    return InputAction::OpenWith;
+                }
+                if c == "n" || c == "N" {
+                    return InputAction::ToggleFilter;
+                }
+                if c == "t" || c == "T" {
+                    return InputAction::ToggleStatusBar;
+                }
+                if c == "f" || c == "F" {
+                    return InputAction::CycleFitMode;
+                }
+                if c == "k" || c == "K" {
+                    return InputAction::ToggleThumbnailShape;
+                }
+                if c == "c" || c == "C" {
+                    return InputAction::ToggleCompare;
+                }
+                if c == "l" || c == "L" {
+                    return InputAction::ToggleCompareSync;
+                }
+                if c == "b" || c == "B" {
+                    return InputAction::CycleBackground;
+                }
+                if c == "v" || c == "V" {
+                    return InputAction::ToggleVsync;
+                }
+                if c == "s" || c == "S" {
+                    return InputAction::CycleSortMode;
+                }
+                if c == "+" || c == "=" {
+                    return InputAction::ZoomIn;
+                }
+                if c == "-" {
+                    return InputAction::ZoomOut;
+                }
+                if c == "/" {
+                    self.search = Some(String::new());
+                    return InputAction::None;
+                }
             }
             _ => {}
         }
         InputAction::None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use winit::dpi::PhysicalPosition;
+    use winit::event::{DeviceId, ElementState, MouseButton};
+
+    fn left_button(state: ElementState) -> WindowEvent {
+        WindowEvent::MouseInput {
+            device_id: DeviceId::dummy(),
+            state,
+            button: MouseButton::Left,
+        }
+    }
+
+    fn cursor_at(x: f64, y: f64) -> WindowEvent {
+        WindowEvent::CursorMoved {
+            device_id: DeviceId::dummy(),
+            position: PhysicalPosition::new(x, y),
+        }
+    }
+
+    /// Presses at (10, 10), moves to `(x, y)` and releases, returning the release's action.
+    fn press_move_release(x: f64, y: f64) -> InputAction {
+        let mut input = InputHandler::new();
+        input.handle_window_event(&cursor_at(10.0, 10.0));
+        input.handle_window_event(&left_button(ElementState::Pressed));
+        assert!(matches!(
+            input.handle_window_event(&cursor_at(x, y)),
+            InputAction::Pan(..)
+        ));
+        input.handle_window_event(&left_button(ElementState::Released))
+    }
+
+    #[test]
+    fn release_after_a_drag_is_not_a_click() {
+        let action = press_move_release(10.0 + CLICK_DRAG_DISTANCE + 1.0, 10.0);
+        assert!(matches!(action, InputAction::None));
+    }
+
+    #[test]
+    fn release_after_a_small_wobble_is_still_a_click() {
+        let action = press_move_release(12.0, 11.0);
+        assert!(matches!(action, InputAction::Click(x, y) if x == 12.0 && y == 11.0));
+    }
+
+    fn type_digit(input: &mut InputHandler, digit: &str) -> Option<InputAction> {
+        input.handle_number_input(&Key::Character(digit.into()))
+    }
+
+    #[test]
+    fn typing_a_number_never_applies_a_zoom_preset() {
+        let mut input = InputHandler::new();
+        for digit in ["2", "5", "0"] {
+            assert!(matches!(
+                type_digit(&mut input, digit),
+                Some(InputAction::None)
+            ));
+        }
+        let later = Instant::now() + DIGIT_PRESET_DELAY * 2;
+        assert!(input.take_expired_digit(later).is_none());
+        assert!(matches!(
+            input.handle_number_input(&Key::Named(NamedKey::Enter)),
+            Some(InputAction::GoTo(250))
+        ));
+    }
+
+    #[test]
+    fn lone_digit_applies_its_zoom_preset_after_the_delay() {
+        let mut input = InputHandler::new();
+        assert!(matches!(
+            type_digit(&mut input, "2"),
+            Some(InputAction::None)
+        ));
+        assert!(input.take_expired_digit(Instant::now()).is_none());
+
+        let deadline = input.digit_deadline().unwrap();
+        assert!(matches!(
+            input.take_expired_digit(deadline),
+            Some(InputAction::ZoomPreset(Some(zoom))) if zoom == 2.0
+        ));
+        assert!(input.pending_number().is_none());
+        assert!(input.digit_deadline().is_none());
+    }
+}
@@ -0,0 +1,118 @@
+use interprocess::local_socket::NameTypeSupport;
+use std::path::PathBuf;
+
+/// A request from a second instance to the running one.
+///
+/// On the wire each command is one line: `OPEN <absolute path>` or `FOCUS`.
+/// A message without any recognised verb is a bare path, as sent by older builds.
+#[derive(Debug, PartialEq)]
+pub enum IpcCommand {
+    Open(PathBuf),
+    Focus,
+}
+
+/// Per-user name, so instances of different users on one machine stay apart.
+pub fn socket_name() -> String {
+    let name = format!("fastview_ipc_{}", user_id());
+    if NameTypeSupport::query().paths_supported() {
+        // Prefer the private per-user runtime dir over the shared /tmp
+        let dir = std::env::var("XDG_RUNTIME_DIR")
+            .ok()
+            .filter(|dir| !dir.is_empty())
+            .unwrap_or_else(|| "/tmp".to_string());
+        format!("{}/{}.sock", dir.trim_end_matches('/'), name)
+    } else {
+        name
+    }
+}
+
+#[cfg(unix)]
+fn user_id() -> String {
+    // SAFETY: getuid has no preconditions and cannot fail
+    unsafe { libc::getuid() }.to_string()
+}
+
+#[cfg(not(unix))]
+fn user_id() -> String {
+    std::env::var("USERNAME")
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect()
+}
+
+pub fn encode(commands: &[IpcCommand]) -> String {
+    let mut message = String::new();
+    for command in commands {
+        match command {
+            IpcCommand::Open(path) => {
+                message.push_str("OPEN ");
+                message.push_str(&path.to_string_lossy());
+            }
+            IpcCommand::Focus => message.push_str("FOCUS"),
+        }
+        message.push('\n');
+    }
+    message
+}
+
+pub fn decode(message: &str) -> Vec<IpcCommand> {
+    let is_verb = |line: &str| line.starts_with("OPEN ") || line == "FOCUS";
+    if !message.lines().any(is_verb) {
+        let path = message.trim();
+        return if path.is_empty() {
+            Vec::new()
+        } else {
+            vec![IpcCommand::Open(PathBuf::from(path))]
+        };
+    }
+
+    message
+        .lines()
+        .filter_map(|line| {
+            if line == "FOCUS" {
+                Some(IpcCommand::Focus)
+            } else {
+                line.strip_prefix("OPEN ")
+                    .map(|path| IpcCommand::Open(PathBuf::from(path)))
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_survive_a_round_trip() {
+        let commands = vec![
+            IpcCommand::Open(PathBuf::from("/photos/summer trip/IMG 0001.jpg")),
+            IpcCommand::Open(PathBuf::from("/photos/b.png")),
+            IpcCommand::Focus,
+        ];
+        let message = encode(&commands);
+        assert_eq!(
+            message,
+            "OPEN /photos/summer trip/IMG 0001.jpg\nOPEN /photos/b.png\nFOCUS\n"
+        );
+        assert_eq!(decode(&message), commands);
+    }
+
+    #[test]
+    fn bare_path_from_an_older_build_is_an_open() {
+        assert_eq!(
+            decode("/photos/a.jpg\n"),
+            [IpcCommand::Open(PathBuf::from("/photos/a.jpg"))]
+        );
+        assert_eq!(decode("  \n"), []);
+    }
+
+    #[test]
+    fn unknown_lines_are_skipped() {
+        assert_eq!(
+            decode("FOCUS\nCLOSE everything\nOPEN /a.png"),
+            [IpcCommand::Focus, IpcCommand::Open(PathBuf::from("/a.png"))]
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use crate::cache_manager::app_dir;
+use crate::input_handler::InputAction;
+use std::collections::HashMap;
+use winit::keyboard::Key;
+
+const KEYMAP_FILE: &str = "keybindings.json";
+
+/// User key overrides, consulted before the built-in bindings.
+///
+/// `keybindings.json` next to the cache maps key names to action names, e.g.
+/// `{ "j": "NextImage", "k": "PrevImage", "F5": "None" }`. Characters are
+/// matched literally (so `"J"` means Shift+j); named keys use winit's names
+/// such as `"ArrowLeft"` or `"PageDown"`. `"None"` unbinds a default key.
+#[derive(Default)]
+pub struct Keymap {
+    bindings: HashMap<String, InputAction>,
+}
+
+impl Keymap {
+    pub fn load() -> Self {
+        let path = app_dir().join(KEYMAP_FILE);
+        let Ok(text) = std::fs::read_to_string(&path) else {
+            return Self::default();
+        };
+
+        let entries: HashMap<String, String> = match serde_json::from_str(&text) {
+            Ok(entries) => entries,
+            Err(e) => {
+                log::warn!("Ignoring {}: {}", path.display(), e);
+                return Self::default();
+            }
+        };
+
+        let mut bindings = HashMap::new();
+        for (key, action_name) in entries {
+            match action_from_name(&action_name) {
+                Some(action) => {
+                    bindings.insert(key, action);
+                }
+                None => log::warn!(
+                    "Unknown action {:?} for key {:?} in {}",
+                    action_name,
+                    key,
+                    path.display()
+                ),
+            }
+        }
+        Keymap { bindings }
+    }
+
+    pub fn lookup(&self, key: &Key) -> Option<InputAction> {
+        let name = match key {
+            Key::Character(c) => c.to_string(),
+            Key::Named(named) => format!("{:?}", named),
+            _ => return None,
+        };
+        self.bindings.get(&name).copied()
+    }
+}
+
+/// Actions that can be bound to a key; those carrying positions are mouse-only.
+fn action_from_name(name: &str) -> Option<InputAction> {
+    let action = match name {
+        "None" => InputAction::None,
+        "NextImage" => InputAction::NextImage,
+        "PrevImage" => InputAction::PrevImage,
+        "ZoomIn" => InputAction::ZoomIn,
+        "ZoomOut" => InputAction::ZoomOut,
+        "Back" => InputAction::Back,
+        "ParentDir" => InputAction::ParentDir,
+        "ActualSize" => InputAction::ActualSize,
+        "FitToWindow" => InputAction::FitToWindow,
+        "SelectUp" => InputAction::SelectUp,
+        "SelectDown" => InputAction::SelectDown,
+        "SelectLeft" => InputAction::SelectLeft,
+        "SelectRight" => InputAction::SelectRight,
+        "OpenSelected" => InputAction::OpenSelected,
+        "PageUp" => InputAction::PageUp,
+        "PageDown" => InputAction::PageDown,
+        "First" => InputAction::First,
+        "Last" => InputAction::Last,
+        "Exit" => InputAction::Exit,
+        "ToggleMetadata" => InputAction::ToggleMetadata,
+        "ToggleFullscreen" => InputAction::ToggleFullscreen,
+        "ToggleSlideshow" => InputAction::ToggleSlideshow,
+        "CycleSortMode" => InputAction::CycleSortMode,
+        "Reload" => InputAction::Reload,
+        "Delete" => InputAction::Delete,
+        "MoveToSortBin" => InputAction::MoveToSortBin,
+        "Copy" => InputAction::Copy,
+        "CopyPath" => InputAction::CopyPath,
+        "RevealInExplorer" => InputAction::RevealInExplorer,
+        "OpenWith" => InputAction::OpenWith,
+        "SetWallpaper" => InputAction::SetWallpaper,
+        "RotateCW" => InputAction::RotateCW,
+        "RotateCCW" => InputAction::RotateCCW,
+        "SaveRotation" => InputAction::SaveRotation,
+        "ToggleSmoothMotion" => InputAction::ToggleSmoothMotion,
+        "ToggleVsync" => InputAction::ToggleVsync,
+        "CycleBackground" => InputAction::CycleBackground,
+        "ToggleCompare" => InputAction::ToggleCompare,
+        "ToggleCompareSync" => InputAction::ToggleCompareSync,
+        "CycleFitMode" => InputAction::CycleFitMode,
+        "ToggleUpscale" => InputAction::ToggleUpscale,
+        "ToggleFilter" => InputAction::ToggleFilter,
+        "ToggleStatusBar" => InputAction::ToggleStatusBar,
+        "ToggleThumbnailShape" => InputAction::ToggleThumbnailShape,
+        _ => return None,
+    };
+    Some(action)
+}
@@ -0,0 +1,740 @@
+use crate::cache_manager::CacheManager;
+use crate::font_loader;
+use crate::image_loader::ImageLoader;
+use crate::metadata::{apply_orientation, read_exif_thumbnail, ExifOrientation};
+use crate::prefetch::PrefetchCache;
+use crate::profile;
+use ab_glyph::{FontArc, PxScale};
+use crossbeam_channel::{select, Receiver, Sender};
+use image::imageops::FilterType;
+use image::{DynamicImage, Rgba, Rgba32FImage, RgbaImage};
+use imageproc::drawing::{
+    draw_filled_rect_mut, draw_hollow_rect_mut, draw_line_segment_mut, draw_text_mut,
+};
+use imageproc::rect::Rect;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+/// Edge of the square box grid thumbnails are generated for
+pub const THUMBNAIL_SIZE: u32 = 256;
+
+/// Smallest embedded EXIF preview worth using instead of decoding the full image;
+/// a little upscaling is cheaper than a full-resolution decode
+const MIN_EMBEDDED_THUMBNAIL: u32 = THUMBNAIL_SIZE * 3 / 4;
+
+/// Images previewed, two by two, in a folder's thumbnail
+const MONTAGE_IMAGES: usize = 4;
+/// Folder colour left around and between the montage tiles, in pixels
+const MONTAGE_GAP: u32 = 6;
+
+/// Upper bound on decoder threads, so huge core counts don't starve the UI of memory bandwidth
+const MAX_WORKERS: usize = 8;
+
+/// How a thumbnail fills its square grid cell.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ThumbnailShape {
+    /// Center-cropped to the square
+    Crop,
+    /// The whole image scaled to fit, letterboxed by the grid
+    Fit,
+}
+
+impl ThumbnailShape {
+    /// `FASTVIEW_THUMBNAILS=fit` starts the grid with fitted thumbnails.
+    pub fn from_env() -> Self {
+        let choice = std::env::var("FASTVIEW_THUMBNAILS")
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+        match choice.as_str() {
+            "fit" => ThumbnailShape::Fit,
+            "crop" | "" => ThumbnailShape::Crop,
+            other => {
+                log::warn!("Unknown FASTVIEW_THUMBNAILS={:?}", other);
+                ThumbnailShape::Crop
+            }
+        }
+    }
+
+    pub fn toggled(self) -> Self {
+        match self {
+            ThumbnailShape::Crop => ThumbnailShape::Fit,
+            ThumbnailShape::Fit => ThumbnailShape::Crop,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ThumbnailShape::Crop => "cropped to square",
+            ThumbnailShape::Fit => "whole image",
+        }
+    }
+}
+
+pub struct LoaderRequest {
+    pub path: PathBuf,
+    pub index: usize,
+    pub is_directory: bool,
+    pub shape: ThumbnailShape,
+    // Grid generation this request belongs to; indices are only valid within it
+    pub generation: u64,
+}
+
+pub struct LoaderResponse {
+    pub index: usize,
+    // An error card when `failed`; None only if even that could not be drawn
+    pub image: Option<RgbaImage>,
+    pub failed: bool,
+    pub generation: u64,
+}
+
+/// Work shared by all loader threads.
+#[derive(Default)]
+struct LoaderQueue {
+    pending: Vec<LoaderRequest>,
+    visible: Vec<usize>,
+    prefetch: Vec<PathBuf>,
+}
+
+enum Job {
+    Prefetch(PathBuf),
+    Thumbnail(LoaderRequest),
+}
+
+#[derive(Clone)]
+pub struct LoaderChannels {
+    pub requests: Receiver<Vec<LoaderRequest>>,
+    pub visible_indices: Receiver<Vec<usize>>,
+    pub prefetch: Receiver<Vec<PathBuf>>,
+    pub responses: Sender<LoaderResponse>,
+    // Called after each response so the UI wakes up to collect it
+    pub notify: Arc<dyn Fn() + Send + Sync>,
+}
+
+#[derive(Clone)]
+struct LoaderWorker {
+    queue: Arc<Mutex<LoaderQueue>>,
+    // Signalled whenever the queue receives something new
+    work_ready: Arc<Condvar>,
+    responses: Sender<LoaderResponse>,
+    notify: Arc<dyn Fn() + Send + Sync>,
+    generation: Arc<AtomicU64>,
+    cache: CacheManager,
+    prefetch: PrefetchCache,
+    thumbnail_filter: FilterType,
+}
+
+/// Downscale filter for new thumbnails. `FASTVIEW_THUMBNAIL_FILTER=lanczos`
+/// gives sharper ones at several times the cost of the default triangle filter.
+fn thumbnail_filter() -> FilterType {
+    filter_named(&std::env::var("FASTVIEW_THUMBNAIL_FILTER").unwrap_or_default())
+}
+
+fn filter_named(name: &str) -> FilterType {
+    match name.trim().to_lowercase().as_str() {
+        "lanczos" | "lanczos3" => FilterType::Lanczos3,
+        "triangle" | "" => FilterType::Triangle,
+        other => {
+            log::warn!("Unknown FASTVIEW_THUMBNAIL_FILTER={:?}", other);
+            FilterType::Triangle
+        }
+    }
+}
+
+/// Starts one loader thread per core (capped), all pulling from one queue,
+/// plus a thread that blocks on the channels and fills that queue.
+/// Requests older than `generation` are dropped undecoded.
+pub fn spawn_workers(
+    channels: LoaderChannels,
+    generation: Arc<AtomicU64>,
+    cache: CacheManager,
+    prefetch: PrefetchCache,
+) {
+    let count = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(MAX_WORKERS);
+    let worker = LoaderWorker {
+        queue: Arc::new(Mutex::new(LoaderQueue::default())),
+        work_ready: Arc::new(Condvar::new()),
+        responses: channels.responses.clone(),
+        notify: Arc::clone(&channels.notify),
+        generation,
+        cache,
+        prefetch,
+        thumbnail_filter: thumbnail_filter(),
+    };
+    for _ in 0..count {
+        let worker = worker.clone();
+        thread::spawn(move || worker.run());
+    }
+    thread::spawn(move || receive(channels, worker.queue, worker.work_ready));
+}
+
+/// Moves incoming messages into the shared queue, sleeping until one arrives.
+/// Returns once the UI side hangs up.
+fn receive(channels: LoaderChannels, queue: Arc<Mutex<LoaderQueue>>, work_ready: Arc<Condvar>) {
+    loop {
+        select! {
+            recv(channels.requests) -> requests => {
+                let Ok(mut requests) = requests else { return };
+                let Ok(mut queue) = queue.lock() else { return };
+                queue.pending.append(&mut requests);
+            }
+            recv(channels.visible_indices) -> visible => {
+                let Ok(visible) = visible else { return };
+                let Ok(mut queue) = queue.lock() else { return };
+                queue.visible = visible;
+            }
+            // Only the latest neighbours matter
+            recv(channels.prefetch) -> paths => {
+                let Ok(paths) = paths else { return };
+                let Ok(mut queue) = queue.lock() else { return };
+                queue.prefetch = paths;
+            }
+        }
+        work_ready.notify_all();
+    }
+}
+
+impl LoaderWorker {
+    fn run(self) {
+        let mut font: Option<FontArc> = None;
+        while let Some(job) = self.next_job() {
+            match job {
+                Job::Prefetch(path) => {
+                    if !self.prefetch.contains(&path) {
+                        match ImageLoader::load_dynamic_image_path_with_metadata(&path) {
+                            Some(img) => self.prefetch.insert(path, img.to_rgba8()),
+                            None => self.prefetch.mark_failed(path),
+                        }
+                        // Single view may be showing a thumbnail until this is ready
+                        (self.notify)();
+                    }
+                }
+                Job::Thumbnail(request) => {
+                    // Lazy load font on first use
+                    if font.is_none() {
+                        font = font_loader::load_font();
+                    }
+                    let thumb = profile::time("Thumbnail for", &request.path, || {
+                        self.load_thumbnail(&request, font.as_ref())
+                    });
+                    let failed = thumb.is_none();
+                    let image = thumb.or_else(|| {
+                        let name = request.path.file_name()?.to_string_lossy();
+                        Some(draw_error_card(
+                            THUMBNAIL_SIZE,
+                            THUMBNAIL_SIZE,
+                            &name,
+                            font.as_ref(),
+                        ))
+                    });
+                    // The folder may have changed while this was decoding
+                    if self.is_current(&request) {
+                        let _ = self.responses.send(LoaderResponse {
+                            index: request.index,
+                            image,
+                            failed,
+                            generation: request.generation,
+                        });
+                        (self.notify)();
+                    }
+                }
+            }
+        }
+    }
+
+    fn is_current(&self, request: &LoaderRequest) -> bool {
+        request.generation == self.generation.load(Ordering::Relaxed)
+    }
+
+    /// Blocks until there is work; None only if another loader thread panicked.
+    fn next_job(&self) -> Option<Job> {
+        let mut queue = self.queue.lock().ok()?;
+        loop {
+            if let Some(job) = self.take_job(&mut queue) {
+                return Some(job);
+            }
+            queue = self.work_ready.wait(queue).ok()?;
+        }
+    }
+
+    fn take_job(&self, queue: &mut LoaderQueue) -> Option<Job> {
+        // Neighbours of the open image go ahead of thumbnails
+        if !queue.prefetch.is_empty() {
+            return Some(Job::Prefetch(queue.prefetch.remove(0)));
+        }
+
+        queue.pending.retain(|request| self.is_current(request));
+        if queue.pending.is_empty() {
+            return None;
+        }
+
+        // Re-prioritize: items in visible_indices first
+        let LoaderQueue {
+            pending, visible, ..
+        } = queue;
+        pending.sort_by(|a, b| {
+            let a_visible = visible.contains(&a.index);
+            let b_visible = visible.contains(&b.index);
+            match (a_visible, b_visible) {
+                (true, false) => std::cmp::Ordering::Less,
+                (false, true) => std::cmp::Ordering::Greater,
+                _ => a.index.cmp(&b.index),
+            }
+        });
+        Some(Job::Thumbnail(pending.remove(0)))
+    }
+
+    fn load_thumbnail(&self, request: &LoaderRequest, font: Option<&FontArc>) -> Option<RgbaImage> {
+        if !request.is_directory {
+            let cached = self
+                .cache
+                .get_thumbnail(&request.path, THUMBNAIL_SIZE, request.shape);
+            profile::cache_lookup(&request.path, cached.is_some());
+            if cached.is_some() {
+                return cached;
+            }
+            let mut img = read_exif_thumbnail(&request.path, MIN_EMBEDDED_THUMBNAIL)
+                .or_else(|| ImageLoader::load_dynamic_image_path_with_metadata(&request.path))?;
+            let turns = self.cache.get_rotation(&request.path);
+            if turns != 0 {
+                img = apply_orientation(&img, ExifOrientation::Normal.rotated_cw(turns));
+            }
+            let thumb = resize_linear(&img, THUMBNAIL_SIZE, request.shape, self.thumbnail_filter);
+            self.cache
+                .set_thumbnail(&request.path, THUMBNAIL_SIZE, request.shape, &thumb);
+            return Some(thumb);
+        }
+
+        let images = ImageLoader::first_images(&request.path, MONTAGE_IMAGES);
+        if images.is_empty() {
+            let mut thumb = RgbaImage::new(256, 256);
+            for p in thumb.pixels_mut() {
+                *p = Rgba([30, 40, 60, 255]);
+            }
+            draw_filled_rect_mut(
+                &mut thumb,
+                Rect::at(40, 40).of_size(176, 176),
+                Rgba([200, 160, 40, 255]),
+            );
+            draw_folder_name(&mut thumb, &request.path, font);
+            return Some(thumb);
+        }
+
+        let signature = contents_signature(&images, &self.cache);
+        if let Some(cached) =
+            self.cache
+                .get_folder_thumbnail(&request.path, THUMBNAIL_SIZE, signature)
+        {
+            return Some(cached);
+        }
+        let thumb = self.folder_montage(&request.path, &images, font);
+        self.cache
+            .set_folder_thumbnail(&request.path, THUMBNAIL_SIZE, signature, &thumb);
+        Some(thumb)
+    }
+
+    /// Folder thumbnail with `images` tiled two by two on the folder colour.
+    fn folder_montage(
+        &self,
+        folder: &Path,
+        images: &[PathBuf],
+        font: Option<&FontArc>,
+    ) -> RgbaImage {
+        let mut thumb = RgbaImage::from_pixel(256, 256, Rgba([200, 160, 40, 255]));
+        let tile = (THUMBNAIL_SIZE - 3 * MONTAGE_GAP) / 2;
+        for (i, path) in images.iter().enumerate() {
+            let x = MONTAGE_GAP + (i as u32 % 2) * (tile + MONTAGE_GAP);
+            let y = MONTAGE_GAP + (i as u32 / 2) * (tile + MONTAGE_GAP);
+            draw_filled_rect_mut(
+                &mut thumb,
+                Rect::at(x as i32, y as i32).of_size(tile, tile),
+                Rgba([30, 40, 60, 255]),
+            );
+            // A cropped grid thumbnail from an earlier visit saves decoding the file,
+            // and already has the user's rotation applied
+            let img = self
+                .cache
+                .get_thumbnail(path, THUMBNAIL_SIZE, ThumbnailShape::Crop)
+                .map(DynamicImage::ImageRgba8)
+                .or_else(|| {
+                    let img = read_exif_thumbnail(path, tile)
+                        .or_else(|| ImageLoader::load_dynamic_image_path_with_metadata(path))?;
+                    let turns = self.cache.get_rotation(path);
+                    if turns == 0 {
+                        return Some(img);
+                    }
+                    Some(apply_orientation(
+                        &img,
+                        ExifOrientation::Normal.rotated_cw(turns),
+                    ))
+                });
+            if let Some(img) = img {
+                let preview =
+                    resize_linear(&img, tile, ThumbnailShape::Crop, self.thumbnail_filter);
+                image::imageops::overlay(&mut thumb, &preview, x as i64, y as i64);
+            }
+        }
+        draw_folder_name(&mut thumb, folder, font);
+        thumb
+    }
+}
+
+/// Labels a folder thumbnail with the folder's name along the bottom.
+fn draw_folder_name(thumb: &mut RgbaImage, folder: &Path, font: Option<&FontArc>) {
+    let Some(font) = font else {
+        return;
+    };
+    let text = folder
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let scale = PxScale::from(18.0);
+    draw_filled_rect_mut(
+        thumb,
+        Rect::at(0, 220).of_size(256, 36),
+        Rgba([0, 0, 0, 180]),
+    );
+    draw_text_mut(
+        thumb,
+        Rgba([255, 255, 255, 255]),
+        10,
+        228,
+        scale,
+        font,
+        &text,
+    );
+}
+
+/// Changes whenever one of `images` is added, removed, renamed, rewritten or
+/// given a different rotation in `cache`.
+fn contents_signature(images: &[PathBuf], cache: &CacheManager) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for path in images {
+        path.hash(&mut hasher);
+        cache.get_rotation(path).hash(&mut hasher);
+        if let Ok(meta) = std::fs::metadata(path) {
+            meta.len().hash(&mut hasher);
+            meta.modified().ok().hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+/// Like `DynamicImage::resize_to_fill` (or `resize` when fitting) to a `size`
+/// square, but averaging in linear light: filtering sRGB values directly
+/// darkens fine bright detail.
+fn resize_linear(
+    img: &DynamicImage,
+    size: u32,
+    shape: ThumbnailShape,
+    filter: FilterType,
+) -> RgbaImage {
+    // A box-filtered pass first, so big photos never need a full-size linear copy
+    let (short, long) = (img.width().min(img.height()), img.width().max(img.height()));
+    let factor = match shape {
+        ThumbnailShape::Crop => short / (2 * size),
+        // Never so far that a panorama's short side shrinks to nothing
+        ThumbnailShape::Fit => (long / (2 * size)).min(short),
+    }
+    .max(1);
+    let linear = DynamicImage::ImageRgba32F(shrink_to_linear(img, factor));
+    let resized = match shape {
+        ThumbnailShape::Crop => linear.resize_to_fill(size, size, filter),
+        ThumbnailShape::Fit => linear.resize(size, size, filter),
+    }
+    .into_rgba32f();
+
+    // Indexed by a linear value scaled to 0..4096
+    let to_srgb: Vec<u8> = (0..4096)
+        .map(|i| linear_to_srgb((i as f32 + 0.5) / 4096.0))
+        .collect();
+    let encode = |v: f32| to_srgb[((v * 4096.0) as usize).min(4095)];
+    let mut pixels = Vec::with_capacity(resized.as_raw().len());
+    for pixel in resized.as_raw().chunks_exact(4) {
+        let alpha = pixel[3].clamp(0.0, 1.0);
+        if alpha > 0.0 {
+            pixels.extend(pixel[..3].iter().map(|&v| encode(v / alpha)));
+        } else {
+            pixels.extend([0; 3]);
+        }
+        pixels.push((alpha * 255.0).round() as u8);
+    }
+    RgbaImage::from_raw(resized.width(), resized.height(), pixels).expect("four samples per pixel")
+}
+
+/// `img` in premultiplied linear light, each `factor` x `factor` block averaged into one
+/// pixel. Weighting color by alpha keeps transparent pixels from darkening the edges.
+fn shrink_to_linear(img: &DynamicImage, factor: u32) -> Rgba32FImage {
+    let to_linear: Vec<f32> = (0..=255u8)
+        .map(|v| srgb_to_linear(v as f32 / 255.0))
+        .collect();
+    // 8-bit layouts are read in place; anything else is converted once
+    let converted;
+    let (samples, channels) = match img {
+        DynamicImage::ImageLuma8(gray) => (gray.as_raw(), 1),
+        DynamicImage::ImageLumaA8(gray) => (gray.as_raw(), 2),
+        DynamicImage::ImageRgb8(rgb) => (rgb.as_raw(), 3),
+        DynamicImage::ImageRgba8(rgba) => (rgba.as_raw(), 4),
+        _ => {
+            converted = img.to_rgba8();
+            (converted.as_raw(), 4)
+        }
+    };
+
+    let (width, height) = (img.width() / factor, img.height() / factor);
+    let mut shrunk = Rgba32FImage::new(width, height);
+    if width == 0 || height == 0 {
+        return shrunk;
+    }
+    let factor = factor as usize;
+    let row_len = img.width() as usize * channels;
+    let count = (factor * factor) as f32;
+    let mut sums = vec![[0f32; 4]; width as usize];
+    for (out_y, out_row) in shrunk.chunks_exact_mut(width as usize * 4).enumerate() {
+        sums.fill([0.0; 4]);
+        let rows = samples
+            .chunks_exact(row_len)
+            .skip(out_y * factor)
+            .take(factor);
+        for row in rows {
+            for (sum, block) in sums.iter_mut().zip(row.chunks_exact(factor * channels)) {
+                for pixel in block.chunks_exact(channels) {
+                    let (r, g, b) = match channels {
+                        1 | 2 => (pixel[0], pixel[0], pixel[0]),
+                        _ => (pixel[0], pixel[1], pixel[2]),
+                    };
+                    let alpha = match channels {
+                        2 => pixel[1] as f32 / 255.0,
+                        4 => pixel[3] as f32 / 255.0,
+                        _ => 1.0,
+                    };
+                    sum[0] += to_linear[r as usize] * alpha;
+                    sum[1] += to_linear[g as usize] * alpha;
+                    sum[2] += to_linear[b as usize] * alpha;
+                    sum[3] += alpha;
+                }
+            }
+        }
+        for (out, sum) in out_row.chunks_exact_mut(4).zip(&sums) {
+            for (channel, total) in out.iter_mut().zip(sum) {
+                *channel = total / count;
+            }
+        }
+    }
+    shrunk
+}
+
+fn srgb_to_linear(v: f32) -> f32 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(v: f32) -> u8 {
+    let encoded = if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    };
+    (encoded * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+/// Card shown in place of an image that could not be decoded: a cracked frame
+/// and, when a font is available, "Couldn't load" with the file name.
+pub fn draw_error_card(width: u32, height: u32, name: &str, font: Option<&FontArc>) -> RgbaImage {
+    let mut card = RgbaImage::from_pixel(width, height, Rgba([40, 26, 26, 255]));
+    let glyph = Rgba([190, 90, 90, 255]);
+
+    let side = width.min(height) / 3;
+    let left = (width - side) / 2;
+    let top = (height - side) / 2 - height / 10;
+    for inset in 0..3 {
+        draw_hollow_rect_mut(
+            &mut card,
+            Rect::at((left + inset) as i32, (top + inset) as i32)
+                .of_size(side - 2 * inset, side - 2 * inset),
+            glyph,
+        );
+    }
+    for offset in -1..=1 {
+        let offset = offset as f32;
+        draw_line_segment_mut(
+            &mut card,
+            (left as f32 + offset, (top + side) as f32),
+            ((left + side) as f32 + offset, top as f32),
+            glyph,
+        );
+    }
+
+    if let Some(font) = font {
+        let scale = PxScale::from(18.0);
+        let text_top = (top + side) as i32 + 16;
+        draw_text_mut(
+            &mut card,
+            Rgba([230, 200, 200, 255]),
+            10,
+            text_top,
+            scale,
+            font,
+            "Couldn't load",
+        );
+        draw_text_mut(
+            &mut card,
+            Rgba([255, 255, 255, 255]),
+            10,
+            text_top + 24,
+            scale,
+            font,
+            name,
+        );
+    }
+    card
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One-pixel black and white squares, which average to mid grey in linear light
+    fn checkerboard(size: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(image::RgbImage::from_fn(size, size, |x, y| {
+            if (x + y) % 2 == 0 {
+                image::Rgb([255, 255, 255])
+            } else {
+                image::Rgb([0, 0, 0])
+            }
+        }))
+    }
+
+    fn mean_red(img: &RgbaImage) -> f32 {
+        let total: u32 = img.pixels().map(|pixel| pixel[0] as u32).sum();
+        total as f32 / (img.width() * img.height()) as f32
+    }
+
+    #[test]
+    fn checkerboard_keeps_its_luminance_in_linear_light() {
+        let board = checkerboard(512);
+        let linear = resize_linear(&board, 16, ThumbnailShape::Fit, FilterType::Triangle);
+        let srgb = board.resize(16, 16, FilterType::Triangle).to_rgba8();
+
+        // Half of the light is 0.5 linear, which sRGB encodes as 188
+        let expected = linear_to_srgb(0.5) as f32;
+        assert!(
+            (mean_red(&linear) - expected).abs() < 2.0,
+            "{}",
+            mean_red(&linear)
+        );
+        // Averaging the encoded values instead lands near 128, visibly darker
+        assert!(mean_red(&srgb) < expected - 40.0, "{}", mean_red(&srgb));
+    }
+
+    #[test]
+    fn transparent_pixels_do_not_darken_the_average() {
+        // Opaque white next to fully transparent black
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(64, 64, |x, _| {
+            if x % 2 == 0 {
+                Rgba([255, 255, 255, 255])
+            } else {
+                Rgba([0, 0, 0, 0])
+            }
+        }));
+        let thumb = resize_linear(&img, 8, ThumbnailShape::Fit, FilterType::Triangle);
+        for pixel in thumb.pixels() {
+            assert_eq!(pixel[0], 255);
+            assert!((pixel[3] as i32 - 128).abs() <= 1, "{:?}", pixel);
+        }
+    }
+
+    #[test]
+    fn shrink_averages_whole_blocks() {
+        let shrunk = shrink_to_linear(&checkerboard(9), 4);
+        assert_eq!(shrunk.dimensions(), (2, 2));
+        for pixel in shrunk.pixels() {
+            assert!((pixel[0] - 0.5).abs() < 1e-6);
+            assert_eq!(pixel[3], 1.0);
+        }
+    }
+
+    #[test]
+    fn error_card_draws_a_frame_on_the_background() {
+        let background = Rgba([40, 26, 26, 255]);
+        let glyph = Rgba([190, 90, 90, 255]);
+        let card = draw_error_card(THUMBNAIL_SIZE, THUMBNAIL_SIZE, "broken.jpg", None);
+        assert_eq!(card.dimensions(), (THUMBNAIL_SIZE, THUMBNAIL_SIZE));
+        assert_eq!(card.get_pixel(0, 0), &background);
+
+        let side = THUMBNAIL_SIZE / 3;
+        let left = (THUMBNAIL_SIZE - side) / 2;
+        let top = (THUMBNAIL_SIZE - side) / 2 - THUMBNAIL_SIZE / 10;
+        assert_eq!(card.get_pixel(left, top + side / 4), &glyph);
+        assert_eq!(card.get_pixel(left + side - 1, top + side / 4), &glyph);
+        // Without a font nothing is written under the frame
+        assert!((top + side + 1..THUMBNAIL_SIZE)
+            .all(|y| card.get_pixel(THUMBNAIL_SIZE / 2, y) == &background));
+    }
+
+    #[test]
+    fn filter_is_chosen_by_name() {
+        assert_eq!(filter_named(""), FilterType::Triangle);
+        assert_eq!(filter_named(" Lanczos "), FilterType::Lanczos3);
+        assert_eq!(filter_named("lanczos3"), FilterType::Lanczos3);
+        assert_eq!(filter_named("bicubic"), FilterType::Triangle);
+    }
+
+    #[test]
+    fn lanczos_keeps_edges_sharper_than_triangle() {
+        // Black left half, white right half
+        let edge = DynamicImage::ImageRgb8(image::RgbImage::from_fn(64, 64, |x, _| {
+            image::Rgb([if x < 32 { 0 } else { 255 }; 3])
+        }));
+        // How far a row strays from the ideal step, summed over the row
+        let blur = |filter| {
+            let thumb = resize_linear(&edge, 16, ThumbnailShape::Fit, filter);
+            (0..16)
+                .map(|x| {
+                    let ideal = if x < 8 { 0 } else { 255 };
+                    (thumb.get_pixel(x, 8)[0] as i32 - ideal).abs()
+                })
+                .sum::<i32>()
+        };
+        let (triangle, lanczos) = (blur(FilterType::Triangle), blur(FilterType::Lanczos3));
+        assert!(lanczos < triangle, "{lanczos} vs {triangle}");
+    }
+
+    #[test]
+    fn thumbnail_of_a_rotated_jpeg_is_upright() {
+        let dir = crate::test_util::TempDir::new("thumbnail-orientation");
+        let path = dir.join("phone.jpg");
+        image::RgbImage::new(400, 200).save(&path).unwrap();
+        crate::metadata::write_jpeg_orientation(&path, ExifOrientation::Rotate90).unwrap();
+
+        let worker = LoaderWorker {
+            queue: Arc::default(),
+            work_ready: Arc::default(),
+            responses: crossbeam_channel::unbounded().0,
+            notify: Arc::new(|| {}),
+            generation: Arc::default(),
+            cache: CacheManager::temporary(),
+            prefetch: PrefetchCache::default(),
+            thumbnail_filter: FilterType::Triangle,
+        };
+        let request = LoaderRequest {
+            path,
+            index: 0,
+            is_directory: false,
+            shape: ThumbnailShape::Fit,
+            generation: 0,
+        };
+        let thumb = worker.load_thumbnail(&request, None).unwrap();
+        assert_eq!(thumb.dimensions(), (THUMBNAIL_SIZE / 2, THUMBNAIL_SIZE));
+    }
+}
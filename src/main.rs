@@ -1,34 +1,51 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod breadcrumb;
 mod cache_manager;
+mod clipboard;
+mod font_loader;
 mod image_loader;
 mod input_handler;
+mod ipc;
+mod keymap;
+mod loader;
 mod metadata;
+mod prefetch;
+mod profile;
 mod renderer;
+mod shell;
+#[cfg(test)]
+mod test_util;
 
 use ab_glyph::{FontArc, PxScale};
 use crossbeam_channel::{unbounded, Receiver, Sender};
 use image::{Rgba, RgbaImage};
-use imageproc::drawing::{draw_filled_rect_mut, draw_text_mut};
-use imageproc::rect::Rect;
-use interprocess::local_socket::{LocalSocketListener, LocalSocketStream, NameTypeSupport};
+use imageproc::drawing::draw_text_mut;
+use interprocess::local_socket::{LocalSocketListener, LocalSocketStream};
+use std::collections::HashSet;
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::thread;
+use std::time::{Duration, Instant};
 use winit::{
     application::ApplicationHandler,
     dpi::LogicalSize,
     event::WindowEvent,
-    event_loop::{ActiveEventLoop, EventLoop, EventLoopProxy},
-    window::{UserAttentionType, Window, WindowId},
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop, EventLoopProxy},
+    window::{Fullscreen, UserAttentionType, Window, WindowId},
 };
 
-use cache_manager::{CacheManager, WindowSettings};
-use image_loader::{FileItem, ImageLoader};
-use input_handler::{InputAction, InputHandler};
+use breadcrumb::Breadcrumb;
+use cache_manager::{CacheManager, FolderState, WindowSettings};
+use clipboard::SystemClipboard;
+use image_loader::{FileItem, ImageLoader, LoadError};
+use input_handler::{InputAction, InputHandler, PIXELS_PER_WHEEL_STEP};
+use ipc::IpcCommand;
+use loader::{LoaderChannels, LoaderRequest, LoaderResponse, ThumbnailShape};
 use metadata::ImageMetadata;
-use renderer::Renderer;
+use renderer::{FitMode, GridLayout, Renderer, GRID_HEADER_HEIGHT, SCROLLBAR_HIT_WIDTH};
 
 #[derive(PartialEq)]
 enum ViewMode {
@@ -39,17 +56,11 @@ enum ViewMode {
 #[derive(Debug)]
 enum UserEvent {
     OpenPath(PathBuf),
-}
-
-struct LoaderRequest {
-    path: PathBuf,
-    index: usize,
-    is_directory: bool,
-}
-
-struct LoaderResponse {
-    index: usize,
-    image: RgbaImage,
+    Focus,
+    // A loader thread finished a thumbnail
+    ThumbnailReady,
+    // The wallpaper thread is done with the image at the path
+    WallpaperSet(PathBuf, std::io::Result<()>),
 }
 
 struct AppState {
@@ -64,19 +75,90 @@ struct AppState {
     loader_tx: Sender<Vec<LoaderRequest>>,
     response_rx: Receiver<LoaderResponse>,
     visible_indices_tx: Sender<Vec<usize>>,
+    prefetch_tx: Sender<Vec<PathBuf>>,
+    // Bumped by every load_grid so stale thumbnails are dropped
+    grid_generation: Arc<AtomicU64>,
+    // Wakes the event loop with the results of work done off the UI thread
+    event_loop_proxy: EventLoopProxy<UserEvent>,
 
     // Zoom state
     saved_zoom: f32,
     is_actual_size: bool,
+    // Set by manual zooming; until then a resized window refits the image
+    user_zoomed: bool,
+    fit_mode: FitMode,
+    upscale_to_fit: bool,
+    // Nearest-neighbour sampling at every zoom, for pixel art; kept for the session
+    nearest_filter: bool,
+    // Whether grid thumbnails are cropped to their cells or fitted inside them
+    thumbnail_shape: ThumbnailShape,
 
     // Grid selection
     selected_index: usize,
+    // Image last shown in single view, remembered for the folder when leaving it
+    last_viewed: Option<PathBuf>,
+    // Grid images picked with Ctrl/Shift+click, and where a Shift+click range starts
+    multi_selected: HashSet<usize>,
+    selection_anchor: Option<usize>,
 
     // Metadata overlay
     show_metadata: bool,
     current_metadata: Option<ImageMetadata>,
+
+    // Status bar, with the text and window size it was last drawn for
+    show_status_bar: bool,
+    status_bar_shown: Option<(String, [f32; 2])>,
+    // Grid folder's path bar, as last drawn
+    breadcrumb: Option<Breadcrumb>,
+
+    // Image shown as a thumbnail preview until its full decode is prefetched
+    pending_full: Option<PathBuf>,
+
+    // Lazily resolved UI font for overlays
+    font: Option<FontArc>,
+
+    // Animation playback (single view)
+    frames: Vec<(RgbaImage, Duration)>,
+    frame_index: usize,
+    next_frame_at: Option<Instant>,
+
+    // Slideshow (single view)
+    slideshow_interval: Duration,
+    next_slide_at: Option<Instant>,
+
+    // Transient message shown in the title bar
+    status_message: Option<(String, Instant)>,
+
+    clipboard: SystemClipboard,
+
+    // Compare mode: image list index shown right of the divider
+    compare_index: Option<usize>,
+    // What the current mouse drag moves, decided when it starts
+    drag_target: Option<DragTarget>,
+}
+
+/// What a left-button drag moves.
+#[derive(Copy, Clone, PartialEq)]
+enum DragTarget {
+    Content,
+    CompareDivider,
+    // Grid scrollbar thumb, grabbed this far below its top edge
+    Scrollbar(f32),
 }
 
+const STATUS_MESSAGE_DURATION: Duration = Duration::from_secs(4);
+
+const STATUS_BAR_HEIGHT: u32 = 24;
+
+/// Images at least this large open with their thumbnail while the full decode runs
+const PROGRESSIVE_MIN_PIXELS: u64 = 12_000_000;
+
+/// How close, in pixels, a drag must start to the compare divider to move it
+const DIVIDER_GRAB_PIXELS: f32 = 8.0;
+
+const MIN_SLIDESHOW_INTERVAL: Duration = Duration::from_secs(1);
+const MAX_SLIDESHOW_INTERVAL: Duration = Duration::from_secs(60);
+
 impl AppState {
     fn new(
         window: Window,
@@ -95,7 +177,10 @@ impl AppState {
         let input_path = if args.len() > 1 {
             PathBuf::from(&args[1])
         } else {
-            PathBuf::from(".")
+            cache
+                .get_last_folder()
+                .filter(|p| p.is_dir())
+                .unwrap_or_else(|| PathBuf::from("."))
         };
 
         // Start File System scan in parallel with WGPU setup
@@ -120,20 +205,16 @@ impl AppState {
             .create_surface(Arc::clone(&window))
             .expect("Failed to create surface");
 
-        let adapter = futures_lite::future::block_on(instance.request_adapter(
-            &wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::HighPerformance,
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            },
-        ))
-        .expect("Failed to find an appropriate adapter");
+        let adapter = select_adapter(&instance, &surface);
+        let info = adapter.get_info();
+        log::info!("Using GPU adapter: {} ({:?})", info.name, info.backend);
 
         let (device, queue) = futures_lite::future::block_on(adapter.request_device(
             &wgpu::DeviceDescriptor {
                 label: None,
                 required_features: wgpu::Features::empty(),
-                required_limits: wgpu::Limits::default(),
+                // The largest texture the adapter supports, so big images keep their detail
+                required_limits: wgpu::Limits::default().using_resolution(adapter.limits()),
                 memory_hints: wgpu::MemoryHints::Performance,
             },
             None,
@@ -148,9 +229,11 @@ impl AppState {
         let (loader_tx, loader_rx) = unbounded::<Vec<LoaderRequest>>();
         let (response_tx, response_rx) = unbounded::<LoaderResponse>();
         let (visible_indices_tx, visible_indices_rx) = unbounded::<Vec<usize>>();
+        let (prefetch_tx, prefetch_rx) = unbounded::<Vec<PathBuf>>();
 
         // Wait for FS init
-        let image_loader = init_rx.recv().expect("Failed to initialize FS");
+        let mut image_loader = init_rx.recv().expect("Failed to initialize FS");
+        image_loader.set_rotation_store(cache.clone_db_handle());
         let initial_file = if args.len() > 1 {
             let p = PathBuf::from(&args[1]);
             if p.is_file() {
@@ -162,116 +245,28 @@ impl AppState {
             None
         };
 
-        // Spawn background thread for image loading
-        let cache_for_thread = cache.clone_db_handle();
-        thread::spawn(move || {
-            let mut pending_requests: Vec<LoaderRequest> = Vec::new();
-            let mut visible_indices: Vec<usize> = Vec::new();
-            let mut font: Option<FontArc> = None;
-
-            loop {
-                // Check for new requests
-                while let Ok(mut requests) = loader_rx.try_recv() {
-                    pending_requests.append(&mut requests);
-                }
-
-                // Check for visible update
-                while let Ok(visible) = visible_indices_rx.try_recv() {
-                    visible_indices = visible;
-                }
-
-                if pending_requests.is_empty() {
-                    thread::sleep(std::time::Duration::from_millis(10));
-                    continue;
-                }
-
-                // Lazy load font on first use
-                if font.is_none() {
-                    font = std::fs::read("C:\\Windows\\Fonts\\arial.ttf")
-                        .ok()
-                        .and_then(|data| FontArc::try_from_vec(data).ok());
-                }
-
-                // Re-prioritize: items in visible_indices first
-                pending_requests.sort_by(|a, b| {
-                    let a_visible = visible_indices.contains(&a.index);
-                    let b_visible = visible_indices.contains(&b.index);
-                    match (a_visible, b_visible) {
-                        (true, false) => std::cmp::Ordering::Less,
-                        (false, true) => std::cmp::Ordering::Greater,
-                        _ => a.index.cmp(&b.index),
-                    }
-                });
-
-                let request = pending_requests.remove(0);
-                let mut thumb_opt: Option<RgbaImage> = None;
-
-                if request.is_directory {
-                    let mut img = RgbaImage::new(256, 256);
-                    for p in img.pixels_mut() {
-                        *p = Rgba([30, 40, 60, 255]);
-                    }
-                    draw_filled_rect_mut(
-                        &mut img,
-                        Rect::at(40, 40).of_size(176, 176),
-                        Rgba([200, 160, 40, 255]),
-                    );
-                    thumb_opt = Some(img);
-                } else {
-                    if let Some(img) = cache_for_thread.get_thumbnail(&request.path) {
-                        thumb_opt = Some(img);
-                    } else if let Some(img) =
-                        ImageLoader::load_dynamic_image_path_with_metadata(&request.path)
-                    {
-                        let thumb = img
-                            .resize_to_fill(256, 256, image::imageops::FilterType::Triangle)
-                            .to_rgba8();
-                        cache_for_thread.set_thumbnail(&request.path, &thumb);
-                        thumb_opt = Some(thumb);
-                    }
-                }
-
-                if let Some(mut thumb) = thumb_opt {
-                    if request.is_directory {
-                        if let Some(font) = &font {
-                            let text = request
-                                .path
-                                .file_name()
-                                .map(|n| n.to_string_lossy().to_string())
-                                .unwrap_or_default();
-                            let scale = PxScale::from(18.0);
-                            draw_filled_rect_mut(
-                                &mut thumb,
-                                Rect::at(0, 220).of_size(256, 36),
-                                Rgba([0, 0, 0, 180]),
-                            );
-                            draw_text_mut(
-                                &mut thumb,
-                                Rgba([255, 255, 255, 255]),
-                                10,
-                                228,
-                                scale,
-                                font,
-                                &text,
-                            );
-                        }
-                    }
-                    let _ = response_tx.send(LoaderResponse {
-                        index: request.index,
-                        image: thumb,
-                    });
-                }
-            }
-        });
+        // Spawn background threads for image loading
+        let grid_generation = Arc::new(AtomicU64::new(0));
+        let loader_proxy = event_loop_proxy.clone();
+        loader::spawn_workers(
+            LoaderChannels {
+                requests: loader_rx,
+                visible_indices: visible_indices_rx,
+                prefetch: prefetch_rx,
+                responses: response_tx,
+                notify: Arc::new(move || {
+                    let _ = loader_proxy.send_event(UserEvent::ThumbnailReady);
+                }),
+            },
+            Arc::clone(&grid_generation),
+            cache.clone_db_handle(),
+            image_loader.prefetch_handle(),
+        );
 
         // Spawn IPC listener thread
+        let ipc_proxy = event_loop_proxy.clone();
         thread::spawn(move || {
-            let name = "fastview_ipc";
-            let name = if NameTypeSupport::query().paths_supported() {
-                format!("/tmp/{}.sock", name)
-            } else {
-                name.to_string()
-            };
+            let name = ipc::socket_name();
 
             let listener = match LocalSocketListener::bind(name.clone()) {
                 Ok(l) => l,
@@ -287,8 +282,13 @@ impl AppState {
                 let mut conn = conn;
                 let mut buf = String::new();
                 if conn.read_to_string(&mut buf).is_ok() {
-                    let path = PathBuf::from(buf.trim());
-                    let _ = event_loop_proxy.send_event(UserEvent::OpenPath(path));
+                    for command in ipc::decode(&buf) {
+                        let event = match command {
+                            IpcCommand::Open(path) => UserEvent::OpenPath(path),
+                            IpcCommand::Focus => UserEvent::Focus,
+                        };
+                        let _ = ipc_proxy.send_event(event);
+                    }
                 }
             }
         });
@@ -303,16 +303,45 @@ impl AppState {
             loader_tx,
             response_rx,
             visible_indices_tx,
+            prefetch_tx,
+            grid_generation,
+            event_loop_proxy,
             saved_zoom: 1.0,
             is_actual_size: false,
+            user_zoomed: false,
+            fit_mode: FitMode::default(),
+            upscale_to_fit: true,
+            nearest_filter: false,
+            thumbnail_shape: ThumbnailShape::from_env(),
             selected_index: 0,
+            last_viewed: None,
+            multi_selected: HashSet::new(),
+            selection_anchor: None,
             show_metadata: false,
             current_metadata: None,
+            show_status_bar: false,
+            status_bar_shown: None,
+            breadcrumb: None,
+            pending_full: None,
+            font: None,
+            frames: Vec::new(),
+            frame_index: 0,
+            next_frame_at: None,
+            slideshow_interval: Duration::from_secs(5),
+            next_slide_at: None,
+            status_message: None,
+            clipboard: SystemClipboard::default(),
+            compare_index: None,
+            drag_target: None,
         };
 
         // Sync renderer mode and load grid
         app_state.renderer.set_view_mode(true);
+        app_state
+            .cache
+            .set_last_folder(app_state.image_loader.get_path());
         app_state.load_grid();
+        app_state.restore_folder_state();
 
         if let Some(file_path) = initial_file {
             app_state.open_image_internal(&file_path);
@@ -328,20 +357,65 @@ impl AppState {
 
         if path.is_file() {
             let parent = path.parent().unwrap_or(Path::new(".")).to_path_buf();
-            self.image_loader.set_path(parent);
-            self.load_grid();
+            self.change_folder(parent);
             self.open_image_internal(&path);
         } else {
-            self.image_loader.set_path(path);
-            self.load_grid();
             self.mode = ViewMode::Grid;
             self.renderer.set_view_mode(true);
+            self.change_folder(path);
         }
 
         self.update_window_title();
         self.window.request_redraw();
+    }
+
+    /// Leaves the current folder for `path`, remembering where it was left.
+    fn change_folder(&mut self, path: PathBuf) {
+        self.save_folder_state();
+        let previous = self.image_loader.get_path().to_path_buf();
+        self.image_loader.set_path(path);
+        if self.image_loader.get_path() != previous {
+            self.cache.set_last_folder(self.image_loader.get_path());
+        }
+        self.load_grid();
+        self.restore_folder_state();
+    }
+
+    fn save_folder_state(&self) {
+        let state = FolderState {
+            last_image: self
+                .last_viewed
+                .as_ref()
+                .and_then(|p| p.file_name())
+                .map(PathBuf::from),
+            fit_mode: self.fit_mode,
+        };
+        self.cache
+            .set_folder_state(self.image_loader.get_path(), &state);
+    }
+
+    /// Applies the zoom mode remembered for the current folder and selects
+    /// the image last viewed there, if it still exists.
+    fn restore_folder_state(&mut self) {
+        self.last_viewed = None;
+        let folder = self.image_loader.get_path().to_path_buf();
+        let Some(state) = self.cache.get_folder_state(&folder) else {
+            return;
+        };
+        self.fit_mode = state.fit_mode;
+        let Some(path) = state.last_image.map(|name| folder.join(name)) else {
+            return;
+        };
+        if let Some(index) = self.grid_index_of(&path) {
+            self.image_loader.select_image(&path);
+            self.selected_index = index;
+            self.renderer.scroll_to_item(index);
+            self.last_viewed = Some(path);
+            self.update_viewport();
+        }
+    }
 
-        // Bring to foreground
+    fn bring_to_front(&self) {
         self.window.set_minimized(false);
         self.window.focus_window();
         self.window
@@ -349,78 +423,726 @@ impl AppState {
     }
 
     fn open_image_internal(&mut self, file_path: &Path) {
-        if let Some(img) = self.image_loader.open_image(file_path) {
-            self.selected_index = self
+        if self.image_loader.select_image(file_path) {
+            self.show_current();
+        } else {
+            self.report_load_error(file_path, &LoadError::NotInFolder);
+        }
+    }
+
+    /// Shows the loader's current image. Big images that haven't been decoded
+    /// yet first appear as their cached thumbnail while a loader thread decodes
+    /// them; `finish_progressive_load` swaps in the real image.
+    fn show_current(&mut self) {
+        self.pending_full = None;
+        if let Some(path) = self.image_loader.get_current_path().cloned() {
+            if !self.image_loader.is_prefetched(&path) {
+                if let Some(preview) = self.thumbnail_preview(&path) {
+                    self.pending_full = Some(path);
+                    self.show_image(preview);
+                    return;
+                }
+            }
+        }
+        let img = self.image_loader.try_load_current_image();
+        self.show_loaded(img);
+    }
+
+    /// The cached grid thumbnail laid out on a canvas with the image's own
+    /// proportions, or None if the image is small enough to decode right away.
+    fn thumbnail_preview(&self, path: &Path) -> Option<RgbaImage> {
+        if ImageLoader::is_animated_format(path) || self.image_loader.has_user_rotation(path) {
+            return None;
+        }
+        let (mut width, mut height) = image::image_dimensions(path).ok()?;
+        if (width as u64) * (height as u64) < PROGRESSIVE_MIN_PIXELS {
+            return None;
+        }
+        if metadata::read_orientation_only(path).swaps_dimensions() {
+            std::mem::swap(&mut width, &mut height);
+        }
+        let thumb = self
+            .cache
+            .get_thumbnail(path, loader::THUMBNAIL_SIZE, self.thumbnail_shape)
+            .or_else(|| {
+                let other = self.thumbnail_shape.toggled();
+                self.cache
+                    .get_thumbnail(path, loader::THUMBNAIL_SIZE, other)
+            })?;
+
+        // A cropped thumbnail covers the middle of the canvas, a fitted one all of it
+        let scale =
+            (thumb.width() as f32 / width as f32).max(thumb.height() as f32 / height as f32);
+        let canvas_width = ((width as f32 * scale).round() as u32).max(thumb.width());
+        let canvas_height = ((height as f32 * scale).round() as u32).max(thumb.height());
+        let mut canvas = RgbaImage::new(canvas_width, canvas_height);
+        image::imageops::overlay(
+            &mut canvas,
+            &thumb,
+            ((canvas_width - thumb.width()) / 2) as i64,
+            ((canvas_height - thumb.height()) / 2) as i64,
+        );
+        Some(canvas)
+    }
+
+    /// Replaces a thumbnail preview with the full image once the loader has decoded it.
+    fn finish_progressive_load(&mut self) {
+        let Some(path) = &self.pending_full else {
+            return;
+        };
+        if self.mode != ViewMode::Single || self.image_loader.get_current_path() != Some(path) {
+            self.pending_full = None;
+            return;
+        }
+        if self.image_loader.is_prefetched(path) {
+            self.pending_full = None;
+            let img = self.image_loader.try_load_current_image();
+            self.show_loaded(img);
+        }
+    }
+
+    /// Puts the grid cursor on `path`, if it is listed.
+    fn select_grid_path(&mut self, path: &Path) {
+        let found = self.image_loader.get_items().iter().position(
+            |item| matches!(item, FileItem::Image(p) | FileItem::Directory(p) if p == path),
+        );
+        if let Some(pos) = found {
+            self.selected_index = pos;
+            self.renderer.scroll_to_item(pos);
+            self.update_viewport();
+        }
+    }
+
+    /// Re-reads what is on screen, e.g. after another program changed it. Single
+    /// view decodes the file again and regenerates its thumbnail; the grid
+    /// lists the folder again.
+    fn reload(&mut self) {
+        match self.mode {
+            ViewMode::Single => {
+                let Some(path) = self.image_loader.get_current_path().cloned() else {
+                    return;
+                };
+                self.image_loader.forget_decoded(&path);
+                self.cache.remove_thumbnail(&path, loader::THUMBNAIL_SIZE);
+                if let Some(index) = self.grid_index_of(&path) {
+                    self.renderer.reload_grid_item(index);
+                }
+                self.show_current();
+            }
+            ViewMode::Grid => {
+                let selected = self.selected_path();
+                self.image_loader.refresh();
+                self.load_grid();
+                if let Some(path) = selected {
+                    self.select_grid_path(&path);
+                }
+                self.update_window_title();
+            }
+        }
+        self.window.request_redraw();
+    }
+
+    fn grid_index_of(&self, path: &Path) -> Option<usize> {
+        self.image_loader
+            .get_items()
+            .iter()
+            .position(|item| match item {
+                FileItem::Image(p) => p == path,
+                _ => false,
+            })
+    }
+
+    /// Shows the loader's current image, or an error card if it failed to decode.
+    fn show_loaded(&mut self, img: Result<RgbaImage, LoadError>) {
+        match img {
+            Ok(img) => self.show_image(img),
+            Err(error) => self.show_load_error(&error),
+        }
+    }
+
+    /// Logs why `path` didn't open and says so in the status bar.
+    fn report_load_error(&mut self, path: &Path, error: &LoadError) {
+        log::warn!("Could not open {}: {}", path.display(), error);
+        self.show_status(format!("Can't open {}: {}", file_name(path), error));
+    }
+
+    /// Switches to single view with an error card in place of the current image.
+    fn show_load_error(&mut self, error: &LoadError) {
+        let Some(path) = self.image_loader.get_current_path().cloned() else {
+            return;
+        };
+        if self.font.is_none() {
+            self.font = font_loader::load_font();
+        }
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let card = loader::draw_error_card(480, 270, &name, self.font.as_ref());
+        self.report_load_error(&path, error);
+
+        self.compare_index = None;
+        self.renderer.set_compare_image(None);
+        self.frames.clear();
+        self.next_frame_at = None;
+        self.is_actual_size = false;
+        self.apply_filtering();
+        self.renderer.update_texture(card);
+        self.renderer.set_zoom(1.0);
+        self.renderer.set_view_mode(false);
+        self.renderer.set_overlay(None, None);
+        self.mode = ViewMode::Single;
+
+        if let Some(index) = self.grid_index_of(&path) {
+            self.selected_index = index;
+            self.renderer.scroll_to_item(index);
+        }
+        self.last_viewed = Some(path);
+        self.update_window_title();
+        self.window.request_redraw();
+    }
+
+    /// Switches to single view showing `img`, which must be the loader's current image.
+    fn show_image(&mut self, img: RgbaImage) {
+        self.compare_index = None;
+        self.renderer.set_compare_image(None);
+        self.is_actual_size = false;
+        self.apply_filtering();
+        self.renderer.update_texture(img);
+        self.set_zoom_to_fit();
+        self.renderer.set_view_mode(false);
+        self.mode = ViewMode::Single;
+        self.start_animation();
+
+        // Keep the grid cursor on the image being viewed so Back lands on it
+        if let Some(path) = self.image_loader.get_current_path().cloned() {
+            if let Some(index) = self.grid_index_of(&path) {
+                self.selected_index = index;
+                self.renderer.scroll_to_item(index);
+            }
+            self.last_viewed = Some(path);
+        }
+        self.update_window_title();
+
+        if self.show_metadata {
+            self.current_metadata = self.image_loader.get_current_metadata();
+            self.update_metadata_overlay();
+        }
+        let mut prefetch = self.image_loader.neighbor_paths();
+        if let Some(path) = &self.pending_full {
+            prefetch.insert(0, path.clone());
+        }
+        let _ = self.prefetch_tx.send(prefetch);
+        self.window.request_redraw();
+    }
+
+    /// Switches from single view back to the current folder's grid.
+    fn leave_single_view(&mut self) {
+        self.is_actual_size = false;
+        self.apply_filtering();
+        self.mode = ViewMode::Grid;
+        self.renderer.set_view_mode(true);
+        self.update_viewport();
+        self.save_folder_state();
+    }
+
+    /// Splits single view between the current image and the image at `index`,
+    /// or leaves compare mode with `None`.
+    fn set_compare(&mut self, index: Option<usize>) {
+        let img = index.and_then(|i| self.image_loader.load_image_at(i));
+        self.compare_index = index.filter(|_| img.is_some());
+        self.renderer.set_compare_image(img.as_ref());
+        self.update_window_title();
+        self.window.request_redraw();
+    }
+
+    fn start_compare(&mut self) {
+        if self.mode == ViewMode::Grid {
+            let item = self
                 .image_loader
                 .get_items()
-                .iter()
-                .position(|item| match item {
-                    FileItem::Image(p) => p == file_path,
-                    _ => false,
-                })
-                .unwrap_or(0);
+                .get(self.selected_index)
+                .cloned();
+            if let Some(FileItem::Image(path)) = item {
+                match self.image_loader.open_image(&path) {
+                    Ok(img) => self.show_image(img),
+                    Err(error) => return self.report_load_error(&path, &error),
+                }
+            }
+        }
+        if self.mode != ViewMode::Single {
+            return;
+        }
 
-            self.renderer.update_texture(&img);
-            self.set_zoom_to_fit();
-            self.renderer.set_view_mode(false);
-            self.mode = ViewMode::Single;
+        let count = self.image_loader.get_image_count();
+        if count < 2 {
+            self.show_status("Need at least two images to compare".to_string());
+            return;
+        }
+        self.set_slideshow(false);
+        self.set_compare(Some((self.image_loader.get_current_index() + 1) % count));
+    }
 
-            if self.show_metadata {
-                self.current_metadata = self.image_loader.get_current_metadata();
-                self.update_metadata_overlay();
+    /// Moves the compared image to the next or previous one, skipping the current image.
+    fn step_compare(&mut self, forward: bool) {
+        let Some(mut index) = self.compare_index else {
+            return;
+        };
+        let count = self.image_loader.get_image_count();
+        let current = self.image_loader.get_current_index();
+        if count < 2 {
+            return;
+        }
+        loop {
+            index = if forward {
+                (index + 1) % count
+            } else {
+                (index + count - 1) % count
+            };
+            if index != current {
+                break;
             }
         }
+        self.set_compare(Some(index));
     }
 
-    fn set_zoom_to_fit(&mut self) {
-        let img_size = self.renderer.get_image_size();
-        let win_size = self.renderer.get_window_size();
+    fn start_animation(&mut self) {
+        self.frames.clear();
+        self.frame_index = 0;
+        self.next_frame_at = None;
+
+        if let Some(path) = self.image_loader.get_current_path() {
+            if ImageLoader::is_animated_format(path) {
+                let frames = ImageLoader::load_frames(path);
+                if frames.len() > 1 {
+                    self.next_frame_at = Some(Instant::now() + frames[0].1);
+                    self.frames = frames;
+                }
+            }
+        }
+    }
 
-        if img_size[0] <= 0.0 || img_size[1] <= 0.0 {
-            self.renderer.set_zoom(1.0);
+    fn advance_animation(&mut self, now: Instant) {
+        if self.mode != ViewMode::Single {
+            self.next_frame_at = None;
+            return;
+        }
+        let Some(deadline) = self.next_frame_at else {
             return;
+        };
+        if now < deadline {
+            return;
+        }
+
+        self.frame_index = (self.frame_index + 1) % self.frames.len();
+        let (frame, delay) = &self.frames[self.frame_index];
+        self.renderer.update_frame(frame);
+
+        // Don't try to catch up on frames missed while the window was stalled
+        let next = deadline + *delay;
+        self.next_frame_at = Some(if next < now { now + *delay } else { next });
+        self.window.request_redraw();
+    }
+
+    fn advance_slideshow(&mut self, now: Instant) {
+        if self.mode != ViewMode::Single {
+            self.next_slide_at = None;
+            return;
+        }
+        let Some(deadline) = self.next_slide_at else {
+            return;
+        };
+        if now < deadline {
+            return;
+        }
+
+        self.image_loader.select_next();
+        self.show_current();
+        self.next_slide_at = Some(Instant::now() + self.slideshow_interval);
+    }
+
+    fn set_slideshow(&mut self, running: bool) {
+        let was_running = self.next_slide_at.is_some();
+        self.next_slide_at = running.then(|| Instant::now() + self.slideshow_interval);
+        if was_running != running {
+            self.update_window_title();
+        }
+    }
+
+    fn show_status(&mut self, message: String) {
+        self.status_message = Some((message, Instant::now() + STATUS_MESSAGE_DURATION));
+        self.update_window_title();
+    }
+
+    fn tick(&mut self, now: Instant) {
+        self.advance_animation(now);
+        self.advance_slideshow(now);
+
+        if self.renderer.animate(now) {
+            self.update_viewport();
+            self.window.request_redraw();
+        }
+
+        if matches!(&self.status_message, Some((_, expires)) if now >= *expires) {
+            self.status_message = None;
+            self.update_window_title();
         }
+    }
 
-        let ia = img_size[0] / img_size[1];
-        let wa = win_size[0] / win_size[1];
+    fn next_wakeup(&self) -> Option<Instant> {
+        [
+            self.next_frame_at,
+            self.next_slide_at,
+            self.renderer.scrollbar_fade_start(),
+            self.status_message.as_ref().map(|(_, expires)| *expires),
+            self.input_handler.digit_deadline(),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+    }
 
-        let zoom = if wa > ia {
-            win_size[1] / img_size[1]
+    /// Switches between 100% zoom and the previous zoom level. With an `anchor`,
+    /// the image point under the cursor stays in place.
+    fn toggle_actual_size(&mut self, anchor: Option<[f32; 2]>) {
+        if !self.is_actual_size {
+            self.saved_zoom = self.renderer.get_zoom();
+            self.is_actual_size = true;
+            self.renderer.zoom_to(1.0, anchor);
         } else {
-            win_size[0] / img_size[0]
+            self.is_actual_size = false;
+            // The window may have been resized since the fit zoom was saved
+            let zoom = if self.user_zoomed {
+                self.saved_zoom
+            } else {
+                self.fit_zoom()
+            };
+            self.renderer.zoom_to(zoom, anchor);
+        }
+
+        self.apply_filtering();
+        self.window.request_redraw();
+    }
+
+    /// Nearest-neighbour sampling at actual size, or at any zoom when toggled on.
+    fn apply_filtering(&mut self) {
+        self.renderer
+            .set_filtering(self.is_actual_size || self.nearest_filter);
+    }
+
+    fn rotate_current(&mut self, clockwise: bool) {
+        if self.frames.len() > 1 {
+            self.show_status("Animated images can't be rotated".to_string());
+            return;
+        }
+
+        let saved = self
+            .image_loader
+            .rotate_current(if clockwise { 1 } else { 3 });
+        let Some(img) = self.image_loader.load_current_image() else {
+            return;
         };
+        self.renderer.update_texture(img);
+        if self.is_actual_size {
+            self.renderer.set_zoom(1.0);
+        } else {
+            self.set_zoom_to_fit();
+        }
+        if self.show_metadata {
+            self.current_metadata = self.image_loader.get_current_metadata();
+            self.update_metadata_overlay();
+        }
 
-        self.renderer.set_zoom(zoom.min(1.0));
+        if saved {
+            self.regenerate_current_thumbnail();
+            self.show_status("Rotated and saved to file".to_string());
+        } else {
+            // Grid thumbnails are cached with the rotation they were made with
+            if let Some(path) = self.image_loader.get_current_path().cloned() {
+                self.cache.remove_thumbnail(&path, loader::THUMBNAIL_SIZE);
+                if let Some(index) = self.grid_index_of(&path) {
+                    self.renderer.reload_grid_item(index);
+                }
+            }
+            self.show_status("Rotated without changing the file (Ctrl+S saves)".to_string());
+        }
+        self.window.request_redraw();
     }
 
-    fn load_grid(&mut self) {
-        self.selected_index = 0;
-        self.renderer.clear_grid();
-        let items = self.image_loader.get_items().to_vec();
-        let mut requests = Vec::new();
+    /// Writes the current image's rotation into the file, which then shows as is.
+    fn save_current_rotation(&mut self) {
+        match self.image_loader.save_current_rotation() {
+            Ok(true) => {
+                self.regenerate_current_thumbnail();
+                self.show_status("Rotation saved to file".to_string());
+            }
+            Ok(false) => self.show_status("No rotation to save".to_string()),
+            Err(e) => {
+                log::warn!("Couldn't save rotation: {}", e);
+                self.show_status(format!("Couldn't save rotation: {}", e));
+            }
+        }
+        self.window.request_redraw();
+    }
+
+    /// Shows how setting `path` as the desktop background went.
+    fn report_wallpaper(&mut self, path: &Path, result: std::io::Result<()>) {
+        match result {
+            Ok(()) => self.show_status("Set as wallpaper".to_string()),
+            Err(e) if e.kind() == std::io::ErrorKind::Unsupported => self
+                .show_status("Setting the wallpaper isn't supported on this desktop".to_string()),
+            Err(e) => {
+                log::error!("Failed to set {} as wallpaper: {}", path.display(), e);
+                self.show_status("Could not set the wallpaper".to_string());
+            }
+        }
+        self.window.request_redraw();
+    }
+
+    /// Queues a new grid thumbnail for the current image after its file changed.
+    fn regenerate_current_thumbnail(&mut self) {
+        if let Some(path) = self.image_loader.get_current_path().cloned() {
+            if let Some(index) = self.grid_index_of(&path) {
+                let _ = self.loader_tx.send(vec![LoaderRequest {
+                    path,
+                    index,
+                    is_directory: false,
+                    shape: self.thumbnail_shape,
+                    generation: self.grid_generation.load(Ordering::Relaxed),
+                }]);
+            }
+        }
+    }
+
+    /// Selects grid item `index`, or opens image `index` in single view,
+    /// clamped to the last one.
+    fn jump_to(&mut self, index: usize) {
+        if self.mode == ViewMode::Grid {
+            let total_items = self.image_loader.get_items().len();
+            if total_items > 0 {
+                self.move_selection_to(index.min(total_items - 1));
+            }
+        } else if self.mode == ViewMode::Single {
+            let count = self.image_loader.get_image_count();
+            if count > 0 {
+                self.image_loader.set_current_index(index.min(count - 1));
+                self.show_current();
+            }
+        }
+    }
 
-        for (i, item) in items.iter().enumerate() {
-            match item {
-                FileItem::Directory(p) => {
-                    self.renderer.add_grid_item(p.clone(), true, None);
-                    requests.push(LoaderRequest {
-                        path: p.clone(),
-                        index: i,
-                        is_directory: true,
-                    });
+    /// Rebuilds the grid for the current search query. Typing selects the
+    /// first match; ending the search keeps the selected or opened file selected.
+    fn apply_search(&mut self) {
+        let query = self.input_handler.search_query().map(str::to_string);
+        let selected = self.selected_path();
+        self.image_loader.set_filter(query.as_deref());
+        self.load_grid();
+
+        if query.is_none() {
+            if let Some(path) = selected {
+                let position = self.image_loader.get_items().iter().position(|item| {
+                    matches!(item, FileItem::Image(p) | FileItem::Directory(p) if *p == path)
+                });
+                if let Some(index) = position {
+                    self.selected_index = index;
+                    self.renderer.scroll_to_item(index);
+                    self.update_viewport();
                 }
-                FileItem::Image(p) => {
-                    self.renderer.add_grid_item(p.clone(), false, None);
-                    requests.push(LoaderRequest {
-                        path: p.clone(),
-                        index: i,
-                        is_directory: false,
-                    });
+            }
+        }
+        self.update_window_title();
+        self.window.request_redraw();
+    }
+
+    /// The open image in single view, or the selected grid entry.
+    fn selected_path(&self) -> Option<PathBuf> {
+        match self.mode {
+            ViewMode::Single => self.image_loader.get_current_path().cloned(),
+            ViewMode::Grid => match self.image_loader.get_items().get(self.selected_index)? {
+                FileItem::Image(p) | FileItem::Directory(p) => Some(p.clone()),
+            },
+        }
+    }
+
+    /// Images a file operation applies to: the grid's multi-selection if there
+    /// is one, else the selected grid image or the one in single view.
+    /// Directories are never included.
+    fn target_paths(&self) -> Vec<PathBuf> {
+        let items = self.image_loader.get_items();
+        let image_at = |index: usize| match items.get(index) {
+            Some(FileItem::Image(p)) => Some(p.clone()),
+            _ => None,
+        };
+        match self.mode {
+            ViewMode::Grid if !self.multi_selected.is_empty() => {
+                let mut indices: Vec<usize> = self.multi_selected.iter().copied().collect();
+                indices.sort_unstable();
+                indices.into_iter().filter_map(image_at).collect()
+            }
+            ViewMode::Grid => image_at(self.selected_index).into_iter().collect(),
+            ViewMode::Single => self
+                .image_loader
+                .get_current_path()
+                .cloned()
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    /// Moves the target images to the OS trash.
+    fn delete_current(&mut self) {
+        let paths = self.target_paths();
+        let failed = self.apply_to_files(&paths, "move to trash", |path| {
+            trash::delete(path).map_err(|e| e.to_string())
+        });
+        let message = match (paths.as_slice(), failed) {
+            ([], _) => return,
+            ([path], 0) => format!(
+                "Moved {} to trash (restore it from the system trash)",
+                file_name(path)
+            ),
+            ([path], _) => format!("Could not delete {}", file_name(path)),
+            (_, 0) => format!(
+                "Moved {} files to trash (restore them from the system trash)",
+                paths.len()
+            ),
+            (_, failed) => format!(
+                "Moved {} files to trash, {} could not be deleted",
+                paths.len() - failed,
+                failed
+            ),
+        };
+        self.show_status(message);
+    }
+
+    /// Moves the target images into the sorting bin folder.
+    fn move_to_sort_bin(&mut self) {
+        let paths = self.target_paths();
+        if paths.is_empty() {
+            return;
+        }
+        let bin = sort_bin(self.image_loader.get_path());
+        if let Err(e) = std::fs::create_dir_all(&bin) {
+            log::error!("Failed to create {}: {}", bin.display(), e);
+            self.show_status(format!("Could not create {}", bin.display()));
+            return;
+        }
+        let failed = self.apply_to_files(&paths, "move", |path| move_file(path, &bin));
+        let moved = match paths.len() - failed {
+            1 => "1 file".to_string(),
+            count => format!("{} files", count),
+        };
+        let mut message = format!("Moved {} to {}", moved, bin.display());
+        if failed > 0 {
+            message.push_str(&format!(", {} failed", failed));
+        }
+        self.show_status(message);
+    }
+
+    /// Runs `op` on each of `paths`, then reloads the listing and keeps the
+    /// cursor near where it was. Paths `op` failed on stay multi-selected.
+    /// Returns how many failed.
+    fn apply_to_files(
+        &mut self,
+        paths: &[PathBuf],
+        verb: &str,
+        op: impl Fn(&Path) -> Result<(), String>,
+    ) -> usize {
+        if paths.is_empty() {
+            return 0;
+        }
+        let failed: Vec<&PathBuf> = paths
+            .iter()
+            .filter(|path| match op(path) {
+                Ok(()) => false,
+                Err(e) => {
+                    log::error!("Failed to {} {}: {}", verb, path.display(), e);
+                    true
+                }
+            })
+            .collect();
+        if failed.len() == paths.len() {
+            return failed.len();
+        }
+
+        let was_batch = !self.multi_selected.is_empty();
+        let image_index = self.image_loader.get_current_index();
+        let grid_index = self.selected_index;
+        self.image_loader.refresh();
+        self.load_grid();
+
+        if self.mode == ViewMode::Single {
+            if self.image_loader.get_image_count() == 0 {
+                // Nothing left to show, fall back to the (now empty) grid
+                self.mode = ViewMode::Grid;
+                self.renderer.set_view_mode(true);
+                self.update_viewport();
+            } else {
+                self.image_loader.set_current_index(image_index);
+                if let Some(next) = self.image_loader.get_current_path().cloned() {
+                    self.open_image_internal(&next);
                 }
             }
+        } else {
+            let total = self.image_loader.get_items().len();
+            self.selected_index = grid_index.min(total.saturating_sub(1));
+            if was_batch {
+                // Rebuilt from paths, since the indices shifted as the list shrank
+                self.multi_selected = failed
+                    .iter()
+                    .filter_map(|path| self.grid_index_of(path))
+                    .collect();
+            }
+            self.renderer.scroll_to_item(self.selected_index);
+            self.update_viewport();
+        }
+        self.window.request_redraw();
+        failed.len()
+    }
+
+    /// Applies the session's fit mode to the current image.
+    fn set_zoom_to_fit(&mut self) {
+        self.user_zoomed = false;
+        self.renderer.set_zoom(self.fit_zoom());
+        if self.fit_mode == FitMode::FitWidth {
+            // Tall images start at the top; the pan clamp stops at the edge
+            self.renderer.pan(0.0, f32::MAX);
+        }
+    }
+
+    fn fit_zoom(&self) -> f32 {
+        let img_size = self.renderer.get_image_size();
+        if img_size[0] <= 0.0 || img_size[1] <= 0.0 {
+            return 1.0;
+        }
+        self.fit_mode.zoom_for(
+            img_size,
+            self.renderer.get_window_size(),
+            self.upscale_to_fit,
+        )
+    }
+
+    fn set_zoom_mode(&mut self, mode: FitMode) {
+        if self.is_actual_size {
+            self.toggle_actual_size(None);
         }
+        self.fit_mode = mode;
+        self.set_zoom_to_fit();
+        self.window.request_redraw();
+    }
+
+    fn load_grid(&mut self) {
+        self.selected_index = 0;
+        // Indices change with the listing
+        self.multi_selected.clear();
+        self.selection_anchor = None;
+        self.renderer.clear_grid();
+        self.grid_generation.fetch_add(1, Ordering::Relaxed);
+
+        self.renderer
+            .add_grid_items(self.image_loader.get_items().len());
 
-        let _ = self.loader_tx.send(requests);
         self.update_viewport();
     }
 
@@ -429,165 +1151,425 @@ impl AppState {
             return;
         }
 
-        let grid_size = 250.0;
-        let spacing = 20.0;
-        let window_size = self.renderer.get_window_size();
-        let cols = (window_size[0] / (grid_size + spacing)).floor().max(1.0) as u32;
-        let scroll = self.renderer.grid_scroll;
+        let layout = self.renderer.get_grid_layout();
+        let visible =
+            layout.visible_range(self.renderer.grid_scroll, self.renderer.get_window_size());
+        // Keep about a page of rows on either side so short scrolls don't reload
+        let margin = visible.len();
+        let retained = visible.start.saturating_sub(margin)..visible.end + margin;
+        let _ = self.visible_indices_tx.send(visible.collect());
+
+        let generation = self.grid_generation.load(Ordering::Relaxed);
+        let items = self.image_loader.get_items();
+        let requests: Vec<LoaderRequest> = self
+            .renderer
+            .retain_grid_resources(retained)
+            .into_iter()
+            .filter_map(|index| {
+                let (path, is_directory) = match items.get(index)? {
+                    FileItem::Directory(p) => (p.clone(), true),
+                    FileItem::Image(p) => (p.clone(), false),
+                };
+                Some(LoaderRequest {
+                    path,
+                    index,
+                    is_directory,
+                    shape: self.thumbnail_shape,
+                    generation,
+                })
+            })
+            .collect();
+        if !requests.is_empty() {
+            let _ = self.loader_tx.send(requests);
+        }
+    }
+
+    /// Uploads finished thumbnails, dropping those from a previous folder.
+    fn process_loader_responses(&mut self) {
+        let generation = self.grid_generation.load(Ordering::Relaxed);
+        while let Ok(msg) = self.response_rx.try_recv() {
+            if msg.generation != generation {
+                continue;
+            }
+            if let Some(image) = &msg.image {
+                self.renderer.update_grid_item_texture(msg.index, image);
+            }
+            if msg.failed {
+                self.renderer.mark_grid_item_failed(msg.index);
+            }
+            self.window.request_redraw();
+        }
+        self.finish_progressive_load();
+    }
+
+    fn handle_window_event(&mut self, event_loop: &ActiveEventLoop, event: WindowEvent) {
+        self.process_loader_responses();
+
+        let pending_number = self.input_handler.pending_number().map(str::to_string);
+        let input_action = match self.input_handler.handle_window_event(&event) {
+            // Escape backs out of compare and single view like Back, and only quits from the grid
+            InputAction::Exit if self.mode == ViewMode::Single => InputAction::Back,
+            action => action,
+        };
+        if self.input_handler.pending_number() != pending_number.as_deref() {
+            self.update_window_title();
+        }
+        if !self.input_handler.mouse_down && !self.input_handler.middle_down {
+            self.drag_target = None;
+        }
 
-        let start_row = ((-scroll - spacing) / (grid_size + spacing))
-            .floor()
-            .max(0.0) as u32;
-        let end_row = ((-scroll + window_size[1] + spacing) / (grid_size + spacing)).ceil() as u32;
+        self.perform_action(event_loop, input_action);
 
-        let start_idx = (start_row * cols) as usize;
-        let end_idx = (end_row * cols) as usize;
+        // Search only narrows the grid; the filter follows whatever has been typed
+        if self.mode != ViewMode::Grid {
+            self.input_handler.end_search();
+        }
+        if self.image_loader.get_filter() != self.input_handler.search_query() {
+            self.apply_search();
+        }
 
-        let visible: Vec<usize> = (start_idx..end_idx).collect();
-        let _ = self.visible_indices_tx.send(visible);
+        match &event {
+            WindowEvent::CursorMoved { position, .. }
+                if self.mode == ViewMode::Grid && self.on_scrollbar(position.x as f32) =>
+            {
+                self.renderer.wake_scrollbar();
+                self.window.request_redraw();
+            }
+            WindowEvent::CloseRequested => {
+                self.save_folder_state();
+                event_loop.exit();
+            }
+            WindowEvent::Resized(new_size) => {
+                if new_size.width > 0 && new_size.height > 0 {
+                    self.renderer.resize(new_size.width, new_size.height);
+                    self.save_window_state();
+                    // The column count may have changed, moving the selection to another row
+                    if self.mode == ViewMode::Grid {
+                        self.renderer.scroll_to_item(self.selected_index);
+                    }
+                    if refits_on_resize(&self.mode, self.is_actual_size, self.user_zoomed) {
+                        // Keeps the pan, unlike set_zoom_to_fit, so fit-width stays in place
+                        self.renderer.set_zoom(self.fit_zoom());
+                    }
+                    self.update_viewport();
+                }
+                self.window.request_redraw();
+            }
+            WindowEvent::Moved(_) => {
+                self.save_window_state();
+            }
+            WindowEvent::DroppedFile(path) => {
+                self.status_message = None;
+                self.open_path(path.clone());
+            }
+            WindowEvent::HoveredFile(path) => {
+                let name = path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                self.show_status(format!("Drop to open {}", name));
+            }
+            WindowEvent::HoveredFileCancelled => {
+                self.status_message = None;
+                self.update_window_title();
+            }
+            WindowEvent::RedrawRequested => {
+                self.update_status_bar();
+                self.update_breadcrumb();
+                self.renderer.render(
+                    self.mode == ViewMode::Grid,
+                    if self.mode == ViewMode::Grid {
+                        Some(self.selected_index)
+                    } else {
+                        None
+                    },
+                    &self.multi_selected,
+                );
+            }
+            _ => {}
+        }
     }
 
-    fn handle_window_event(&mut self, event: WindowEvent) {
-        while let Ok(msg) = self.response_rx.try_recv() {
-            self.renderer
-                .update_grid_item_texture(msg.index, &msg.image);
-            self.window.request_redraw();
+    /// Carries out an action from the keyboard or mouse, or a zoom preset whose
+    /// digit timed out.
+    fn perform_action(&mut self, event_loop: &ActiveEventLoop, input_action: InputAction) {
+        // Any manual navigation takes over from a running slideshow
+        if matches!(
+            input_action,
+            InputAction::NextImage
+                | InputAction::PrevImage
+                | InputAction::SelectLeft
+                | InputAction::SelectRight
+                | InputAction::First
+                | InputAction::Last
+                | InputAction::GoTo(_)
+                | InputAction::Back
+                | InputAction::ParentDir
+        ) {
+            self.set_slideshow(false);
         }
 
-        let input_action = self.input_handler.handle_window_event(&event);
         match input_action {
             InputAction::None => {}
+            InputAction::NextImage | InputAction::SelectRight if self.compare_index.is_some() => {
+                self.step_compare(true);
+            }
+            InputAction::PrevImage | InputAction::SelectLeft if self.compare_index.is_some() => {
+                self.step_compare(false);
+            }
+            InputAction::Back if self.compare_index.is_some() => {
+                self.set_compare(None);
+            }
             InputAction::NextImage => {
                 if self.mode == ViewMode::Single {
-                    if let Some(img) = self.image_loader.next_image() {
-                        self.is_actual_size = false;
-                        self.renderer.set_filtering(false, None);
-                        self.renderer.update_texture(&img);
-                        self.set_zoom_to_fit();
-                        self.update_window_title();
-                        if self.show_metadata {
-                            self.current_metadata = self.image_loader.get_current_metadata();
-                            self.update_metadata_overlay();
-                        }
-                        self.window.request_redraw();
-                    }
+                    self.image_loader.select_next();
+                    self.show_current();
                 } else if self.mode == ViewMode::Grid {
-                    self.move_selection(1, 0);
+                    // Next/previous walk the listing in order, across row ends
+                    let last = self.image_loader.get_items().len().saturating_sub(1);
+                    self.move_selection_to((self.selected_index + 1).min(last));
                 }
             }
             InputAction::PrevImage => {
                 if self.mode == ViewMode::Single {
-                    if let Some(img) = self.image_loader.prev_image() {
-                        self.is_actual_size = false;
-                        self.renderer.set_filtering(false, None);
-                        self.renderer.update_texture(&img);
-                        self.set_zoom_to_fit();
-                        self.update_window_title();
-                        if self.show_metadata {
-                            self.current_metadata = self.image_loader.get_current_metadata();
-                            self.update_metadata_overlay();
-                        }
-                        self.window.request_redraw();
-                    }
+                    self.image_loader.select_prev();
+                    self.show_current();
                 } else if self.mode == ViewMode::Grid {
-                    self.move_selection(-1, 0);
+                    self.move_selection_to(self.selected_index.saturating_sub(1));
                 }
             }
-            InputAction::Zoom(amount) => {
+            InputAction::WheelResize(amount, ..) if self.mode == ViewMode::Grid => {
+                let cell = self.renderer.get_grid_layout().cell;
+                self.renderer.set_grid_cell_size(cell * 1.1f32.powf(amount));
+                self.renderer.scroll_to_item(self.selected_index);
+                self.update_viewport();
+                self.window.request_redraw();
+            }
+            InputAction::WheelPage(amount, ..) if self.mode == ViewMode::Grid => {
+                let layout = self.renderer.get_grid_layout();
+                let rows = layout
+                    .rows_per_page(self.renderer.get_window_size()[1])
+                    .max(1);
+                self.renderer
+                    .scroll_grid(amount.signum() * rows as f32 * layout.stride());
+                self.update_viewport();
+                self.window.request_redraw();
+            }
+            InputAction::Zoom(amount, x, y)
+            | InputAction::WheelResize(amount, x, y)
+            | InputAction::WheelPage(amount, x, y) => {
                 if self.mode == ViewMode::Grid {
                     // Mouse wheel scrolls in grid mode
                     self.renderer.scroll_grid(amount * 50.0);
                     self.update_viewport();
+                } else if self.fit_mode == FitMode::FitWidth && !self.is_actual_size {
+                    // Fit-width reads like a page, so the wheel scrolls it
+                    self.renderer.pan(0.0, amount * 50.0);
                 } else {
-                    self.renderer.zoom(amount);
+                    self.renderer.zoom(amount, Some([x as f32, y as f32]));
+                    self.user_zoomed = true;
                 }
                 self.window.request_redraw();
             }
-            InputAction::Pan(dx, dy) => {
+            InputAction::TouchScroll(dx, dy, ended) => {
+                // Whichever axis the gesture mostly moves along wins, so a
+                // slightly slanted swipe doesn't drift sideways or zoom
+                let vertical = dy.abs() >= dx.abs();
                 if self.mode == ViewMode::Grid {
-                    self.renderer.scroll_grid(dy);
-                } else {
-                    self.renderer.pan(dx, dy);
+                    if vertical && dy != 0.0 {
+                        self.renderer.touch_scroll_grid(dy);
+                    }
+                    if ended {
+                        self.renderer.release_touch_scroll();
+                    }
+                    self.update_viewport();
+                } else if !vertical {
+                    self.renderer.pan(dx, 0.0);
+                } else if self.fit_mode == FitMode::FitWidth && !self.is_actual_size {
+                    self.renderer.pan(0.0, dy);
+                } else if dy != 0.0 {
+                    let anchor = [
+                        self.input_handler.last_mouse_x as f32,
+                        self.input_handler.last_mouse_y as f32,
+                    ];
+                    self.renderer.zoom(dy / PIXELS_PER_WHEEL_STEP, Some(anchor));
+                    self.user_zoomed = true;
                 }
                 self.window.request_redraw();
             }
-            InputAction::Click(x, y) => {
-                if self.mode == ViewMode::Grid {
-                    let grid_size = 250.0;
-                    let spacing = 20.0;
-                    let scroll = self.renderer.grid_scroll;
-
-                    let col = ((x - spacing as f64) / (grid_size + spacing) as f64).floor() as i32;
-                    let row = (((y - scroll as f64) - spacing as f64)
-                        / (grid_size + spacing) as f64)
-                        .floor() as i32;
-
-                    let window_width = self.renderer.get_window_size()[0];
-                    let cols = (window_width / (grid_size + spacing)).floor().max(1.0) as u32;
-
-                    if col >= 0 && col < cols as i32 && row >= 0 {
-                        let index = (row as u32 * cols + col as u32) as usize;
-                        let item_opt = self.image_loader.get_items().get(index).cloned();
-                        if let Some(item) = item_opt {
-                            self.selected_index = index;
-                            match item {
-                                FileItem::Directory(p) => {
-                                    self.image_loader.set_path(p);
-                                    self.load_grid();
-                                }
-                                FileItem::Image(p) => {
-                                    if let Some(img) = self.image_loader.open_image(&p) {
-                                        self.renderer.update_texture(&img);
-                                        self.set_zoom_to_fit();
-                                        self.renderer.set_view_mode(false);
-                                        self.mode = ViewMode::Single;
-                                        if self.show_metadata {
-                                            self.current_metadata =
-                                                self.image_loader.get_current_metadata();
-                                            self.update_metadata_overlay();
-                                        }
-                                    }
-                                }
+            InputAction::Pan(dx, dy) => {
+                let window_width = self.renderer.get_window_size()[0];
+                let cursor_x = self.input_handler.last_mouse_x as f32;
+                let cursor_y = self.input_handler.last_mouse_y as f32;
+                let target = match self.drag_target {
+                    Some(target) => target,
+                    None => {
+                        // Decide once per drag, from where it started
+                        let target = self.drag_target_at(cursor_x - dx, cursor_y - dy);
+                        self.drag_target = Some(target);
+                        target
+                    }
+                };
+
+                match target {
+                    DragTarget::Scrollbar(grab) => {
+                        self.renderer.scroll_grid_to_thumb(cursor_y - grab);
+                        self.update_viewport();
+                    }
+                    _ if self.mode == ViewMode::Grid => self.renderer.drag_grid(dy),
+                    DragTarget::CompareDivider => {
+                        self.renderer.set_compare_split(cursor_x / window_width)
+                    }
+                    DragTarget::Content => self.renderer.pan(dx, dy),
+                }
+                self.window.request_redraw();
+            }
+            InputAction::Click(x, y) | InputAction::DoubleClick(x, y)
+                if self.mode == ViewMode::Grid && self.on_scrollbar(x as f32) =>
+            {
+                // A click on the track centers the thumb there
+                self.input_handler.cancel_double_click();
+                if let Some([_, top, _, length]) = self.renderer.scrollbar_thumb() {
+                    let y = y as f32;
+                    if !(top..top + length).contains(&y) {
+                        self.renderer.scroll_grid_to_thumb(y - length / 2.0);
+                        self.update_viewport();
+                        self.window.request_redraw();
+                    }
+                }
+            }
+            InputAction::Click(x, y) | InputAction::DoubleClick(x, y)
+                if self.mode == ViewMode::Grid && (y as f32) < GRID_HEADER_HEIGHT =>
+            {
+                self.input_handler.cancel_double_click();
+                let target = self
+                    .breadcrumb
+                    .as_ref()
+                    .and_then(|bar| bar.target_at(x as f32))
+                    .map(Path::to_path_buf);
+                if let Some(folder) = target {
+                    self.change_folder(folder);
+                    self.update_window_title();
+                    self.window.request_redraw();
+                }
+            }
+            InputAction::Click(x, y) | InputAction::DoubleClick(x, y)
+                if self.mode == ViewMode::Grid =>
+            {
+                let hit = self.renderer.get_grid_layout().item_at(
+                    x,
+                    y,
+                    self.renderer.grid_scroll,
+                    self.renderer.get_window_size()[0],
+                );
+
+                if let Some(index) = hit {
+                    let item_opt = self.image_loader.get_items().get(index).cloned();
+                    if let Some(item) = item_opt {
+                        self.selected_index = index;
+                        self.multi_selected.clear();
+                        self.selection_anchor = Some(index);
+                        match item {
+                            FileItem::Directory(p) => {
+                                self.change_folder(p);
+                            }
+                            FileItem::Image(p) => {
+                                self.open_image_internal(&p);
                             }
-                            self.update_window_title();
-                            self.window.request_redraw();
                         }
+                        // The click that opened this view must not pair up with the next one
+                        self.input_handler.cancel_double_click();
+                        self.update_window_title();
+                        self.window.request_redraw();
+                    }
+                }
+            }
+            InputAction::Click(..) => {}
+            InputAction::ToggleSelectClick(x, y) | InputAction::RangeSelectClick(x, y)
+                if self.mode == ViewMode::Grid =>
+            {
+                let hit = self.renderer.get_grid_layout().item_at(
+                    x,
+                    y,
+                    self.renderer.grid_scroll,
+                    self.renderer.get_window_size()[0],
+                );
+                if let Some(index) = hit.filter(|&i| i < self.image_loader.get_items().len()) {
+                    if matches!(input_action, InputAction::RangeSelectClick(..)) {
+                        let anchor = self.selection_anchor.unwrap_or(self.selected_index);
+                        self.select_range(anchor, index);
+                    } else {
+                        self.toggle_selected(index);
+                        self.selection_anchor = Some(index);
                     }
+                    self.selected_index = index;
+                    self.update_window_title();
+                    self.window.request_redraw();
                 }
             }
+            InputAction::ToggleSelectClick(..) | InputAction::RangeSelectClick(..) => {}
             InputAction::Back => {
+                // Going up a folder is ParentDir's job, so Back in the grid does nothing
                 if self.mode == ViewMode::Single {
-                    self.is_actual_size = false;
-                    self.renderer.set_filtering(false, None);
-                    self.mode = ViewMode::Grid;
-                    self.renderer.set_view_mode(true);
-                } else {
-                    let mut path = self.image_loader.get_path().to_path_buf();
-                    if path.pop() {
-                        self.image_loader.set_path(path);
-                        self.load_grid();
-                    }
+                    self.leave_single_view();
+                    self.update_window_title();
+                    self.window.request_redraw();
+                }
+            }
+            InputAction::ParentDir => {
+                if self.compare_index.is_some() {
+                    self.set_compare(None);
+                }
+                if self.mode == ViewMode::Single {
+                    self.leave_single_view();
+                }
+                let folder = self.image_loader.get_path().to_path_buf();
+                if let Some(parent) = folder.parent() {
+                    self.change_folder(parent.to_path_buf());
+                    // Land on the folder just left, ready to go back into it
+                    self.select_grid_path(&folder);
                 }
                 self.update_window_title();
                 self.window.request_redraw();
             }
+            InputAction::DoubleClick(x, y) => {
+                self.toggle_actual_size(Some([x as f32, y as f32]));
+            }
             InputAction::ActualSize => {
                 if self.mode == ViewMode::Single {
-                    if !self.is_actual_size {
-                        self.saved_zoom = self.renderer.get_zoom();
-                        self.is_actual_size = true;
-                        self.renderer.set_zoom(1.0);
-                    } else {
-                        self.is_actual_size = false;
-                        self.renderer.set_zoom(self.saved_zoom);
-                    }
-
-                    if let Some(img) = self.image_loader.load_current_image() {
-                        self.renderer.set_filtering(self.is_actual_size, Some(&img));
-                    }
+                    self.toggle_actual_size(None);
+                }
+            }
+            InputAction::FitToWindow => {
+                if self.mode == ViewMode::Single {
+                    // Also drops the nearest-neighbour sampling actual size uses
+                    self.is_actual_size = false;
+                    self.apply_filtering();
+                    self.renderer.center_image();
+                    self.set_zoom_to_fit();
                     self.window.request_redraw();
                 }
             }
+            InputAction::ZoomPreset(preset) => {
+                if self.mode == ViewMode::Single {
+                    match preset {
+                        Some(zoom) => {
+                            if self.is_actual_size {
+                                self.is_actual_size = false;
+                                self.apply_filtering();
+                            }
+                            let anchor = [
+                                self.input_handler.last_mouse_x as f32,
+                                self.input_handler.last_mouse_y as f32,
+                            ];
+                            self.renderer.zoom_to(zoom, Some(anchor));
+                            self.user_zoomed = true;
+                            self.window.request_redraw();
+                        }
+                        None => self.set_zoom_mode(self.fit_mode),
+                    }
+                }
+            }
             InputAction::SelectUp => {
                 if self.mode == ViewMode::Grid {
                     self.move_selection(0, -1);
@@ -602,79 +1584,292 @@ impl AppState {
                 if self.mode == ViewMode::Grid {
                     self.move_selection(-1, 0);
                 } else if self.mode == ViewMode::Single {
-                    if let Some(img) = self.image_loader.prev_image() {
-                        self.renderer.update_texture(&img);
-                        self.set_zoom_to_fit();
-                        self.update_window_title();
-                        if self.show_metadata {
-                            self.current_metadata = self.image_loader.get_current_metadata();
-                            self.update_metadata_overlay();
+                    self.image_loader.select_prev();
+                    self.show_current();
+                }
+            }
+            InputAction::SelectRight => {
+                if self.mode == ViewMode::Grid {
+                    self.move_selection(1, 0);
+                } else if self.mode == ViewMode::Single {
+                    self.image_loader.select_next();
+                    self.show_current();
+                }
+            }
+            InputAction::OpenSelected => {
+                if self.mode == ViewMode::Grid {
+                    let item_opt = self
+                        .image_loader
+                        .get_items()
+                        .get(self.selected_index)
+                        .cloned();
+                    if let Some(item) = item_opt {
+                        match item {
+                            FileItem::Directory(p) => {
+                                self.change_folder(p);
+                                self.update_window_title();
+                            }
+                            FileItem::Image(p) => {
+                                self.open_image_internal(&p);
+                            }
                         }
                         self.window.request_redraw();
                     }
                 }
             }
-            InputAction::SelectRight => {
-                if self.mode == ViewMode::Grid {
-                    self.move_selection(1, 0);
-                } else if self.mode == ViewMode::Single {
-                    if let Some(img) = self.image_loader.next_image() {
-                        self.renderer.update_texture(&img);
-                        self.set_zoom_to_fit();
-                        self.update_window_title();
-                        if self.show_metadata {
-                            self.current_metadata = self.image_loader.get_current_metadata();
-                            self.update_metadata_overlay();
-                        }
-                        self.window.request_redraw();
+            InputAction::PageUp => {
+                if self.mode == ViewMode::Grid {
+                    self.move_selection_by_page(-1);
+                }
+            }
+            InputAction::PageDown => {
+                if self.mode == ViewMode::Grid {
+                    self.move_selection_by_page(1);
+                }
+            }
+            InputAction::First => self.jump_to(0),
+            InputAction::Last => self.jump_to(usize::MAX),
+            InputAction::GoTo(number) => self.jump_to(number.saturating_sub(1)),
+            InputAction::Exit => {
+                event_loop.exit();
+            }
+            InputAction::ZoomIn | InputAction::ZoomOut => {
+                let step = if matches!(input_action, InputAction::ZoomIn) {
+                    1.0
+                } else {
+                    -1.0
+                };
+                if self.next_slide_at.is_some() {
+                    // While a slideshow runs, +/- adjust its interval instead of zooming
+                    let interval = if step > 0.0 {
+                        self.slideshow_interval + Duration::from_secs(1)
+                    } else {
+                        self.slideshow_interval
+                            .saturating_sub(Duration::from_secs(1))
+                    };
+                    self.slideshow_interval =
+                        interval.clamp(MIN_SLIDESHOW_INTERVAL, MAX_SLIDESHOW_INTERVAL);
+                    self.set_slideshow(true);
+                    self.update_window_title();
+                } else if self.mode == ViewMode::Grid {
+                    let cell = self.renderer.get_grid_layout().cell;
+                    self.renderer.set_grid_cell_size(cell * 1.25f32.powf(step));
+                    self.renderer.scroll_to_item(self.selected_index);
+                    self.update_viewport();
+                } else {
+                    self.renderer.zoom(step * 2.0, None);
+                    self.user_zoomed = true;
+                }
+                self.window.request_redraw();
+            }
+            InputAction::ToggleFullscreen => {
+                if self.window.fullscreen().is_some() {
+                    self.window.set_fullscreen(None);
+                    // Put the window back where it was; the saved settings only track windowed geometry
+                    if let Some(settings) = self.cache.get_window_settings() {
+                        if settings.maximized {
+                            self.window.set_maximized(true);
+                        } else {
+                            let _ = self
+                                .window
+                                .request_inner_size(winit::dpi::PhysicalSize::new(
+                                    settings.width,
+                                    settings.height,
+                                ));
+                            self.window
+                                .set_outer_position(winit::dpi::PhysicalPosition::new(
+                                    settings.x, settings.y,
+                                ));
+                        }
+                    }
+                } else {
+                    self.save_window_state();
+                    self.window
+                        .set_fullscreen(Some(Fullscreen::Borderless(None)));
+                }
+            }
+            InputAction::CycleSortMode => {
+                let selected = self.selected_path();
+                let mode = self.image_loader.get_sort_mode().next();
+                self.image_loader.set_sort_mode(mode);
+                self.load_grid();
+
+                // Keep the same file selected after the re-sort
+                if let Some(path) = selected {
+                    self.select_grid_path(&path);
+                }
+                self.update_window_title();
+                self.window.request_redraw();
+            }
+            InputAction::Reload => self.reload(),
+            InputAction::Copy => {
+                if self.mode == ViewMode::Single {
+                    // Copy exactly what is on screen: the current animation frame, or the oriented image
+                    let copied = if let Some((frame, _)) = self.frames.get(self.frame_index) {
+                        self.clipboard.set_image(frame)
+                    } else if let Some(img) = self.image_loader.load_current_image() {
+                        self.clipboard.set_image(&img)
+                    } else {
+                        false
+                    };
+                    if copied {
+                        self.show_status("Copied image to clipboard".to_string());
+                    }
+                }
+            }
+            InputAction::ToggleSmoothMotion => {
+                let enabled = !self.renderer.is_smooth_motion();
+                self.renderer.set_smooth_motion(enabled);
+                self.show_status(format!(
+                    "Smooth zoom and scroll {}",
+                    if enabled { "on" } else { "off" }
+                ));
+                self.window.request_redraw();
+            }
+            InputAction::ToggleFilter => {
+                self.nearest_filter = !self.nearest_filter;
+                self.apply_filtering();
+                self.show_status(format!(
+                    "Nearest-neighbour filtering {}",
+                    if self.nearest_filter { "on" } else { "off" }
+                ));
+                self.window.request_redraw();
+            }
+            InputAction::ToggleThumbnailShape => {
+                if self.mode == ViewMode::Grid {
+                    let selected = self.selected_path();
+                    self.thumbnail_shape = self.thumbnail_shape.toggled();
+                    // Requests for the old shape are dropped with the old generation
+                    self.load_grid();
+                    if let Some(path) = selected {
+                        self.select_grid_path(&path);
+                    }
+                    self.show_status(format!("Thumbnails: {}", self.thumbnail_shape.label()));
+                    self.window.request_redraw();
+                }
+            }
+            InputAction::CycleFitMode => {
+                let mode = self.fit_mode.next();
+                if self.mode == ViewMode::Single {
+                    self.set_zoom_mode(mode);
+                } else {
+                    self.fit_mode = mode;
+                }
+                self.show_status(format!("Zoom mode: {}", mode.label()));
+            }
+            InputAction::CopyPath => {
+                if let Some(path) = self.selected_path() {
+                    if self.clipboard.set_text(&shell::plain_path(&path)) {
+                        self.show_status("Copied path to clipboard".to_string());
+                    }
+                }
+            }
+            InputAction::SetWallpaper => {
+                if self.mode == ViewMode::Single {
+                    if let Some(path) = self.image_loader.get_current_path().cloned() {
+                        // The desktop may wait on a permission prompt, so this runs off the UI thread
+                        let proxy = self.event_loop_proxy.clone();
+                        thread::spawn(move || {
+                            let result = shell::set_wallpaper(&path);
+                            let _ = proxy.send_event(UserEvent::WallpaperSet(path, result));
+                        });
+                        self.show_status("Setting wallpaper...".to_string());
+                    }
+                }
+            }
+            InputAction::RevealInExplorer => {
+                if let Some(path) = self.selected_path() {
+                    if let Err(e) = shell::reveal_in_file_manager(&path) {
+                        log::warn!("Failed to open file manager for {}: {}", path.display(), e);
+                        self.show_status("Could not open the file manager".to_string());
                     }
                 }
             }
-            InputAction::OpenSelected => {
-                if self.mode == ViewMode::Grid {
-                    let item_opt = self
-                        .image_loader
-                        .get_items()
-                        .get(self.selected_index)
-                        .cloned();
-                    if let Some(item) = item_opt {
-                        match item {
-                            FileItem::Directory(p) => {
-                                self.image_loader.set_path(p);
-                                self.load_grid();
-                                self.update_window_title();
-                            }
-                            FileItem::Image(p) => {
-                                if let Some(img) = self.image_loader.open_image(&p) {
-                                    self.renderer.update_texture(&img);
-                                    self.set_zoom_to_fit();
-                                    self.renderer.set_view_mode(false);
-                                    self.mode = ViewMode::Single;
-                                    self.update_window_title();
-                                    if self.show_metadata {
-                                        self.current_metadata =
-                                            self.image_loader.get_current_metadata();
-                                        self.update_metadata_overlay();
-                                    }
-                                }
-                            }
-                        }
-                        self.window.request_redraw();
+            InputAction::OpenWith => {
+                if let Some(path) = self.selected_path().filter(|p| p.is_file()) {
+                    if let Err(e) = shell::open_with_editor(&path) {
+                        log::warn!("Failed to open {} in the editor: {}", path.display(), e);
+                        self.show_status(if e.kind() == std::io::ErrorKind::NotFound {
+                            "Editor not found, check FASTVIEW_EDITOR".to_string()
+                        } else {
+                            "Could not open the editor".to_string()
+                        });
                     }
                 }
             }
-            InputAction::PageUp => {
-                if self.mode == ViewMode::Grid {
-                    self.move_selection_by_page(-1);
+            InputAction::ToggleStatusBar => {
+                self.show_status_bar = !self.show_status_bar;
+                self.window.request_redraw();
+            }
+            InputAction::ToggleUpscale => {
+                self.upscale_to_fit = !self.upscale_to_fit;
+                if self.mode == ViewMode::Single && !self.is_actual_size {
+                    self.set_zoom_to_fit();
+                    self.window.request_redraw();
                 }
+                self.show_status(format!(
+                    "Upscale small images: {}",
+                    if self.upscale_to_fit { "on" } else { "off" }
+                ));
             }
-            InputAction::PageDown => {
-                if self.mode == ViewMode::Grid {
-                    self.move_selection_by_page(1);
+            InputAction::ToggleCompare => {
+                if self.compare_index.is_some() {
+                    self.set_compare(None);
+                } else {
+                    self.start_compare();
                 }
             }
-            InputAction::Exit => {
-                std::process::exit(0);
+            InputAction::ToggleCompareSync => {
+                if self.compare_index.is_some() {
+                    let synced = !self.renderer.is_compare_synced();
+                    self.renderer.set_compare_synced(synced);
+                    self.show_status(format!(
+                        "Compare zoom/pan {}",
+                        if synced { "synced" } else { "independent" }
+                    ));
+                    self.window.request_redraw();
+                }
+            }
+            InputAction::CycleBackground => {
+                let background = self.renderer.get_background().next();
+                self.renderer.set_background(background);
+                self.show_status(format!("Background: {}", background.label()));
+                self.window.request_redraw();
+            }
+            InputAction::ToggleVsync => {
+                let mode = if self.renderer.get_present_mode() == wgpu::PresentMode::Fifo {
+                    wgpu::PresentMode::Mailbox
+                } else {
+                    wgpu::PresentMode::Fifo
+                };
+                let message = if self.renderer.set_present_mode(mode) {
+                    format!("Present mode: {:?}", mode)
+                } else {
+                    format!("Present mode {:?} is not supported", mode)
+                };
+                self.show_status(message);
+                self.window.request_redraw();
+            }
+            InputAction::RotateCW | InputAction::RotateCCW => {
+                if self.mode == ViewMode::Single {
+                    self.rotate_current(matches!(input_action, InputAction::RotateCW));
+                }
+            }
+            InputAction::SaveRotation => {
+                if self.mode == ViewMode::Single {
+                    self.save_current_rotation();
+                }
+            }
+            InputAction::Delete => {
+                self.delete_current();
+            }
+            InputAction::MoveToSortBin => {
+                self.move_to_sort_bin();
+            }
+            InputAction::ToggleSlideshow => {
+                if self.mode == ViewMode::Single {
+                    self.set_slideshow(self.next_slide_at.is_none());
+                }
             }
             InputAction::ToggleMetadata => {
                 if self.mode == ViewMode::Single {
@@ -689,37 +1884,22 @@ impl AppState {
                 }
             }
         }
+    }
 
-        match &event {
-            WindowEvent::CloseRequested => {
-                std::process::exit(0);
-            }
-            WindowEvent::Resized(new_size) => {
-                if new_size.width > 0 && new_size.height > 0 {
-                    self.renderer.resize(new_size.width, new_size.height);
-                    self.save_window_state();
-                    self.update_viewport();
+    fn save_window_state(&self) {
+        if self.window.fullscreen().is_some() {
+            return;
+        }
+        if self.window.is_maximized() {
+            // Keep the windowed geometry to return to when un-maximizing next session
+            if let Some(mut settings) = self.cache.get_window_settings() {
+                if !settings.maximized {
+                    settings.maximized = true;
+                    self.cache.set_window_settings(&settings);
                 }
-                self.window.request_redraw();
-            }
-            WindowEvent::Moved(_) => {
-                self.save_window_state();
-            }
-            WindowEvent::RedrawRequested => {
-                self.renderer.render(
-                    self.mode == ViewMode::Grid,
-                    if self.mode == ViewMode::Grid {
-                        Some(self.selected_index)
-                    } else {
-                        None
-                    },
-                );
+                return;
             }
-            _ => {}
         }
-    }
-
-    fn save_window_state(&self) {
         if let Ok(pos) = self.window.outer_position() {
             let size = self.window.inner_size();
             self.cache.set_window_settings(&WindowSettings {
@@ -727,33 +1907,80 @@ impl AppState {
                 y: pos.y,
                 width: size.width,
                 height: size.height,
+                maximized: self.window.is_maximized(),
             });
         }
     }
 
+    /// Whether `x` is over the grid scrollbar, which only exists when the grid overflows.
+    fn on_scrollbar(&self, x: f32) -> bool {
+        self.renderer.scrollbar_thumb().is_some()
+            && x >= self.renderer.get_window_size()[0] - SCROLLBAR_HIT_WIDTH
+    }
+
+    fn drag_target_at(&self, x: f32, y: f32) -> DragTarget {
+        if self.mode == ViewMode::Grid && self.on_scrollbar(x) {
+            let [_, top, _, length] = self.renderer.scrollbar_thumb().unwrap_or_default();
+            // Grabbing the track rather than the thumb first jumps the thumb under the cursor
+            let grab = if (top..top + length).contains(&y) {
+                y - top
+            } else {
+                length / 2.0
+            };
+            return DragTarget::Scrollbar(grab);
+        }
+        let divider_x = self.renderer.get_compare_split() * self.renderer.get_window_size()[0];
+        if self.renderer.is_comparing() && (x - divider_x).abs() <= DIVIDER_GRAB_PIXELS {
+            return DragTarget::CompareDivider;
+        }
+        DragTarget::Content
+    }
+
+    fn is_image_item(&self, index: usize) -> bool {
+        matches!(
+            self.image_loader.get_items().get(index),
+            Some(FileItem::Image(_))
+        )
+    }
+
+    /// Adds a grid image to the multi-selection, or removes it. Folders can't be selected.
+    fn toggle_selected(&mut self, index: usize) {
+        if !self.multi_selected.remove(&index) && self.is_image_item(index) {
+            self.multi_selected.insert(index);
+        }
+    }
+
+    /// Replaces the multi-selection with the images between `from` and `to`, inclusive.
+    fn select_range(&mut self, from: usize, to: usize) {
+        self.multi_selected = (from.min(to)..=from.max(to))
+            .filter(|&i| self.is_image_item(i))
+            .collect();
+    }
+
     fn move_selection(&mut self, dx: i32, dy: i32) {
         let total_items = self.image_loader.get_items().len();
         if total_items == 0 {
             return;
         }
 
-        let grid_size = 250.0;
-        let spacing = 20.0;
         let window_width = self.renderer.get_window_size()[0];
-        let cols = (window_width / (grid_size + spacing)).floor().max(1.0) as u32;
-
-        let mut index = self.selected_index as i32;
-        if dx != 0 {
-            index += dx;
-        }
-        if dy != 0 {
-            index += dy * cols as i32;
-        }
+        let cols = self.renderer.get_grid_layout().columns(window_width);
+
+        // Ensures thumbnails start loading for the new view
+        self.move_selection_to(GridLayout::step(
+            self.selected_index,
+            dx,
+            dy,
+            cols,
+            total_items,
+        ));
+    }
 
-        if index >= 0 && index < total_items as i32 {
-            self.selected_index = index as usize;
-            self.renderer.scroll_to_item(self.selected_index);
-            self.update_viewport(); // Ensure thumbnails start loading for new view
+    fn move_selection_to(&mut self, index: usize) {
+        if self.selected_index != index {
+            self.selected_index = index;
+            self.renderer.scroll_to_item(index);
+            self.update_viewport();
             self.window.request_redraw();
         }
     }
@@ -764,12 +1991,11 @@ impl AppState {
             return;
         }
 
-        let grid_size = 250.0;
-        let spacing = 20.0;
+        let layout = self.renderer.get_grid_layout();
         let [win_width, win_height] = self.renderer.get_window_size();
 
-        let cols = (win_width / (grid_size + spacing)).floor().max(1.0) as u32;
-        let rows_per_page = (win_height / (grid_size + spacing)).floor().max(1.0) as u32;
+        let cols = layout.columns(win_width);
+        let rows_per_page = layout.rows_per_page(win_height);
         let items_per_page = (rows_per_page * cols) as i32;
 
         let mut index = self.selected_index as i32 + dir * items_per_page;
@@ -794,20 +2020,31 @@ impl AppState {
         let overlay_width = img_screen_width.min(win_size[0] * 0.8).max(250.0);
         let max_overlay_height = (win_size[1] / 3.0) as u32;
 
-        let font = std::fs::read("C:\\Windows\\Fonts\\arial.ttf")
-            .ok()
-            .and_then(|data| FontArc::try_from_vec(data).ok());
+        if self.font.is_none() {
+            self.font = font_loader::load_font();
+        }
 
-        if let Some(font) = font {
+        if let Some(font) = self.font.clone() {
             let scale = PxScale::from(16.0);
             let line_height = 22.0;
             let padding = 10.0;
 
-            let lines = self
-                .current_metadata
-                .as_ref()
-                .map(|m| m.get_metadata_lines())
-                .unwrap_or_else(|| vec!["No metadata found".to_string()]);
+            let mut lines = Vec::new();
+            if let Some(path) = self.image_loader.get_current_path() {
+                let [w, h] = self.renderer.get_image_size();
+                lines.push(
+                    path.file_name()
+                        .map(|n| n.to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                );
+                lines.push(format!("Dimensions: {}x{}", w as u32, h as u32));
+            }
+            lines.extend(
+                self.current_metadata
+                    .as_ref()
+                    .map(|m| m.get_metadata_lines())
+                    .unwrap_or_else(|| vec!["No metadata found".to_string()]),
+            );
 
             let content_height = (lines.len() as f32 * line_height + padding * 2.0).ceil() as u32;
             let overlay_height = content_height.min(max_overlay_height).max(40);
@@ -858,16 +2095,137 @@ impl AppState {
         }
     }
 
+    fn status_bar_text(&self) -> String {
+        let sort = self.image_loader.get_sort_mode().label();
+        if self.mode == ViewMode::Grid {
+            let total = self.image_loader.get_items().len();
+            let position = if total == 0 {
+                0
+            } else {
+                self.selected_index + 1
+            };
+            return format!(
+                "[{}/{}]   {} images, {}   {}",
+                position,
+                total,
+                self.image_loader.get_image_count(),
+                format_bytes(self.image_loader.get_total_size()),
+                sort
+            );
+        }
+        let [w, h] = self.renderer.get_image_size();
+        format!(
+            "{:.0}%   {}x{}   [{}/{}]   {}",
+            self.renderer.get_zoom() * 100.0,
+            w as u32,
+            h as u32,
+            self.image_loader.get_current_index() + 1,
+            self.image_loader.get_image_count(),
+            sort
+        )
+    }
+
+    /// Redraws the status bar along the bottom edge if its text or the window size changed.
+    fn update_status_bar(&mut self) {
+        if !self.show_status_bar {
+            if self.status_bar_shown.take().is_some() {
+                self.renderer.set_status_bar(None, [0.0; 4]);
+            }
+            return;
+        }
+
+        let text = self.status_bar_text();
+        let win_size = self.renderer.get_window_size();
+        if self
+            .status_bar_shown
+            .as_ref()
+            .is_some_and(|(shown, size)| *shown == text && *size == win_size)
+        {
+            return;
+        }
+
+        if self.font.is_none() {
+            self.font = font_loader::load_font();
+        }
+        let Some(font) = self.font.clone() else {
+            return;
+        };
+
+        let width = (win_size[0] as u32).max(1);
+        let mut bar = RgbaImage::from_pixel(width, STATUS_BAR_HEIGHT, Rgba([0, 0, 0, 200]));
+        draw_text_mut(
+            &mut bar,
+            Rgba([220, 220, 220, 255]),
+            10,
+            4,
+            PxScale::from(15.0),
+            &font,
+            &text,
+        );
+        let height = STATUS_BAR_HEIGHT as f32;
+        self.renderer.set_status_bar(
+            Some(&bar),
+            [0.0, win_size[1] - height, width as f32, height],
+        );
+        self.status_bar_shown = Some((text, win_size));
+    }
+
+    /// Redraws the grid's path bar if the folder or the window width changed.
+    fn update_breadcrumb(&mut self) {
+        if self.mode != ViewMode::Grid {
+            return;
+        }
+        let folder = self.image_loader.get_path().to_path_buf();
+        let width = (self.renderer.get_window_size()[0] as u32).max(1);
+        if self
+            .breadcrumb
+            .as_ref()
+            .is_some_and(|bar| bar.is_for(&folder, width))
+        {
+            return;
+        }
+
+        if self.font.is_none() {
+            self.font = font_loader::load_font();
+        }
+        let Some(font) = self.font.clone() else {
+            return;
+        };
+        let bar = Breadcrumb::new(&folder, width, &font);
+        let height = GRID_HEADER_HEIGHT as u32;
+        self.renderer.set_breadcrumb(
+            &bar.draw(height, &font),
+            [0.0, 0.0, width as f32, height as f32],
+        );
+        self.breadcrumb = Some(bar);
+    }
+
     fn update_window_title(&self) {
         let mut title = String::from("FastView");
         if self.mode == ViewMode::Grid {
             title.push_str(" - Browsing: ");
             title.push_str(self.image_loader.get_path().to_string_lossy().as_ref());
+            title.push_str(&format!(" [{}]", self.image_loader.get_sort_mode().label()));
+            title.push_str(&format!(
+                " - {} images, {}",
+                self.image_loader.get_image_count(),
+                format_bytes(self.image_loader.get_total_size())
+            ));
+            if !self.multi_selected.is_empty() {
+                title.push_str(&format!(" - {} selected", self.multi_selected.len()));
+            }
+            if let Some(query) = self.input_handler.search_query() {
+                title.push_str(&format!(
+                    " - Search: {} ({} found)",
+                    query,
+                    self.image_loader.get_items().len()
+                ));
+            }
         } else {
             if let Some(path) = self.image_loader.get_current_path() {
                 let filename = path
                     .file_name()
-                    .map(|n| n.to_string_lossy().to_string())
+                    .map(|n| truncate_middle(&n.to_string_lossy(), MAX_TITLE_FILENAME_CHARS))
                     .unwrap_or_else(|| "Unknown".to_string());
                 title.push_str(&format!(
                     " - {} [{}/{}]",
@@ -876,11 +2234,287 @@ impl AppState {
                     self.image_loader.get_image_count()
                 ));
             }
+            if let Some(other) = self
+                .compare_index
+                .and_then(|i| self.image_loader.get_image_path(i))
+            {
+                let name = other
+                    .file_name()
+                    .map(|n| truncate_middle(&n.to_string_lossy(), MAX_TITLE_FILENAME_CHARS))
+                    .unwrap_or_default();
+                title.push_str(&format!(" vs {}", name));
+            }
+            if self.next_slide_at.is_some() {
+                title.push_str(&format!(
+                    " - Slideshow ({}s)",
+                    self.slideshow_interval.as_secs()
+                ));
+            }
+        }
+        if let Some(number) = self.input_handler.pending_number() {
+            title.push_str(&format!(" - Go to: {}", number));
+        }
+        if let Some((message, _)) = &self.status_message {
+            title.push_str(" - ");
+            title.push_str(message);
         }
         self.window.set_title(&title);
     }
 }
 
+/// A window or monitor area in physical pixels.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct ScreenRect {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+/// How much of a restored window must overlap a monitor, each way, to count as reachable
+const MIN_VISIBLE_PIXELS: i64 = 64;
+
+/// Where to put a restored window: its saved position while enough of it lands
+/// on a monitor, otherwise centered on the primary monitor (which may be
+/// unknown on some platforms, leaving placement to the OS).
+fn restored_position(
+    saved: ScreenRect,
+    monitors: &[ScreenRect],
+    primary: Option<ScreenRect>,
+) -> Option<(i32, i32)> {
+    let overlap = |a: i32, a_len: u32, b: i32, b_len: u32| {
+        let start = (a as i64).max(b as i64);
+        let end = (a as i64 + a_len as i64).min(b as i64 + b_len as i64);
+        end - start
+    };
+    let min_x = MIN_VISIBLE_PIXELS.min(saved.width as i64);
+    let min_y = MIN_VISIBLE_PIXELS.min(saved.height as i64);
+    let reachable = monitors.iter().any(|monitor| {
+        overlap(saved.x, saved.width, monitor.x, monitor.width) >= min_x
+            && overlap(saved.y, saved.height, monitor.y, monitor.height) >= min_y
+    });
+    if reachable {
+        return Some((saved.x, saved.y));
+    }
+
+    let primary = primary.or_else(|| monitors.first().copied())?;
+    let center =
+        |start: i32, len: u32, window: u32| start + (len.saturating_sub(window) / 2) as i32;
+    Some((
+        center(primary.x, primary.width, saved.width),
+        center(primary.y, primary.height, saved.height),
+    ))
+}
+
+/// Whether a window resize should refit the image: only in single view while it
+/// still shows at the fit zoom it opened with.
+fn refits_on_resize(mode: &ViewMode, is_actual_size: bool, user_zoomed: bool) -> bool {
+    *mode == ViewMode::Single && !is_actual_size && !user_zoomed
+}
+
+/// `--metadata <path>`: prints what the metadata panel would show for `path`
+/// as JSON and returns the process exit code.
+fn print_metadata(path: Option<&Path>) -> i32 {
+    let Some(path) = path else {
+        eprintln!("Usage: fastview --metadata <image>");
+        return 2;
+    };
+    if !path.is_file() {
+        eprintln!("{}: no such file", path.display());
+        return 1;
+    }
+    match serde_json::to_string_pretty(&metadata::ImageMetadata::from_path(path)) {
+        Ok(json) => {
+            println!("{}", json);
+            0
+        }
+        Err(e) => {
+            eprintln!("Could not serialize metadata: {}", e);
+            1
+        }
+    }
+}
+
+/// `--thumbnails <folder>`: fills the thumbnail cache for every image in the
+/// folder on the loader threads, printing progress, and returns the exit code:
+/// 1 when any image could not be loaded, so scripts can tell.
+fn generate_thumbnails(folder: Option<&Path>) -> i32 {
+    let Some(folder) = folder else {
+        eprintln!("Usage: fastview --thumbnails <folder>");
+        return 2;
+    };
+    if !folder.is_dir() {
+        eprintln!("{}: no such folder", folder.display());
+        return 1;
+    }
+    let image_loader = ImageLoader::new(folder.to_path_buf());
+    let paths: Vec<PathBuf> = (0..image_loader.get_image_count())
+        .filter_map(|index| image_loader.get_image_path(index).cloned())
+        .collect();
+    if paths.is_empty() {
+        println!("No images in {}", folder.display());
+        return 0;
+    }
+
+    let cache = CacheManager::new();
+    let (request_tx, request_rx) = unbounded::<Vec<LoaderRequest>>();
+    let (response_tx, response_rx) = unbounded::<LoaderResponse>();
+    // Unused, but kept open: the loader shuts down once any channel hangs up
+    let (_visible_tx, visible_rx) = unbounded::<Vec<usize>>();
+    let (_prefetch_tx, prefetch_rx) = unbounded::<Vec<PathBuf>>();
+    loader::spawn_workers(
+        LoaderChannels {
+            requests: request_rx,
+            visible_indices: visible_rx,
+            prefetch: prefetch_rx,
+            responses: response_tx,
+            notify: Arc::new(|| {}),
+        },
+        Arc::new(AtomicU64::new(0)),
+        cache.clone_db_handle(),
+        image_loader.prefetch_handle(),
+    );
+    let requests = paths
+        .iter()
+        .enumerate()
+        .map(|(index, path)| LoaderRequest {
+            path: path.clone(),
+            index,
+            is_directory: false,
+            shape: ThumbnailShape::from_env(),
+            generation: 0,
+        })
+        .collect();
+    let _ = request_tx.send(requests);
+
+    let mut failed = 0;
+    for done in 1..=paths.len() {
+        let Ok(response) = response_rx.recv() else {
+            break;
+        };
+        if response.failed {
+            failed += 1;
+            eprintln!("\rCouldn't load {}", paths[response.index].display());
+        }
+        eprint!("\r{}/{} thumbnails", done, paths.len());
+    }
+    eprintln!();
+
+    cache.flush();
+    cache.prune(cache.max_bytes());
+    println!(
+        "Cached thumbnails for {} images in {}{}",
+        paths.len() - failed,
+        folder.display(),
+        if failed > 0 {
+            format!(", {} could not be loaded", failed)
+        } else {
+            String::new()
+        }
+    );
+    i32::from(failed > 0)
+}
+
+/// Picks the GPU adapter. `FASTVIEW_GPU` may be `low`, `high`, or a substring
+/// of an adapter name; anything unmatched falls back to high performance.
+fn select_adapter(instance: &wgpu::Instance, surface: &wgpu::Surface) -> wgpu::Adapter {
+    let choice = std::env::var("FASTVIEW_GPU")
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase();
+
+    let power_preference = match choice.as_str() {
+        "low" => wgpu::PowerPreference::LowPower,
+        "high" | "" => wgpu::PowerPreference::HighPerformance,
+        name => {
+            let found = instance
+                .enumerate_adapters(wgpu::Backends::all())
+                .into_iter()
+                .find(|adapter| {
+                    adapter.get_info().name.to_lowercase().contains(name)
+                        && adapter.is_surface_supported(surface)
+                });
+            if let Some(adapter) = found {
+                return adapter;
+            }
+            log::warn!("No GPU adapter matches FASTVIEW_GPU={:?}", name);
+            wgpu::PowerPreference::HighPerformance
+        }
+    };
+
+    futures_lite::future::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference,
+        compatible_surface: Some(surface),
+        force_fallback_adapter: false,
+    }))
+    .expect("Failed to find an appropriate adapter")
+}
+
+const MAX_TITLE_FILENAME_CHARS: usize = 60;
+
+/// Shortens `text` to at most `max_chars` by eliding the middle, which keeps
+/// both the start of a filename and its extension readable.
+fn truncate_middle(text: &str, max_chars: usize) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() <= max_chars {
+        return text.to_string();
+    }
+    let tail = max_chars / 3;
+    let head = max_chars - tail - 1;
+    let mut out: String = chars[..head].iter().collect();
+    out.push('…');
+    out.extend(&chars[chars.len() - tail..]);
+    out
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Where Ctrl+M moves images: `FASTVIEW_SORT_BIN` if set, relative paths being
+/// taken from `folder`, otherwise a "Sorted" folder inside `folder`.
+fn sort_bin(folder: &Path) -> PathBuf {
+    match std::env::var_os("FASTVIEW_SORT_BIN") {
+        Some(bin) if !bin.is_empty() => folder.join(bin),
+        _ => folder.join("Sorted"),
+    }
+}
+
+/// Moves `path` into `folder`, copying across filesystems. Never overwrites.
+fn move_file(path: &Path, folder: &Path) -> Result<(), String> {
+    let name = path.file_name().ok_or("no file name")?;
+    let target = folder.join(name);
+    if target.exists() {
+        return Err(format!("{} already exists", target.display()));
+    }
+    if std::fs::rename(path, &target).is_ok() {
+        return Ok(());
+    }
+    std::fs::copy(path, &target).map_err(|e| e.to_string())?;
+    std::fs::remove_file(path).map_err(|e| {
+        let _ = std::fs::remove_file(&target);
+        e.to_string()
+    })
+}
+
+/// Sizes in binary units with one decimal, e.g. `1.5 GB`.
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
 struct App {
     state: Option<AppState>,
     event_loop_proxy: EventLoopProxy<UserEvent>,
@@ -898,7 +2532,26 @@ impl ApplicationHandler<UserEvent> for App {
             if let Some(settings) = self.cache.get_window_settings() {
                 window_attributes = window_attributes
                     .with_inner_size(LogicalSize::new(settings.width, settings.height))
-                    .with_position(winit::dpi::PhysicalPosition::new(settings.x, settings.y));
+                    .with_maximized(settings.maximized);
+                let monitor_rect = |monitor: winit::monitor::MonitorHandle| ScreenRect {
+                    x: monitor.position().x,
+                    y: monitor.position().y,
+                    width: monitor.size().width,
+                    height: monitor.size().height,
+                };
+                let monitors: Vec<ScreenRect> =
+                    event_loop.available_monitors().map(monitor_rect).collect();
+                let saved = ScreenRect {
+                    x: settings.x,
+                    y: settings.y,
+                    width: settings.width,
+                    height: settings.height,
+                };
+                let primary = event_loop.primary_monitor().map(monitor_rect);
+                if let Some((x, y)) = restored_position(saved, &monitors, primary) {
+                    window_attributes =
+                        window_attributes.with_position(winit::dpi::PhysicalPosition::new(x, y));
+                }
             }
 
             let window = event_loop
@@ -914,12 +2567,38 @@ impl ApplicationHandler<UserEvent> for App {
 
     fn window_event(
         &mut self,
-        _event_loop: &ActiveEventLoop,
+        event_loop: &ActiveEventLoop,
         _window_id: WindowId,
         event: WindowEvent,
     ) {
         if let Some(state) = &mut self.state {
-            state.handle_window_event(event);
+            state.handle_window_event(event_loop, event);
+        }
+    }
+
+    fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        // Loader threads may still be writing thumbnails; make them durable before exit
+        self.cache.flush();
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        if let Some(state) = &mut self.state {
+            let now = Instant::now();
+            // A lone digit nothing followed becomes its zoom preset
+            if let Some(action) = state.input_handler.take_expired_digit(now) {
+                state.update_window_title();
+                state.perform_action(event_loop, action);
+            }
+            state.tick(now);
+            if state.renderer.is_animating() {
+                // Keep the loop spinning only until eased zoom/scroll settles
+                event_loop.set_control_flow(ControlFlow::Poll);
+                return;
+            }
+            match state.next_wakeup() {
+                Some(deadline) => event_loop.set_control_flow(ControlFlow::WaitUntil(deadline)),
+                None => event_loop.set_control_flow(ControlFlow::Wait),
+            }
         }
     }
 
@@ -928,35 +2607,74 @@ impl ApplicationHandler<UserEvent> for App {
             match event {
                 UserEvent::OpenPath(path) => {
                     state.open_path(path);
+                    state.bring_to_front();
                 }
+                UserEvent::Focus => state.bring_to_front(),
+                UserEvent::ThumbnailReady => state.process_loader_responses(),
+                UserEvent::WallpaperSet(path, result) => state.report_wallpaper(&path, result),
             }
         }
     }
 }
 
 fn main() {
-    env_logger::init();
+    let mut logger = env_logger::Builder::from_default_env();
+    if profile::enabled() {
+        // Timings are logged at info level, which RUST_LOG would otherwise have to ask for
+        logger.filter_module("fastview::profile", log::LevelFilter::Info);
+    }
+    logger.init();
 
     let args: Vec<String> = std::env::args().collect();
-    let name = "fastview_ipc";
-    let name = if NameTypeSupport::query().paths_supported() {
-        format!("/tmp/{}.sock", name)
-    } else {
-        name.to_string()
-    };
+
+    let command_line_mode = args.iter().skip(1).any(|a| {
+        matches!(
+            a.as_str(),
+            "--clear-cache" | "--clear-rotations" | "--metadata" | "--thumbnails"
+        )
+    });
+    if command_line_mode {
+        shell::attach_console();
+    }
+    if args.iter().skip(1).any(|a| a == "--clear-cache") {
+        // Runs before any loader thread exists, so nothing can repopulate the cache meanwhile
+        let removed = CacheManager::new().clear_thumbnails();
+        println!("Removed {} cached thumbnails", removed);
+        return;
+    }
+    if args.iter().skip(1).any(|a| a == "--clear-rotations") {
+        let removed = CacheManager::new().clear_rotations(loader::THUMBNAIL_SIZE);
+        println!("Forgot {} remembered rotations", removed);
+        return;
+    }
+
+    if let Some(pos) = args.iter().skip(1).position(|a| a == "--metadata") {
+        std::process::exit(print_metadata(args.get(pos + 2).map(Path::new)));
+    }
+    if let Some(pos) = args.iter().skip(1).position(|a| a == "--thumbnails") {
+        std::process::exit(generate_thumbnails(args.get(pos + 2).map(Path::new)));
+    }
 
     // Try to connect to existing instance
-    if let Ok(mut stream) = LocalSocketStream::connect(name.clone()) {
-        let path = if args.len() > 1 {
-            args[1].clone()
-        } else {
-            ".".to_string()
-        };
-        let _ = stream.write_all(path.as_bytes());
+    if let Ok(mut stream) = LocalSocketStream::connect(ipc::socket_name()) {
+        // Paths are made absolute here since the running instance has its own cwd
+        let mut commands: Vec<IpcCommand> = args
+            .iter()
+            .skip(1)
+            .map(PathBuf::from)
+            .map(|p| IpcCommand::Open(std::fs::canonicalize(&p).unwrap_or(p)))
+            .collect();
+        if commands.is_empty() {
+            commands.push(IpcCommand::Focus);
+        }
+        let _ = stream.write_all(ipc::encode(&commands).as_bytes());
         return;
     }
 
     let cache = CacheManager::new();
+    let pruner = cache.clone();
+    thread::spawn(move || pruner.prune(pruner.max_bytes()));
+
     let event_loop = EventLoop::<UserEvent>::with_user_event().build().unwrap();
     let event_loop_proxy = event_loop.create_proxy();
     let mut app = App {
@@ -966,3 +2684,89 @@ fn main() {
     };
     event_loop.run_app(&mut app).unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: i32, y: i32, width: u32, height: u32) -> ScreenRect {
+        ScreenRect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn window_on_a_connected_monitor_keeps_its_position() {
+        let monitors = [rect(0, 0, 1920, 1080), rect(1920, 0, 2560, 1440)];
+        let saved = rect(2500, 200, 800, 600);
+        assert_eq!(
+            restored_position(saved, &monitors, Some(monitors[0])),
+            Some((2500, 200))
+        );
+        // Mostly off screen, but a grabbable strip is still on the monitor
+        let edge = rect(1920 - 800 + MIN_VISIBLE_PIXELS as i32, 100, 800, 600);
+        assert_eq!(
+            restored_position(edge, &monitors[..1], None),
+            Some((edge.x, edge.y))
+        );
+    }
+
+    #[test]
+    fn window_on_a_removed_monitor_is_centered_on_the_primary() {
+        let primary = rect(0, 0, 1920, 1080);
+        let saved = rect(2500, 200, 800, 600);
+        assert_eq!(
+            restored_position(saved, &[primary], Some(primary)),
+            Some((560, 240))
+        );
+        // Without a known primary the first monitor is used
+        let second = rect(-1280, 0, 1280, 1024);
+        assert_eq!(
+            restored_position(saved, &[second, primary], None),
+            Some((-1040, 212))
+        );
+        assert_eq!(restored_position(saved, &[], None), None);
+    }
+
+    #[test]
+    fn sliver_on_a_monitor_is_not_reachable() {
+        let primary = rect(0, 0, 1920, 1080);
+        let saved = rect(1920 - 10, 100, 800, 600);
+        assert_eq!(
+            restored_position(saved, &[primary], Some(primary)),
+            Some((560, 240))
+        );
+    }
+
+    #[test]
+    fn byte_counts_use_binary_units() {
+        assert_eq!(format_bytes(0), "0 B");
+        assert_eq!(format_bytes(1023), "1023 B");
+        assert_eq!(format_bytes(1024), "1.0 KB");
+        assert_eq!(format_bytes(1536 * 1024 * 1024), "1.5 GB");
+        assert_eq!(format_bytes(3 << 50), "3072.0 TB");
+    }
+
+    #[test]
+    fn long_names_lose_their_middle() {
+        assert_eq!(truncate_middle("short.png", 20), "short.png");
+        assert_eq!(truncate_middle("exactly-ten", 11), "exactly-ten");
+        let elided = truncate_middle("a_very_long_file_name_from_a_camera.jpeg", 20);
+        assert_eq!(elided, "a_very_long_f…a.jpeg");
+        assert_eq!(elided.chars().count(), 20);
+        // Counted in characters, so multi-byte names are never split mid-character
+        assert_eq!(truncate_middle("ééééééééééé.png", 10), "éééééé…png");
+    }
+
+    #[test]
+    fn only_an_image_still_at_its_fit_zoom_refits() {
+        assert!(refits_on_resize(&ViewMode::Single, false, false));
+        // Zoomed by hand, or showing at 100%, the zoom is kept
+        assert!(!refits_on_resize(&ViewMode::Single, false, true));
+        assert!(!refits_on_resize(&ViewMode::Single, true, false));
+        assert!(!refits_on_resize(&ViewMode::Grid, false, false));
+    }
+}
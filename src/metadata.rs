@@ -1,9 +1,15 @@
 use image::DynamicImage;
+use img_parts::jpeg::Jpeg;
+use img_parts::png::Png;
+use img_parts::webp::WebP;
+use img_parts::{ImageEXIF, ImageICC};
+use serde::Serialize;
+use std::fmt;
 use std::fs::File;
-use std::io::{BufReader, Read};
+use std::io::{BufRead, BufReader, Read, Seek, Write};
 use std::path::Path;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
 pub enum ExifOrientation {
     Normal,
     Rotate90,
@@ -30,27 +36,85 @@ impl ExifOrientation {
         }
     }
 
+    pub fn to_u32(self) -> u32 {
+        match self {
+            ExifOrientation::Normal => 1,
+            ExifOrientation::FlipHorizontal => 2,
+            ExifOrientation::Rotate180 => 3,
+            ExifOrientation::FlipHorizontalRotate180 => 4,
+            ExifOrientation::FlipHorizontalRotate90 => 5,
+            ExifOrientation::Rotate90 => 6,
+            ExifOrientation::FlipHorizontalRotate270 => 7,
+            ExifOrientation::Rotate270 => 8,
+        }
+    }
+
     pub fn needs_rotation(&self) -> bool {
         !matches!(self, ExifOrientation::Normal)
     }
 
-    pub fn to_string(&self) -> String {
+    /// Whether applying this orientation exchanges width and height.
+    pub fn swaps_dimensions(self) -> bool {
+        self.to_parts().1 % 2 == 1
+    }
+
+    // Every orientation is a clockwise rotation by `turns` quarter turns,
+    // optionally followed by a horizontal flip (see apply_orientation)
+    fn to_parts(self) -> (bool, u8) {
         match self {
-            ExifOrientation::Normal => "Normal".to_string(),
-            ExifOrientation::Rotate90 => "Rotate 90° CW".to_string(),
-            ExifOrientation::Rotate180 => "Rotate 180°".to_string(),
-            ExifOrientation::Rotate270 => "Rotate 90° CCW".to_string(),
-            ExifOrientation::FlipHorizontal => "Flip Horizontal".to_string(),
-            ExifOrientation::FlipHorizontalRotate90 => "Flip Horizontal, Rotate 90° CW".to_string(),
-            ExifOrientation::FlipHorizontalRotate180 => "Flip Horizontal, Rotate 180°".to_string(),
-            ExifOrientation::FlipHorizontalRotate270 => {
-                "Flip Horizontal, Rotate 90° CCW".to_string()
-            }
+            ExifOrientation::Normal => (false, 0),
+            ExifOrientation::Rotate90 => (false, 1),
+            ExifOrientation::Rotate180 => (false, 2),
+            ExifOrientation::Rotate270 => (false, 3),
+            ExifOrientation::FlipHorizontal => (true, 0),
+            ExifOrientation::FlipHorizontalRotate90 => (true, 1),
+            ExifOrientation::FlipHorizontalRotate180 => (true, 2),
+            ExifOrientation::FlipHorizontalRotate270 => (true, 3),
+        }
+    }
+
+    fn from_parts(flip: bool, turns: u8) -> Self {
+        match (flip, turns % 4) {
+            (false, 0) => ExifOrientation::Normal,
+            (false, 1) => ExifOrientation::Rotate90,
+            (false, 2) => ExifOrientation::Rotate180,
+            (false, _) => ExifOrientation::Rotate270,
+            (true, 0) => ExifOrientation::FlipHorizontal,
+            (true, 1) => ExifOrientation::FlipHorizontalRotate90,
+            (true, 2) => ExifOrientation::FlipHorizontalRotate180,
+            (true, _) => ExifOrientation::FlipHorizontalRotate270,
+        }
+    }
+
+    /// The orientation that displays like `self` followed by `quarter_turns` clockwise rotations.
+    pub fn rotated_cw(self, quarter_turns: u8) -> Self {
+        let (flip, turns) = self.to_parts();
+        if flip {
+            // Rotating after a flip runs the flip's inner rotation backwards
+            Self::from_parts(true, (turns + 4 - quarter_turns % 4) % 4)
+        } else {
+            Self::from_parts(false, turns + quarter_turns)
         }
     }
 }
 
-#[derive(Debug, Clone)]
+impl fmt::Display for ExifOrientation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            ExifOrientation::Normal => "Normal",
+            ExifOrientation::Rotate90 => "Rotate 90° CW",
+            ExifOrientation::Rotate180 => "Rotate 180°",
+            ExifOrientation::Rotate270 => "Rotate 90° CCW",
+            ExifOrientation::FlipHorizontal => "Flip Horizontal",
+            ExifOrientation::FlipHorizontalRotate90 => "Flip Horizontal, Rotate 90° CW",
+            ExifOrientation::FlipHorizontalRotate180 => "Flip Horizontal, Rotate 180°",
+            ExifOrientation::FlipHorizontalRotate270 => "Flip Horizontal, Rotate 90° CCW",
+        };
+        f.write_str(s)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct ExifData {
     pub make: Option<String>,
     pub model: Option<String>,
@@ -63,6 +127,8 @@ pub struct ExifData {
     pub software: Option<String>,
     pub image_size: Option<String>,
     pub orientation: ExifOrientation,
+    // Decimal degrees, positive north and east
+    pub location: Option<(f64, f64)>,
 }
 
 impl ExifData {
@@ -104,14 +170,125 @@ impl ExifData {
         if let Some(ref software) = self.software {
             pairs.push(("Software".to_string(), software.clone()));
         }
+        if let Some((lat, lon)) = self.location {
+            pairs.push((
+                "Location".to_string(),
+                format!(
+                    "{:.6}° {}, {:.6}° {}",
+                    lat.abs(),
+                    if lat < 0.0 { 'S' } else { 'N' },
+                    lon.abs(),
+                    if lon < 0.0 { 'W' } else { 'E' }
+                ),
+            ));
+            pairs.push((
+                "Map".to_string(),
+                format!("https://www.openstreetmap.org/?mlat={lat:.6}&mlon={lon:.6}"),
+            ));
+        }
 
         pairs
     }
 }
 
+/// Generation settings Automatic1111 writes to a PNG `parameters` text chunk:
+/// the prompt, an optional `Negative prompt:` line, and a final settings line
+/// such as `Steps: 20, Sampler: Euler a, CFG scale: 7, Seed: 1, Model: sd15`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GenerationParameters {
+    pub positive: String,
+    pub negative: Option<String>,
+    pub steps: Option<String>,
+    pub sampler: Option<String>,
+    pub cfg_scale: Option<String>,
+    pub seed: Option<String>,
+    pub model: Option<String>,
+}
+
+impl GenerationParameters {
+    pub fn parse(text: &str) -> Self {
+        let mut params = GenerationParameters::default();
+        let mut prompt_lines: Vec<&str> = text.lines().collect();
+
+        if let Some(pos) = prompt_lines
+            .iter()
+            .rposition(|line| line.trim_start().starts_with("Steps:"))
+        {
+            for (key, value) in split_settings(prompt_lines[pos]) {
+                let value = Some(value.to_string());
+                match key {
+                    "Steps" => params.steps = value,
+                    "Sampler" => params.sampler = value,
+                    "CFG scale" => params.cfg_scale = value,
+                    "Seed" => params.seed = value,
+                    "Model" => params.model = value,
+                    "Model hash" if params.model.is_none() => params.model = value,
+                    _ => {}
+                }
+            }
+            prompt_lines.truncate(pos);
+        }
+
+        if let Some(pos) = prompt_lines
+            .iter()
+            .position(|line| line.starts_with("Negative prompt:"))
+        {
+            let mut negative = prompt_lines[pos]["Negative prompt:".len()..]
+                .trim()
+                .to_string();
+            for line in &prompt_lines[pos + 1..] {
+                negative.push('\n');
+                negative.push_str(line);
+            }
+            params.negative = Some(negative.trim().to_string()).filter(|n| !n.is_empty());
+            prompt_lines.truncate(pos);
+        }
+
+        params.positive = prompt_lines.join("\n").trim().to_string();
+        params
+    }
+
+    pub fn to_key_values(&self) -> Vec<(String, String)> {
+        [
+            ("Steps", &self.steps),
+            ("Sampler", &self.sampler),
+            ("CFG scale", &self.cfg_scale),
+            ("Seed", &self.seed),
+            ("Model", &self.model),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| Some((key.to_string(), value.clone()?)))
+        .collect()
+    }
+}
+
+/// Splits `Key: value, Key: "quoted, value"` pairs, keeping commas inside quotes.
+fn split_settings(line: &str) -> Vec<(&str, &str)> {
+    let mut pairs = Vec::new();
+    let mut in_quotes = false;
+    let mut start = 0;
+    for (i, c) in line
+        .char_indices()
+        .chain(std::iter::once((line.len(), ',')))
+    {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => {
+                if let Some((key, value)) = line[start..i].split_once(':') {
+                    pairs.push((key.trim(), value.trim().trim_matches('"')));
+                }
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    pairs
+}
+
+#[derive(Serialize)]
 pub struct ImageMetadata {
-    pub orientation: ExifOrientation,
     pub prompt: Option<String>,
+    pub parameters: Option<GenerationParameters>,
     pub exif: Option<ExifData>,
 }
 
@@ -122,21 +299,22 @@ impl ImageMetadata {
             .map(|e| e.to_string_lossy().to_lowercase())
             .unwrap_or_default();
 
-        let (exif, prompt) = match extension.as_str() {
-            "jpg" | "jpeg" => (Self::read_exif_data(path), None),
-            "png" => (None, Self::read_png_prompt(path)),
-            "webp" => (None, Self::read_webp_prompt(path)),
-            _ => (None, None),
+        let (exif, prompt, parameters) = match extension.as_str() {
+            "jpg" | "jpeg" | "tif" | "tiff" => (Self::read_exif_data(path), None, None),
+            "heic" | "heif" | "avif" if cfg!(feature = "heif") => {
+                (Self::read_exif_data(path), None, None)
+            }
+            "png" => {
+                let (prompt, parameters) = Self::read_png_prompt(path);
+                (None, prompt, parameters)
+            }
+            "webp" => (None, Self::read_webp_prompt(path), None),
+            _ => (None, None, None),
         };
 
-        let orientation = exif
-            .as_ref()
-            .map(|e| e.orientation)
-            .unwrap_or(ExifOrientation::Normal);
-
         ImageMetadata {
-            orientation,
             prompt,
+            parameters,
             exif,
         }
     }
@@ -150,6 +328,24 @@ impl ImageMetadata {
             }
         }
 
+        if let Some(ref parameters) = self.parameters {
+            lines.push("".to_string());
+            lines.push("Prompt:".to_string());
+            lines.extend(wrap_chars(&parameters.positive, 80));
+            if let Some(ref negative) = parameters.negative {
+                lines.push("".to_string());
+                lines.push("Negative prompt:".to_string());
+                lines.extend(wrap_chars(negative, 80));
+            }
+            let settings = parameters.to_key_values();
+            if !settings.is_empty() {
+                lines.push("".to_string());
+            }
+            for (key, value) in settings {
+                lines.push(format!("{}: {}", key, value));
+            }
+        }
+
         if let Some(ref prompt) = self.prompt {
             if prompt.trim().starts_with('{') {
                 lines.push("".to_string());
@@ -166,12 +362,7 @@ impl ImageMetadata {
             } else {
                 lines.push("".to_string());
                 lines.push("Prompt:".to_string());
-                let max_chars = 80;
-                let chars: Vec<char> = prompt.chars().collect();
-                for chunk in chars.chunks(max_chars) {
-                    let chunk_str: String = chunk.iter().collect();
-                    lines.push(chunk_str);
-                }
+                lines.extend(wrap_chars(prompt, 80));
             }
         }
 
@@ -183,16 +374,14 @@ impl ImageMetadata {
     }
 
     fn read_exif_data(path: &Path) -> Option<ExifData> {
-        let file = match File::open(path) {
-            Ok(f) => f,
-            Err(_) => return None,
-        };
-        let mut reader = BufReader::new(file);
+        let file = File::open(path).ok()?;
+        Self::read_exif_from(&mut BufReader::new(file))
+    }
 
-        let exif = match exif::Reader::new().read_from_container(&mut reader) {
-            Ok(e) => e,
-            Err(_) => return None,
-        };
+    /// EXIF from any container kamadak-exif recognizes: JPEG, TIFF, HEIF,
+    /// PNG or WebP, whether read from a file or bytes already in memory.
+    fn read_exif_from<R: BufRead + Seek>(reader: &mut R) -> Option<ExifData> {
+        let exif = exif::Reader::new().read_from_container(reader).ok()?;
 
         let get_str = |tag: exif::Tag| -> Option<String> {
             exif.get_field(tag, exif::In::PRIMARY)
@@ -229,26 +418,77 @@ impl ImageMetadata {
             software: get_str(exif::Tag::Software),
             image_size,
             orientation,
+            location: Self::read_gps_location(&exif),
         })
     }
 
-    fn read_png_prompt(path: &Path) -> Option<String> {
+    fn read_gps_location(exif: &exif::Exif) -> Option<(f64, f64)> {
+        // Degrees, minutes and seconds, negated for the southern/western reference
+        let coordinate = |tag: exif::Tag, ref_tag: exif::Tag, negative: u8| -> Option<f64> {
+            let field = exif.get_field(tag, exif::In::PRIMARY)?;
+            let exif::Value::Rational(ref dms) = field.value else {
+                return None;
+            };
+            let degrees = dms
+                .iter()
+                .zip([1.0, 60.0, 3600.0])
+                .map(|(part, scale)| part.to_f64() / scale)
+                .sum::<f64>();
+            let reference =
+                exif.get_field(ref_tag, exif::In::PRIMARY)
+                    .and_then(|f| match f.value {
+                        exif::Value::Ascii(ref v) => v.first()?.first().copied(),
+                        _ => None,
+                    });
+            let sign = if reference.is_some_and(|r| r.eq_ignore_ascii_case(&negative)) {
+                -1.0
+            } else {
+                1.0
+            };
+            Some(sign * degrees).filter(|d| d.is_finite())
+        };
+
+        let lat = coordinate(exif::Tag::GPSLatitude, exif::Tag::GPSLatitudeRef, b'S')?;
+        let lon = coordinate(exif::Tag::GPSLongitude, exif::Tag::GPSLongitudeRef, b'W')?;
+        // Cameras without a fix often write all zeros
+        if lat == 0.0 && lon == 0.0 {
+            return None;
+        }
+        Some((lat, lon))
+    }
+
+    /// A free-form prompt, or Automatic1111 parameters when the PNG has them.
+    fn read_png_prompt(path: &Path) -> (Option<String>, Option<GenerationParameters>) {
         let file = match File::open(path) {
             Ok(f) => f,
-            Err(_) => return None,
+            Err(_) => return (None, None),
         };
 
         let mut reader = BufReader::new(file);
         let mut bytes = Vec::new();
         if reader.read_to_end(&mut bytes).is_err() {
-            return None;
+            return (None, None);
         }
 
         let png = match img_parts::png::Png::from_bytes(bytes.into()) {
             Ok(p) => p,
-            Err(_) => return None,
+            Err(_) => return (None, None),
         };
 
+        for chunk in png.chunks() {
+            if chunk.kind() == *b"tEXt" {
+                if let Ok(text) = std::str::from_utf8(chunk.contents()) {
+                    if let Some(value) = text.strip_prefix("parameters\0") {
+                        return (None, Some(GenerationParameters::parse(value)));
+                    }
+                }
+            }
+        }
+
+        (Self::find_png_prompt(&png), None)
+    }
+
+    fn find_png_prompt(png: &img_parts::png::Png) -> Option<String> {
         for chunk in png.chunks() {
             let kind = chunk.kind();
             if kind == *b"tEXt" || kind == *b"iTXt" {
@@ -324,26 +564,27 @@ impl ImageMetadata {
                 let chunk_type = std::str::from_utf8(&bytes[offset + 8..offset + 12]).unwrap_or("");
 
                 if chunk_type == "WEBP" {
+                    let end = (offset + 8 + chunk_size).min(bytes.len());
                     let mut inner = offset + 12;
-                    while inner < offset + 12 + chunk_size && inner < bytes.len().saturating_sub(8)
-                    {
-                        let sub_type = std::str::from_utf8(&bytes[inner..inner + 4]).unwrap_or("");
+                    while inner + 8 <= end {
+                        let sub_type = &bytes[inner..inner + 4];
                         let sub_size = u32::from_le_bytes([
                             bytes[inner + 4],
                             bytes[inner + 5],
                             bytes[inner + 6],
                             bytes[inner + 7],
                         ]) as usize;
+                        // A truncated last chunk still gets whatever bytes are there
+                        let data_end = (inner + 8).saturating_add(sub_size).min(end);
 
-                        if sub_type == "EXIF" {
-                            let exif_data = &bytes[inner + 8..inner + 8 + sub_size];
-
-                            let cursor = std::io::Cursor::new(exif_data);
-                            let mut exif_reader = BufReader::new(cursor);
+                        if sub_type.eq_ignore_ascii_case(b"EXIF") {
+                            let mut exif_data = &bytes[inner + 8..data_end];
+                            // Some writers keep the JPEG APP1 header in front of the TIFF data
+                            if let Some(tiff) = exif_data.strip_prefix(b"Exif\0\0") {
+                                exif_data = tiff;
+                            }
 
-                            if let Ok(exif) =
-                                exif::Reader::new().read_from_container(&mut exif_reader)
-                            {
+                            if let Ok(exif) = exif::Reader::new().read_raw(exif_data.to_vec()) {
                                 if let Some(field) =
                                     exif.get_field(exif::Tag::ImageDescription, exif::In::PRIMARY)
                                 {
@@ -372,10 +613,8 @@ impl ImageMetadata {
                             }
                         }
 
-                        inner += 8 + sub_size;
-                        if sub_size % 2 != 0 {
-                            inner += 1;
-                        }
+                        // Chunks are padded to an even length
+                        inner = data_end + (sub_size % 2);
                     }
                 }
                 break;
@@ -476,6 +715,354 @@ impl ImageMetadata {
     }
 }
 
+/// Splits `text` into lines of at most `max_chars` characters.
+fn wrap_chars(text: &str, max_chars: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    chars
+        .chunks(max_chars)
+        .map(|chunk| chunk.iter().collect())
+        .collect()
+}
+
+/// Rewrites the EXIF Orientation tag of a JPEG in place, leaving the pixel data untouched.
+/// The tag is added if the file has none, along with an EXIF block if it has no EXIF at all.
+pub fn write_jpeg_orientation(path: &Path, orientation: ExifOrientation) -> std::io::Result<()> {
+    let bytes = std::fs::read(path)?;
+    let mut jpeg = Jpeg::from_bytes(bytes.into()).map_err(|e| invalid_data(&e.to_string()))?;
+    let mut exif = match jpeg.exif() {
+        Some(exif) => exif.to_vec(),
+        // Bare little-endian header with an empty IFD0
+        None => b"II*\0\x08\0\0\0\0\0\0\0\0\0".to_vec(),
+    };
+    set_tiff_orientation(&mut exif, orientation.to_u32() as u16)
+        .ok_or_else(|| invalid_data("malformed EXIF data"))?;
+    // The APP1 segment length, which also counts itself and the "Exif" prefix, is 16 bits
+    if exif.len() + 8 > u16::MAX as usize {
+        return Err(invalid_data("EXIF data too large for its segment"));
+    }
+    jpeg.set_exif(Some(exif.into()));
+
+    replace_file(path, |file| jpeg.encoder().write_to(file).map(|_| ()))
+}
+
+/// Re-encodes a PNG or lossless WebP with its pixels rotated by `orientation`, carrying
+/// over text chunks, color profile and EXIF. Lossy WebPs are refused: the only WebP
+/// encoder available is lossless, which would make them several times larger.
+pub fn write_rotated_pixels(path: &Path, orientation: ExifOrientation) -> std::io::Result<()> {
+    let bytes = std::fs::read(path)?;
+    let format = image::guess_format(&bytes).map_err(|e| invalid_data(&e.to_string()))?;
+    if format == image::ImageFormat::WebP && is_lossy_webp(&bytes) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "lossy WebP would be re-encoded lossless",
+        ));
+    }
+    let img = image::load_from_memory_with_format(&bytes, format)
+        .map_err(|e| invalid_data(&e.to_string()))?;
+    let mut encoded = Vec::new();
+    apply_orientation(&img, orientation)
+        .write_to(&mut std::io::Cursor::new(&mut encoded), format)
+        .map_err(|e| invalid_data(&e.to_string()))?;
+
+    let output = match format {
+        image::ImageFormat::Png => {
+            let original =
+                Png::from_bytes(bytes.into()).map_err(|e| invalid_data(&e.to_string()))?;
+            let mut png =
+                Png::from_bytes(encoded.into()).map_err(|e| invalid_data(&e.to_string()))?;
+            // The encoder writes only IHDR, pixel data and IEND; ancillary chunks go after IHDR
+            let kept: Vec<_> = original
+                .chunks()
+                .iter()
+                .filter(|chunk| PNG_KEPT_CHUNKS.contains(&chunk.kind()))
+                .filter(|chunk| png.chunk_by_type(chunk.kind()).is_none())
+                .cloned()
+                .collect();
+            for (i, chunk) in kept.into_iter().enumerate() {
+                png.chunks_mut().insert(1 + i, chunk);
+            }
+            png.encoder().bytes()
+        }
+        image::ImageFormat::WebP => {
+            let original =
+                WebP::from_bytes(bytes.into()).map_err(|e| invalid_data(&e.to_string()))?;
+            let mut webp =
+                WebP::from_bytes(encoded.into()).map_err(|e| invalid_data(&e.to_string()))?;
+            webp.set_icc_profile(original.icc_profile());
+            webp.set_exif(original.exif());
+            webp.encoder().bytes()
+        }
+        _ => return Err(invalid_data("only PNG and WebP pixels are rewritten")),
+    };
+    replace_file(path, |file| file.write_all(&output))
+}
+
+/// Whether the WebP in `bytes` holds VP8 (lossy) rather than VP8L image data.
+fn is_lossy_webp(bytes: &[u8]) -> bool {
+    WebP::from_bytes(bytes.to_vec().into())
+        .is_ok_and(|webp| webp.has_chunk(img_parts::webp::CHUNK_VP8))
+}
+
+/// PNG chunks that survive a re-encode: text, color space and physical size
+const PNG_KEPT_CHUNKS: [[u8; 4]; 9] = [
+    *b"tEXt", *b"zTXt", *b"iTXt", *b"iCCP", *b"sRGB", *b"gAMA", *b"cHRM", *b"pHYs", *b"eXIf",
+];
+
+fn invalid_data(msg: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg.to_string())
+}
+
+/// Writes the new contents next to `path` and swaps them in, so a failed write can't
+/// truncate the original. The temporary file is removed if anything goes wrong.
+fn replace_file(
+    path: &Path,
+    write: impl FnOnce(&mut File) -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    let tmp_path = path.with_extension("fastview-tmp");
+    let result = File::create(&tmp_path)
+        .and_then(|mut file| {
+            write(&mut file)?;
+            file.flush()
+        })
+        .and_then(|()| std::fs::rename(&tmp_path, path));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+/// Just the EXIF orientation, for callers that don't need the rest of the metadata.
+/// JPEGs are scanned only up to their EXIF segment; if that fails to parse the
+/// full EXIF reader gets a try. TIFFs only have their first directory read.
+///
+/// HEIF and AVIF report Normal even though their EXIF may say otherwise: the
+/// decoder already applies the container's own rotation, which takes precedence.
+pub fn read_orientation_only(path: &Path) -> ExifOrientation {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let orientation = match extension.as_str() {
+        "jpg" | "jpeg" => read_jpeg_orientation(path)
+            .or_else(|| ImageMetadata::read_exif_data(path).map(|e| e.orientation)),
+        "tif" | "tiff" => read_tiff_orientation(path),
+        _ => None,
+    };
+    orientation.unwrap_or(ExifOrientation::Normal)
+}
+
+fn read_tiff_orientation(path: &Path) -> Option<ExifOrientation> {
+    use image::ImageDecoder;
+
+    let mut decoder = image::ImageReader::open(path).ok()?.into_decoder().ok()?;
+    let orientation = decoder.orientation().ok()?;
+    Some(ExifOrientation::from_u32(orientation.to_exif() as u32))
+}
+
+/// The preview JPEG that cameras embed in the EXIF block, oriented like the photo.
+/// Only returned if its shorter side is at least `min_side` and its shape matches
+/// the full image, since some cameras letterbox the preview to a fixed size.
+pub fn read_exif_thumbnail(path: &Path, min_side: u32) -> Option<DynamicImage> {
+    let is_jpeg = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .is_some_and(|e| e == "jpg" || e == "jpeg");
+    if !is_jpeg {
+        return None;
+    }
+
+    let mut reader = BufReader::new(File::open(path).ok()?);
+    let exif = exif::Reader::new().read_from_container(&mut reader).ok()?;
+    let get_uint = |tag: exif::Tag, ifd: exif::In| {
+        exif.get_field(tag, ifd)
+            .and_then(|f| f.value.get_uint(0))
+            .map(|v| v as usize)
+    };
+
+    let offset = get_uint(exif::Tag::JPEGInterchangeFormat, exif::In::THUMBNAIL)?;
+    let length = get_uint(exif::Tag::JPEGInterchangeFormatLength, exif::In::THUMBNAIL)?;
+    let bytes = exif.buf().get(offset..offset.checked_add(length)?)?;
+    let thumb = image::load_from_memory_with_format(bytes, image::ImageFormat::Jpeg).ok()?;
+    if thumb.width().min(thumb.height()) < min_side {
+        return None;
+    }
+
+    let full_width = get_uint(exif::Tag::PixelXDimension, exif::In::PRIMARY)?;
+    let full_height = get_uint(exif::Tag::PixelYDimension, exif::In::PRIMARY)?;
+    let full_aspect = full_width as f32 / full_height.max(1) as f32;
+    let thumb_aspect = thumb.width() as f32 / thumb.height() as f32;
+    if (full_aspect / thumb_aspect - 1.0).abs() > 0.02 {
+        return None;
+    }
+
+    let orientation = exif
+        .get_field(exif::Tag::Orientation, exif::In::PRIMARY)
+        .and_then(|f| f.value.get_uint(0))
+        .map(ExifOrientation::from_u32)
+        .unwrap_or(ExifOrientation::Normal);
+    Some(apply_orientation(&thumb, orientation))
+}
+
+fn read_jpeg_orientation(path: &Path) -> Option<ExifOrientation> {
+    let mut reader = BufReader::new(File::open(path).ok()?);
+    let mut soi = [0u8; 2];
+    reader.read_exact(&mut soi).ok()?;
+    if soi != [0xFF, 0xD8] {
+        return None;
+    }
+
+    loop {
+        let mut marker = [0u8; 2];
+        reader.read_exact(&mut marker).ok()?;
+        if marker[0] != 0xFF {
+            return None;
+        }
+        match marker[1] {
+            // Fill byte before the real marker
+            0xFF => {
+                reader.seek_relative(-1).ok()?;
+                continue;
+            }
+            // Standalone markers without a length
+            0x01 | 0xD0..=0xD7 => continue,
+            // Image data starts without any EXIF segment
+            0xDA | 0xD9 => return Some(ExifOrientation::Normal),
+            _ => {}
+        }
+
+        let mut length = [0u8; 2];
+        reader.read_exact(&mut length).ok()?;
+        let length = (u16::from_be_bytes(length) as usize).checked_sub(2)?;
+        if marker[1] != 0xE1 {
+            reader.seek_relative(length as i64).ok()?;
+            continue;
+        }
+
+        let mut segment = vec![0u8; length];
+        reader.read_exact(&mut segment).ok()?;
+        // APP1 is also used for XMP, which has no orientation tag
+        let Some(tiff) = segment.strip_prefix(b"Exif\0\0") else {
+            continue;
+        };
+        return match tiff_orientation_entry(tiff)? {
+            Some((entry, little_endian)) => {
+                let value = tiff.get(entry + 8..entry + 10)?;
+                let value = if little_endian {
+                    u16::from_le_bytes([value[0], value[1]])
+                } else {
+                    u16::from_be_bytes([value[0], value[1]])
+                };
+                Some(ExifOrientation::from_u32(value as u32))
+            }
+            None => Some(ExifOrientation::Normal),
+        };
+    }
+}
+
+/// Sets the Orientation tag of a TIFF block, adding it to IFD0 if it isn't there.
+/// None if the block is malformed.
+fn set_tiff_orientation(tiff: &mut Vec<u8>, value: u16) -> Option<()> {
+    let little_endian = tiff.first() == Some(&b'I');
+    let u16_bytes = |v: u16| {
+        if little_endian {
+            v.to_le_bytes()
+        } else {
+            v.to_be_bytes()
+        }
+    };
+    let u32_bytes = |v: u32| {
+        if little_endian {
+            v.to_le_bytes()
+        } else {
+            v.to_be_bytes()
+        }
+    };
+
+    if let Some((entry, _)) = tiff_orientation_entry(tiff)? {
+        tiff.get_mut(entry + 8..entry + 10)?
+            .copy_from_slice(&u16_bytes(value));
+        return Some(());
+    }
+
+    // Entries have to stay sorted by tag and values elsewhere in the block are found by
+    // absolute offset, so IFD0 can't grow in place. Instead a copy with the new entry
+    // goes at the end of the block and the header is pointed at it.
+    let ifd0 = tiff_u32(tiff, 4, little_endian)? as usize;
+    let count = tiff_u16(tiff, ifd0, little_endian)? as usize;
+    let entries_end = ifd0 + 2 + count * 12;
+    let mut entries: Vec<Vec<u8>> = tiff
+        .get(ifd0 + 2..entries_end)?
+        .chunks(12)
+        .map(<[u8]>::to_vec)
+        .collect();
+    let next_ifd = tiff.get(entries_end..entries_end + 4)?.to_vec();
+
+    let mut orientation = Vec::with_capacity(12);
+    orientation.extend(u16_bytes(0x0112));
+    orientation.extend(u16_bytes(3));
+    orientation.extend(u32_bytes(1));
+    orientation.extend(u16_bytes(value));
+    orientation.extend([0, 0]);
+    let position = entries
+        .iter()
+        .position(|entry| tiff_u16(entry, 0, little_endian) > Some(0x0112))
+        .unwrap_or(entries.len());
+    entries.insert(position, orientation);
+
+    // IFDs start on a word boundary
+    if tiff.len() % 2 == 1 {
+        tiff.push(0);
+    }
+    let new_ifd0 = u32::try_from(tiff.len()).ok()?;
+    tiff.extend(u16_bytes(entries.len() as u16));
+    tiff.extend(entries.concat());
+    tiff.extend(next_ifd);
+    tiff[4..8].copy_from_slice(&u32_bytes(new_ifd0));
+    Some(())
+}
+
+fn tiff_u16(tiff: &[u8], at: usize, little_endian: bool) -> Option<u16> {
+    let s = tiff.get(at..at + 2)?;
+    Some(if little_endian {
+        u16::from_le_bytes([s[0], s[1]])
+    } else {
+        u16::from_be_bytes([s[0], s[1]])
+    })
+}
+
+fn tiff_u32(tiff: &[u8], at: usize, little_endian: bool) -> Option<u32> {
+    let s = tiff.get(at..at + 4)?;
+    let b = [s[0], s[1], s[2], s[3]];
+    Some(if little_endian {
+        u32::from_le_bytes(b)
+    } else {
+        u32::from_be_bytes(b)
+    })
+}
+
+/// Offset of the Orientation entry in IFD0 and whether the TIFF block is little endian.
+/// None if the block is malformed, Some(None) if it simply has no orientation.
+fn tiff_orientation_entry(tiff: &[u8]) -> Option<Option<(usize, bool)>> {
+    let little_endian = match tiff.get(0..2)? {
+        b"II" => true,
+        b"MM" => false,
+        _ => return None,
+    };
+    let ifd0 = tiff_u32(tiff, 4, little_endian)? as usize;
+
+    let count = tiff_u16(tiff, ifd0, little_endian)? as usize;
+    for i in 0..count {
+        let entry = ifd0 + 2 + i * 12;
+        // Orientation is tag 0x0112, stored as a single SHORT
+        if tiff_u16(tiff, entry, little_endian)? == 0x0112
+            && tiff_u16(tiff, entry + 2, little_endian)? == 3
+        {
+            return Some(Some((entry, little_endian)));
+        }
+    }
+    Some(None)
+}
+
 pub fn apply_orientation(img: &DynamicImage, orientation: ExifOrientation) -> DynamicImage {
     match orientation {
         ExifOrientation::Normal => img.clone(),
@@ -497,3 +1084,422 @@ pub fn apply_orientation(img: &DynamicImage, orientation: ExifOrientation) -> Dy
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn metadata_of_a_generated_png_serializes_to_json() {
+        let dir = TempDir::new("metadata-json");
+        let path = dir.join("sample.png");
+        let mut encoded = Vec::new();
+        image::RgbaImage::new(4, 4)
+            .write_to(
+                &mut std::io::Cursor::new(&mut encoded),
+                image::ImageFormat::Png,
+            )
+            .unwrap();
+        let mut png = img_parts::png::Png::from_bytes(encoded.into()).unwrap();
+        let text = b"parameters\0a red fox\nNegative prompt: blurry\nSteps: 20, Seed: 42".to_vec();
+        let end = png.chunks().len() - 1;
+        png.chunks_mut()
+            .insert(end, img_parts::png::PngChunk::new(*b"tEXt", text.into()));
+        png.encoder()
+            .write_to(File::create(&path).unwrap())
+            .unwrap();
+
+        let json = serde_json::to_value(ImageMetadata::from_path(&path)).unwrap();
+        let parameters = &json["parameters"];
+        assert_eq!(parameters["positive"], "a red fox");
+        assert_eq!(parameters["negative"], "blurry");
+        assert_eq!(parameters["steps"], "20");
+        assert_eq!(parameters["seed"], "42");
+        assert_eq!(json["exif"], serde_json::Value::Null);
+    }
+
+    /// A 2x1 image whose left pixel is red and right pixel blue
+    fn red_blue() -> DynamicImage {
+        let mut img = image::RgbImage::new(2, 1);
+        img.put_pixel(0, 0, image::Rgb([255, 0, 0]));
+        img.put_pixel(1, 0, image::Rgb([0, 0, 255]));
+        DynamicImage::ImageRgb8(img)
+    }
+
+    fn encode(img: &DynamicImage, format: image::ImageFormat) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        img.write_to(&mut std::io::Cursor::new(&mut encoded), format)
+            .unwrap();
+        encoded
+    }
+
+    #[test]
+    fn rotated_cw_displays_like_a_further_clockwise_turn() {
+        let mut img = image::RgbImage::new(3, 2);
+        for (i, pixel) in img.pixels_mut().enumerate() {
+            *pixel = image::Rgb([i as u8 * 40, 0, 0]);
+        }
+        let img = DynamicImage::ImageRgb8(img);
+        for value in 1..=8 {
+            let orientation = ExifOrientation::from_u32(value);
+            for turns in 0..4u8 {
+                let mut expected = apply_orientation(&img, orientation);
+                for _ in 0..turns {
+                    expected = expected.rotate90();
+                }
+                let rotated = apply_orientation(&img, orientation.rotated_cw(turns));
+                assert_eq!(
+                    rotated.to_rgb8(),
+                    expected.to_rgb8(),
+                    "{orientation} turned {turns} times"
+                );
+            }
+            assert_eq!(orientation.rotated_cw(4), orientation);
+        }
+    }
+
+    #[test]
+    fn jpeg_without_exif_gets_an_orientation_tag() {
+        let dir = TempDir::new("jpeg-no-exif");
+        let path = dir.join("photo.jpg");
+        std::fs::write(&path, encode(&red_blue(), image::ImageFormat::Jpeg)).unwrap();
+
+        write_jpeg_orientation(&path, ExifOrientation::Rotate90).unwrap();
+        assert_eq!(read_orientation_only(&path), ExifOrientation::Rotate90);
+        // Once the tag exists it is patched in place
+        write_jpeg_orientation(&path, ExifOrientation::Rotate180).unwrap();
+        assert_eq!(read_orientation_only(&path), ExifOrientation::Rotate180);
+        assert!(image::open(&path).is_ok());
+        assert!(!dir.join("photo.fastview-tmp").exists());
+    }
+
+    #[test]
+    fn orientation_is_inserted_in_tag_order_keeping_other_entries() {
+        // Big-endian IFD0 with Model (0x0110) and Software (0x0131), values stored inline
+        let mut tiff = b"MM\0*\0\0\0\x08\0\x02".to_vec();
+        tiff.extend(b"\x01\x10\0\x02\0\0\0\x03ab\0\0");
+        tiff.extend(b"\x01\x31\0\x02\0\0\0\x03cd\0\0");
+        tiff.extend(b"\0\0\0\0");
+        let dir = TempDir::new("jpeg-exif-no-orientation");
+        let path = dir.join("photo.jpg");
+        let mut jpeg =
+            Jpeg::from_bytes(encode(&red_blue(), image::ImageFormat::Jpeg).into()).unwrap();
+        jpeg.set_exif(Some(tiff.into()));
+        jpeg.encoder()
+            .write_to(File::create(&path).unwrap())
+            .unwrap();
+
+        write_jpeg_orientation(&path, ExifOrientation::FlipHorizontalRotate90).unwrap();
+        let jpeg = Jpeg::from_bytes(std::fs::read(&path).unwrap().into()).unwrap();
+        let exif = exif::Reader::new()
+            .read_raw(jpeg.exif().unwrap().to_vec())
+            .unwrap();
+        let tags: Vec<_> = exif.fields().map(|field| field.tag).collect();
+        assert_eq!(
+            tags,
+            [
+                exif::Tag::Model,
+                exif::Tag::Orientation,
+                exif::Tag::Software
+            ]
+        );
+        let text = |tag| {
+            exif.get_field(tag, exif::In::PRIMARY)
+                .unwrap()
+                .display_value()
+                .to_string()
+        };
+        assert_eq!(text(exif::Tag::Model), "\"ab\"");
+        assert_eq!(text(exif::Tag::Software), "\"cd\"");
+        assert_eq!(
+            read_orientation_only(&path),
+            ExifOrientation::FlipHorizontalRotate90
+        );
+    }
+
+    #[test]
+    fn png_pixels_are_rotated_keeping_text_chunks() {
+        let dir = TempDir::new("png-rotate");
+        let path = dir.join("image.png");
+        let mut png = Png::from_bytes(encode(&red_blue(), image::ImageFormat::Png).into()).unwrap();
+        let end = png.chunks().len() - 1;
+        png.chunks_mut().insert(
+            end,
+            img_parts::png::PngChunk::new(*b"tEXt", b"parameters\0a red fox".to_vec().into()),
+        );
+        png.encoder()
+            .write_to(File::create(&path).unwrap())
+            .unwrap();
+
+        write_rotated_pixels(&path, ExifOrientation::Rotate90).unwrap();
+        let rotated = image::open(&path).unwrap().to_rgb8();
+        assert_eq!(rotated.dimensions(), (1, 2));
+        assert_eq!(rotated.get_pixel(0, 0), &image::Rgb([255, 0, 0]));
+        assert_eq!(rotated.get_pixel(0, 1), &image::Rgb([0, 0, 255]));
+        let metadata = ImageMetadata::from_path(&path);
+        assert_eq!(
+            serde_json::to_value(metadata).unwrap()["parameters"]["positive"],
+            "a red fox"
+        );
+    }
+
+    #[test]
+    fn webp_pixels_are_rotated_keeping_exif() {
+        let dir = TempDir::new("webp-rotate");
+        let path = dir.join("image.webp");
+        let mut webp =
+            WebP::from_bytes(encode(&red_blue(), image::ImageFormat::WebP).into()).unwrap();
+        webp.set_exif(Some(b"II*\0\x08\0\0\0\0\0\0\0\0\0".to_vec().into()));
+        webp.encoder()
+            .write_to(File::create(&path).unwrap())
+            .unwrap();
+
+        write_rotated_pixels(&path, ExifOrientation::Rotate270).unwrap();
+        let rotated = image::open(&path).unwrap().to_rgb8();
+        assert_eq!(rotated.dimensions(), (1, 2));
+        assert_eq!(rotated.get_pixel(0, 0), &image::Rgb([0, 0, 255]));
+        let webp = WebP::from_bytes(std::fs::read(&path).unwrap().into()).unwrap();
+        assert!(webp.exif().is_some());
+    }
+
+    #[test]
+    fn failed_write_leaves_no_temporary_file() {
+        let dir = TempDir::new("replace-file");
+        let path = dir.join("photo.jpg");
+        std::fs::write(&path, b"original").unwrap();
+
+        let result = replace_file(&path, |file| {
+            file.write_all(b"partial")?;
+            Err(std::io::Error::other("disk full"))
+        });
+        assert!(result.is_err());
+        assert_eq!(std::fs::read(&path).unwrap(), b"original");
+        assert!(!dir.join("photo.fastview-tmp").exists());
+    }
+
+    #[test]
+    fn generation_parameters_split_into_prompt_negative_and_settings() {
+        let params = GenerationParameters::parse(
+            "a castle on a hill,\ngolden hour\nNegative prompt: blurry,\nlow quality\n\
+             Steps: 30, Sampler: DPM++ 2M Karras, CFG scale: 7.5, Seed: 1234, \
+             Model hash: abc123, Model: sdxl_base",
+        );
+        assert_eq!(params.positive, "a castle on a hill,\ngolden hour");
+        assert_eq!(params.negative.as_deref(), Some("blurry,\nlow quality"));
+        assert_eq!(params.steps.as_deref(), Some("30"));
+        assert_eq!(params.sampler.as_deref(), Some("DPM++ 2M Karras"));
+        assert_eq!(params.cfg_scale.as_deref(), Some("7.5"));
+        assert_eq!(params.seed.as_deref(), Some("1234"));
+        // The model name wins over its hash, whichever comes first
+        assert_eq!(params.model.as_deref(), Some("sdxl_base"));
+    }
+
+    #[test]
+    fn prompt_without_settings_or_negative_is_all_positive() {
+        let params = GenerationParameters::parse("  just a prompt\n");
+        assert_eq!(params.positive, "just a prompt");
+        assert!(params.negative.is_none());
+        assert!(params.to_key_values().is_empty());
+
+        let params = GenerationParameters::parse("a cat\nNegative prompt: \nSteps: 20");
+        assert_eq!(params.positive, "a cat");
+        assert!(params.negative.is_none());
+        assert_eq!(
+            params.to_key_values(),
+            [("Steps".to_string(), "20".to_string())]
+        );
+    }
+
+    #[test]
+    fn model_hash_stands_in_for_a_missing_model_name() {
+        let params = GenerationParameters::parse("a cat\nSteps: 20, Model hash: abc123");
+        assert_eq!(params.model.as_deref(), Some("abc123"));
+    }
+
+    #[test]
+    fn settings_keep_commas_inside_quotes() {
+        assert_eq!(
+            split_settings(r#"Steps: 20, Lora hashes: "a: 1, b: 2", Seed: 5"#),
+            [
+                ("Steps", "20"),
+                ("Lora hashes", "a: 1, b: 2"),
+                ("Seed", "5")
+            ]
+        );
+        // Fragments without a colon are dropped
+        assert_eq!(split_settings("Steps: 20, stray, "), [("Steps", "20")]);
+    }
+
+    /// EXIF holding a GPS position given as (degrees, minutes, seconds) and its N/S and E/W refs
+    fn gps_exif(lat: [u32; 3], lat_ref: &str, lon: [u32; 3], lon_ref: &str) -> exif::Exif {
+        let rationals =
+            |dms: [u32; 3]| exif::Value::Rational(dms.iter().map(|&v| (v, 1).into()).collect());
+        let field = |tag, value| exif::Field {
+            tag,
+            ifd_num: exif::In::PRIMARY,
+            value,
+        };
+        let ascii = |text: &str| exif::Value::Ascii(vec![text.as_bytes().to_vec()]);
+        let fields = [
+            field(exif::Tag::GPSLatitudeRef, ascii(lat_ref)),
+            field(exif::Tag::GPSLatitude, rationals(lat)),
+            field(exif::Tag::GPSLongitudeRef, ascii(lon_ref)),
+            field(exif::Tag::GPSLongitude, rationals(lon)),
+        ];
+        let mut writer = exif::experimental::Writer::new();
+        for field in &fields {
+            writer.push_field(field);
+        }
+        let mut tiff = std::io::Cursor::new(Vec::new());
+        writer.write(&mut tiff, false).unwrap();
+        exif::Reader::new().read_raw(tiff.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn gps_position_is_converted_to_signed_degrees() {
+        let exif = gps_exif([48, 51, 36], "N", [2, 17, 24], "E");
+        let (lat, lon) = ImageMetadata::read_gps_location(&exif).unwrap();
+        assert!((lat - 48.86).abs() < 1e-9, "{lat}");
+        assert!((lon - 2.29).abs() < 1e-9, "{lon}");
+
+        let exif = gps_exif([33, 51, 36], "S", [151, 12, 36], "w");
+        let (lat, lon) = ImageMetadata::read_gps_location(&exif).unwrap();
+        assert!((lat + 33.86).abs() < 1e-9, "{lat}");
+        assert!((lon + 151.21).abs() < 1e-9, "{lon}");
+    }
+
+    #[test]
+    fn all_zero_gps_position_means_no_fix() {
+        let exif = gps_exif([0, 0, 0], "N", [0, 0, 0], "E");
+        assert_eq!(ImageMetadata::read_gps_location(&exif), None);
+    }
+
+    #[test]
+    fn webp_prompt_is_read_with_or_without_the_exif_header() {
+        let dir = TempDir::new("webp-prompt");
+        let workflow =
+            r#"Workflow: {"6": {"inputs": {"text": "a lighthouse on a cliff at dusk"}}}"#;
+        let mut writer = exif::experimental::Writer::new();
+        let description = exif::Field {
+            tag: exif::Tag::ImageDescription,
+            ifd_num: exif::In::PRIMARY,
+            value: exif::Value::Ascii(vec![workflow.as_bytes().to_vec()]),
+        };
+        writer.push_field(&description);
+        let mut tiff = std::io::Cursor::new(Vec::new());
+        writer.write(&mut tiff, false).unwrap();
+        let tiff = tiff.into_inner();
+
+        for (name, keep_header) in [("header.webp", true), ("bare.webp", false)] {
+            let path = dir.join(name);
+            let mut webp =
+                WebP::from_bytes(encode(&red_blue(), image::ImageFormat::WebP).into()).unwrap();
+            // img-parts always writes the header, so the bare chunk is swapped in afterwards
+            webp.set_exif(Some(tiff.clone().into()));
+            if !keep_header {
+                let bare = img_parts::riff::RiffChunk::new(
+                    img_parts::webp::CHUNK_EXIF,
+                    img_parts::riff::RiffContent::Data(tiff.clone().into()),
+                );
+                webp.remove_chunks_by_id(img_parts::webp::CHUNK_EXIF);
+                webp.chunks_mut().push(bare);
+            }
+            webp.encoder()
+                .write_to(File::create(&path).unwrap())
+                .unwrap();
+            assert_eq!(
+                ImageMetadata::read_webp_prompt(&path).as_deref(),
+                Some("a lighthouse on a cliff at dusk"),
+                "{name}"
+            );
+        }
+    }
+
+    /// A JPEG whose EXIF claims a `full` size image and embeds a 32x16 thumbnail
+    fn jpeg_with_exif_thumbnail(path: &Path, full: (u32, u32), orientation: u16) {
+        let thumbnail = encode(
+            &DynamicImage::ImageRgb8(image::RgbImage::new(32, 16)),
+            image::ImageFormat::Jpeg,
+        );
+        let field = |tag, value| exif::Field {
+            tag,
+            ifd_num: exif::In::PRIMARY,
+            value,
+        };
+        let fields = [
+            field(
+                exif::Tag::Orientation,
+                exif::Value::Short(vec![orientation]),
+            ),
+            field(exif::Tag::PixelXDimension, exif::Value::Long(vec![full.0])),
+            field(exif::Tag::PixelYDimension, exif::Value::Long(vec![full.1])),
+        ];
+        let mut writer = exif::experimental::Writer::new();
+        for field in &fields {
+            writer.push_field(field);
+        }
+        writer.set_jpeg(&thumbnail, exif::In::THUMBNAIL);
+        let mut tiff = std::io::Cursor::new(Vec::new());
+        writer.write(&mut tiff, false).unwrap();
+
+        let full_image = DynamicImage::ImageRgb8(image::RgbImage::new(full.0, full.1));
+        let mut jpeg =
+            Jpeg::from_bytes(encode(&full_image, image::ImageFormat::Jpeg).into()).unwrap();
+        jpeg.set_exif(Some(tiff.into_inner().into()));
+        jpeg.encoder()
+            .write_to(File::create(path).unwrap())
+            .unwrap();
+    }
+
+    #[test]
+    fn embedded_thumbnail_is_used_when_large_enough() {
+        let dir = TempDir::new("exif-thumbnail");
+        let path = dir.join("photo.jpg");
+        jpeg_with_exif_thumbnail(&path, (64, 32), 6);
+
+        // Turned upright like the full image would be
+        let thumb = read_exif_thumbnail(&path, 16).unwrap();
+        assert_eq!((thumb.width(), thumb.height()), (16, 32));
+        assert!(read_exif_thumbnail(&path, 17).is_none());
+    }
+
+    #[test]
+    fn embedded_thumbnail_of_another_shape_is_ignored() {
+        let dir = TempDir::new("exif-thumbnail-aspect");
+        let path = dir.join("photo.jpg");
+        // Cameras letterbox thumbnails of images cropped in camera
+        jpeg_with_exif_thumbnail(&path, (48, 48), 1);
+        assert!(read_exif_thumbnail(&path, 16).is_none());
+    }
+
+    #[test]
+    fn tiff_orientation_is_read_from_its_first_directory() {
+        let dir = TempDir::new("tiff-orientation");
+        let path = dir.join("scan.tif");
+        let mut tiff = encode(&red_blue(), image::ImageFormat::Tiff);
+        std::fs::write(&path, &tiff).unwrap();
+        assert_eq!(read_orientation_only(&path), ExifOrientation::Normal);
+
+        set_tiff_orientation(&mut tiff, 6).unwrap();
+        std::fs::write(&path, &tiff).unwrap();
+        assert_eq!(read_orientation_only(&path), ExifOrientation::Rotate90);
+        let exif = ImageMetadata::read_exif_data(&path).unwrap();
+        assert_eq!(exif.orientation, ExifOrientation::Rotate90);
+        // The pixels are still where the strip offsets say
+        assert_eq!(image::open(&path).unwrap().to_rgb8(), red_blue().to_rgb8());
+    }
+
+    #[test]
+    fn lossy_webp_pixels_are_not_rewritten() {
+        let dir = TempDir::new("webp-lossy");
+        let path = dir.join("photo.webp");
+        // Only the container is looked at before refusing, so the VP8 data can be a stub
+        let lossy = [&b"RIFF\x14\0\0\0WEBPVP8 \x08\0\0\0"[..], &[0; 8]].concat();
+        std::fs::write(&path, &lossy).unwrap();
+
+        let error = write_rotated_pixels(&path, ExifOrientation::Rotate90).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::Unsupported);
+        assert_eq!(std::fs::read(&path).unwrap(), lossy);
+    }
+}
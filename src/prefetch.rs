@@ -0,0 +1,65 @@
+use image::RgbaImage;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Full-resolution images for the current one and its neighbours
+const PREFETCH_CAPACITY: usize = 3;
+
+// None records a file that failed to decode
+type Entry = (PathBuf, Option<RgbaImage>);
+
+/// Decoded full-size images shared between the UI and the loader thread,
+/// evicting the least recently used entry once full. Files that failed to
+/// decode are remembered too, so the loader doesn't retry them.
+#[derive(Clone, Default)]
+pub struct PrefetchCache {
+    entries: Arc<Mutex<VecDeque<Entry>>>,
+}
+
+impl PrefetchCache {
+    pub fn get(&self, path: &Path) -> Option<RgbaImage> {
+        let mut entries = self.entries.lock().ok()?;
+        let pos = entries.iter().position(|(p, _)| p == path)?;
+        let entry = entries.remove(pos)?;
+        let img = entry.1.clone();
+        entries.push_back(entry);
+        img
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.entries
+            .lock()
+            .is_ok_and(|entries| entries.iter().any(|(p, _)| p == path))
+    }
+
+    pub fn insert(&self, path: PathBuf, img: RgbaImage) {
+        self.store(path, Some(img));
+    }
+
+    pub fn mark_failed(&self, path: PathBuf) {
+        self.store(path, None);
+    }
+
+    fn store(&self, path: PathBuf, img: Option<RgbaImage>) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.retain(|(p, _)| *p != path);
+            if entries.len() >= PREFETCH_CAPACITY {
+                entries.pop_front();
+            }
+            entries.push_back((path, img));
+        }
+    }
+
+    pub fn remove(&self, path: &Path) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.retain(|(p, _)| p != path);
+        }
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.clear();
+        }
+    }
+}
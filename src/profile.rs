@@ -0,0 +1,40 @@
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+static ENABLED: OnceLock<bool> = OnceLock::new();
+
+/// Whether `FASTVIEW_PROFILE` asks for timing logs. The variable is read on the
+/// first call, which `main` makes at startup; after that this is a single load.
+pub fn enabled() -> bool {
+    *ENABLED.get_or_init(|| {
+        std::env::var("FASTVIEW_PROFILE").is_ok_and(|value| !matches!(value.trim(), "" | "0"))
+    })
+}
+
+/// Runs `work`, logging how long it took when profiling is on.
+pub fn time<T>(what: &str, path: &Path, work: impl FnOnce() -> T) -> T {
+    if !enabled() {
+        return work();
+    }
+    let start = Instant::now();
+    let result = work();
+    log::info!(
+        "{} {} in {:.1} ms",
+        what,
+        path.display(),
+        start.elapsed().as_secs_f64() * 1000.0
+    );
+    result
+}
+
+/// Logs whether `path` was found in the thumbnail cache.
+pub fn cache_lookup(path: &Path, hit: bool) {
+    if enabled() {
+        log::info!(
+            "Thumbnail cache {} for {}",
+            if hit { "hit" } else { "miss" },
+            path.display()
+        );
+    }
+}
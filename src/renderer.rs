@@ -1,7 +1,121 @@
 use bytemuck::{Pod, Zeroable};
 use image::RgbaImage;
-use std::path::PathBuf;
-use wgpu;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+/// Time constant of the exponential easing used for smooth zoom and scroll
+const MOTION_TIME_CONSTANT: f32 = 0.06;
+/// Distance in pixels below which an animation snaps to its target
+const MOTION_SETTLE_PIXELS: f32 = 0.5;
+/// Time constant in seconds of the slowdown after a trackpad flick
+const FLING_TIME_CONSTANT: f32 = 0.325;
+/// Release speed in pixels per second below which a trackpad gesture just stops
+const MIN_FLING_VELOCITY: f32 = 50.0;
+/// Pause between trackpad deltas after which the gesture counts as a new one
+const FLING_GAP: Duration = Duration::from_millis(100);
+/// Thumbnail textures kept for reuse after the grid is cleared (256x256 with mips is ~350 KB)
+const TEXTURE_POOL_LIMIT: usize = 256;
+/// Edge of one layer of the grid's array texture; thumbnails are at most this size
+const GRID_LAYER_SIZE: u32 = 256;
+/// Most layers the array texture grows to, further capped by the device limit (~350 KB each)
+const GRID_LAYER_LIMIT: u32 = 256;
+/// Layers the array texture starts with; it doubles whenever they are all taken
+const GRID_LAYER_CHUNK: u32 = 32;
+/// Grid scrollbar thumb width, its gap to the window edges, and its shortest length
+const SCROLLBAR_WIDTH: f32 = 6.0;
+const SCROLLBAR_MARGIN: f32 = 3.0;
+const SCROLLBAR_MIN_THUMB: f32 = 32.0;
+/// Width of the strip along the right edge that hovers and grabs the scrollbar
+pub const SCROLLBAR_HIT_WIDTH: f32 = 16.0;
+/// Band along the top of the grid kept clear for the breadcrumb bar
+pub const GRID_HEADER_HEIGHT: f32 = 28.0;
+/// How long the scrollbar stays after scrolling or hovering, then how long it fades
+const SCROLLBAR_IDLE: Duration = Duration::from_secs(1);
+const SCROLLBAR_FADE: Duration = Duration::from_millis(400);
+
+/// What single view shows behind and around the image.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Background {
+    #[default]
+    Black,
+    White,
+    Checkerboard,
+}
+
+impl Background {
+    pub fn next(self) -> Self {
+        match self {
+            Background::Black => Background::White,
+            Background::White => Background::Checkerboard,
+            Background::Checkerboard => Background::Black,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Background::Black => "Black",
+            Background::White => "White",
+            Background::Checkerboard => "Checkerboard",
+        }
+    }
+
+    fn shader_value(self) -> f32 {
+        match self {
+            Background::Black => 0.0,
+            Background::White => 1.0,
+            Background::Checkerboard => 2.0,
+        }
+    }
+}
+
+/// How single view sizes an image when it is first shown.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum FitMode {
+    #[default]
+    FitWhole,
+    FitWidth,
+    FitHeight,
+    FillWindow,
+}
+
+impl FitMode {
+    pub fn next(self) -> Self {
+        match self {
+            FitMode::FitWhole => FitMode::FitWidth,
+            FitMode::FitWidth => FitMode::FitHeight,
+            FitMode::FitHeight => FitMode::FillWindow,
+            FitMode::FillWindow => FitMode::FitWhole,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FitMode::FitWhole => "Fit",
+            FitMode::FitWidth => "Fit width",
+            FitMode::FitHeight => "Fit height",
+            FitMode::FillWindow => "Fill",
+        }
+    }
+
+    /// Zoom for this mode. Without `upscale`, images are never enlarged past 100%.
+    pub fn zoom_for(self, image_size: [f32; 2], window_size: [f32; 2], upscale: bool) -> f32 {
+        let width_zoom = window_size[0] / image_size[0];
+        let height_zoom = window_size[1] / image_size[1];
+        let zoom = match self {
+            FitMode::FitWhole => width_zoom.min(height_zoom),
+            FitMode::FitWidth => width_zoom,
+            FitMode::FitHeight => height_zoom,
+            FitMode::FillWindow => width_zoom.max(height_zoom),
+        };
+        if upscale {
+            zoom
+        } else {
+            zoom.min(1.0)
+        }
+    }
+}
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
@@ -11,18 +125,334 @@ pub struct Params {
     pub pan: [f32; 2],
     pub zoom: f32,
     pub is_grid_item: f32, // 0.0 for single view, 1.0 for grid
-    pub is_selected: f32,
-    pub _pad: f32,
-    pub _pad2: [f32; 2], // Pad to 48 bytes (12 floats)
+    pub is_selected: f32,  // Grid only: 1.0 for the cursor, plus 2.0 when multi-selected
+    pub background: f32,   // Background::shader_value in single view
+    pub _pad2: [f32; 2],   // Pad to 48 bytes (12 floats)
+}
+
+/// Bytes last written to a buffer, so frames where nothing changed skip the upload.
+#[derive(Default)]
+struct Uploaded(Vec<u8>);
+
+impl Uploaded {
+    /// Writes `bytes` unless the buffer already holds them; returns whether it wrote.
+    fn write(&mut self, queue: &wgpu::Queue, buffer: &wgpu::Buffer, bytes: &[u8]) -> bool {
+        if self.0 == bytes {
+            return false;
+        }
+        queue.write_buffer(buffer, 0, bytes);
+        self.0.clear();
+        self.0.extend_from_slice(bytes);
+        true
+    }
+}
+
+/// Per-cell data for the batched grid draw, laid out as in `grid.wgsl`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct GridInstance {
+    origin: [f32; 2],
+    image_size: [f32; 2],
+    uv_scale: [f32; 2],
+    cell: f32,
+    // Same flags as Params::is_selected
+    selected: f32,
+    layer: u32,
+    status: u32,
+}
+
+impl GridInstance {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 7] = wgpu::vertex_attr_array![
+        0 => Float32x2,
+        1 => Float32x2,
+        2 => Float32x2,
+        3 => Float32,
+        4 => Float32,
+        5 => Uint32,
+        6 => Uint32,
+    ];
+
+    // Values of `status`, matching `grid.wgsl`
+    const LOADED: u32 = 0;
+    const LOADING: u32 = 1;
+    const FAILED: u32 = 2;
+}
+
+/// Cell size and spacing, in pixels, shared by grid rendering and hit-testing.
+#[derive(Copy, Clone, Debug)]
+pub struct GridLayout {
+    pub cell: f32,
+    pub spacing: f32,
+    // Space above the first row, which the breadcrumb bar covers
+    pub top: f32,
+}
+
+impl Default for GridLayout {
+    fn default() -> Self {
+        GridLayout {
+            cell: 250.0,
+            spacing: 20.0,
+            top: GRID_HEADER_HEIGHT,
+        }
+    }
+}
+
+impl GridLayout {
+    pub const MIN_CELL: f32 = 80.0;
+    pub const MAX_CELL: f32 = 640.0;
+
+    pub fn stride(&self) -> f32 {
+        self.cell + self.spacing
+    }
+
+    pub fn columns(&self, window_width: f32) -> u32 {
+        (window_width / self.stride()).floor().max(1.0) as u32
+    }
+
+    pub fn rows_per_page(&self, window_height: f32) -> u32 {
+        (window_height / self.stride()).floor().max(1.0) as u32
+    }
+
+    pub fn content_height(&self, item_count: usize, window_width: f32) -> f32 {
+        let cols = self.columns(window_width);
+        let rows = (item_count as f32 / cols as f32).ceil();
+        self.top + rows * self.stride() + self.spacing
+    }
+
+    /// Top-left corner of the cell at `index`, ignoring scroll.
+    pub fn item_origin(&self, index: usize, cols: u32) -> [f32; 2] {
+        let col = (index as u32) % cols;
+        let row = (index as u32) / cols;
+        [
+            self.spacing + col as f32 * self.stride(),
+            self.top + self.spacing + row as f32 * self.stride(),
+        ]
+    }
+
+    /// Arrow-key move from `index` among `total` items laid out in `cols` columns.
+    /// Horizontal steps stay within the row, stopping at its first and last cell.
+    /// Vertical steps keep the column, landing on the last item when the
+    /// bottom row is too short, and stop at the first and last rows.
+    pub fn step(index: usize, dx: i32, dy: i32, cols: u32, total: usize) -> usize {
+        if total == 0 {
+            return 0;
+        }
+        let cols = cols.max(1) as i64;
+        let last = total as i64 - 1;
+        let index = (index as i64).min(last);
+        let (mut row, mut col) = (index / cols, index % cols);
+
+        row = (row + dy as i64).clamp(0, last / cols);
+        let row_end = (row * cols + cols - 1).min(last);
+        col = (col + dx as i64).clamp(0, row_end - row * cols);
+        (row * cols + col).min(last) as usize
+    }
+
+    /// Scroll that brings the cell at `index` into view with as small a move as
+    /// possible, or `scroll` unchanged if it is already fully shown.
+    pub fn scroll_to_show(&self, index: usize, scroll: f32, window_size: [f32; 2]) -> f32 {
+        let cols = self.columns(window_size[0]);
+        let item_top = self.item_origin(index, cols)[1];
+        let item_bottom = item_top + self.cell;
+
+        // If above current view, or under the breadcrumb bar
+        if item_top < -scroll + self.top {
+            -item_top + self.top + self.spacing
+        }
+        // If below current view
+        else if item_bottom > -scroll + window_size[1] {
+            -item_bottom + window_size[1] - self.spacing
+        } else {
+            scroll
+        }
+    }
+
+    /// Index of the cell under the screen position, if any. Not bounded by item count.
+    pub fn item_at(&self, x: f64, y: f64, scroll: f32, window_width: f32) -> Option<usize> {
+        let stride = self.stride() as f64;
+        let col = ((x - self.spacing as f64) / stride).floor() as i32;
+        let row = ((y - scroll as f64 - (self.top + self.spacing) as f64) / stride).floor() as i32;
+        let cols = self.columns(window_width);
+
+        if col >= 0 && col < cols as i32 && row >= 0 {
+            Some((row as u32 * cols + col as u32) as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Range of item indices intersecting the viewport at the given scroll offset.
+    pub fn visible_range(&self, scroll: f32, window_size: [f32; 2]) -> std::ops::Range<usize> {
+        let cols = self.columns(window_size[0]);
+        let start_row = ((-scroll - self.top - self.spacing) / self.stride())
+            .floor()
+            .max(0.0) as u32;
+        let end_row =
+            ((-scroll - self.top + window_size[1] + self.spacing) / self.stride()).ceil() as u32;
+        (start_row * cols) as usize..(end_row * cols) as usize
+    }
 }
 
 pub struct GridItem {
-    pub path: PathBuf,
-    pub is_directory: bool,
-    pub texture_bind_group: wgpu::BindGroup,
-    pub params_buffer: wgpu::Buffer,
-    pub params_bind_group: wgpu::BindGroup,
     pub image_size: [f32; 2],
+    // Thumbnail asked of the loader and not yet delivered
+    requested: bool,
+    // Only items near the viewport hold GPU resources
+    retained: bool,
+    // The last load of this file failed; it shows as broken until a retry succeeds
+    load_failed: bool,
+    // None until the thumbnail arrives; drawn as a placeholder meanwhile
+    thumbnail: Option<GridThumbnail>,
+}
+
+enum GridThumbnail {
+    // Layer of the shared array texture, drawn in the batched call
+    Layer(u32),
+    // Own texture, drawn separately: not full size, or all GRID_LAYER_LIMIT layers are taken
+    Texture(Box<GridTexture>),
+}
+
+/// Array texture holding most grid thumbnails, so visible cells take one instanced draw.
+/// Array textures are available on every backend; the separately drawn textures are only
+/// for thumbnails that aren't full size, or that arrive once GRID_LAYER_LIMIT layers are taken.
+struct GridLayers {
+    texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+    // Layer 0 is never written; placeholders point at it but draw a card instead
+    free: Vec<u32>,
+    instance_buffer: wgpu::Buffer,
+    instance_capacity: usize,
+    instances_uploaded: Uploaded,
+    view_buffer: wgpu::Buffer,
+    view_bind_group: wgpu::BindGroup,
+    view_uploaded: Uploaded,
+}
+
+/// A mipmapped thumbnail texture with the uniforms for drawing it, kept so
+/// same-sized updates are written in place.
+pub struct GridTexture {
+    texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+    params_buffer: wgpu::Buffer,
+    params_bind_group: wgpu::BindGroup,
+    params_uploaded: Uploaded,
+}
+
+impl GridTexture {
+    fn dimensions(&self) -> (u32, u32) {
+        (self.texture.width(), self.texture.height())
+    }
+}
+
+/// Second image shown right of the divider in compare mode.
+struct CompareView {
+    // Kept so the bind group can be rebuilt when the sampler changes
+    texture: wgpu::Texture,
+    texture_bind_group: wgpu::BindGroup,
+    image_size: [f32; 2],
+    params_buffer: wgpu::Buffer,
+    params_bind_group: wgpu::BindGroup,
+    params_uploaded: Uploaded,
+    divider_bind_group: wgpu::BindGroup,
+    divider_params_buffer: wgpu::Buffer,
+    divider_params_bind_group: wgpu::BindGroup,
+    divider_uploaded: Uploaded,
+}
+
+/// Full-resolution part of an image too big for one texture, drawn over the
+/// downscaled copy once the zoom shows more detail than that copy holds.
+struct DetailTile {
+    // Image pixels the texture covers: x, y, width, height
+    region: [u32; 4],
+    // Kept so the bind group can be rebuilt when the sampler changes
+    texture: wgpu::Texture,
+    texture_bind_group: wgpu::BindGroup,
+    params_buffer: wgpu::Buffer,
+    params_bind_group: wgpu::BindGroup,
+    params_uploaded: Uploaded,
+}
+
+/// A screen-space image drawn over the view, such as the metadata panel.
+struct Overlay {
+    params_buffer: wgpu::Buffer,
+    params_bind_group: wgpu::BindGroup,
+    params_uploaded: Uploaded,
+    // Kept so a same-sized update can rewrite the texture in place
+    texture: Option<(wgpu::Texture, wgpu::BindGroup)>,
+    rect: [f32; 4], // [x, y, w, h]
+}
+
+impl Overlay {
+    fn new(device: &wgpu::Device, params_layout: &wgpu::BindGroupLayout, label: &str) -> Self {
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: std::mem::size_of::<Params>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let params_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: params_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            }],
+            label: Some(label),
+        });
+        Overlay {
+            params_buffer,
+            params_bind_group,
+            params_uploaded: Uploaded::default(),
+            texture: None,
+            rect: [0.0; 4],
+        }
+    }
+
+    fn draw(
+        &mut self,
+        queue: &wgpu::Queue,
+        pipeline: &wgpu::RenderPipeline,
+        window_size: [f32; 2],
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+    ) -> u32 {
+        let Some((_, bind_group)) = &self.texture else {
+            return 0;
+        };
+        let [x, y, width, height] = self.rect;
+        let params = Params {
+            image_size: [1.0, 1.0], // 1:1 map, no aspect correction in shader
+            window_size,
+            pan: [x, y],
+            zoom: width,
+            is_grid_item: 1.0,
+            is_selected: 0.0,
+            background: 0.0,
+            _pad2: [height, 0.0], // Pass height for non-square quad
+        };
+        let uploads =
+            self.params_uploaded
+                .write(queue, &self.params_buffer, bytemuck::bytes_of(&params)) as u32;
+
+        let mut rp = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        rp.set_pipeline(pipeline);
+        rp.set_bind_group(0, bind_group, &[]);
+        rp.set_bind_group(1, &self.params_bind_group, &[]);
+        rp.draw(0..3, 0..1);
+        uploads
+    }
 }
 
 pub struct Renderer {
@@ -30,37 +460,70 @@ pub struct Renderer {
     queue: wgpu::Queue,
     surface: wgpu::Surface<'static>,
     config: wgpu::SurfaceConfiguration,
+    present_modes: Vec<wgpu::PresentMode>,
     render_pipeline: wgpu::RenderPipeline,
+    grid_pipeline: wgpu::RenderPipeline,
 
     // Single view state
+    diffuse_texture: wgpu::Texture,
     diffuse_bind_group: wgpu::BindGroup,
+    // Longest texture edge the device accepts; bigger images are downscaled to it
+    max_texture_size: u32,
+    // Full-resolution copy of a downscaled image, and the part of it on the GPU
+    detail_source: Option<RgbaImage>,
+    detail_tile: Option<DetailTile>,
 
     // Common resources
     texture_bind_group_layout: wgpu::BindGroupLayout,
+    layers_bind_group_layout: wgpu::BindGroupLayout,
     params_bind_group_layout: wgpu::BindGroupLayout,
 
     // Global params (used for single view)
     params_buffer: wgpu::Buffer,
     params_bind_group: wgpu::BindGroup,
+    params_uploaded: Uploaded,
     params: Params,
 
-    // Overlay params (separate to avoid conflicts)
-    overlay_params_buffer: wgpu::Buffer,
-    overlay_params_bind_group: wgpu::BindGroup,
-
     // Grid view state
     pub grid_items: Vec<GridItem>,
     pub grid_scroll: f32,
+    grid_layout: GridLayout,
+    grid_layers: Option<GridLayers>,
+    texture_pool: Vec<GridTexture>,
 
     // Samplers
     sampler_linear: wgpu::Sampler,
     sampler_nearest: wgpu::Sampler,
+    sampler_grid: wgpu::Sampler,
     pub is_nearest: bool,
-
-    // Overlay
-    overlay_bind_group: Option<wgpu::BindGroup>,
-    overlay_size: Option<[f32; 2]>,
-    overlay_rect: Option<[f32; 4]>, // [x, y, w, h]
+    background: Background,
+
+    // Metadata panel in single view, and the status bar drawn in every mode
+    overlay: Overlay,
+    status_bar: Overlay,
+    // Grid scrollbar thumb, its uploaded alpha, and when it was last woken
+    scrollbar: Overlay,
+    breadcrumb: Overlay,
+    scrollbar_alpha: u8,
+    scrollbar_woken: Option<Instant>,
+    scrollbar_drawn_at: f32,
+
+    // Compare mode: divider position as a fraction of the window width
+    compare: Option<CompareView>,
+    compare_split: f32,
+    compare_synced: bool,
+
+    // Eased motion: `params` and `grid_scroll` hold the targets, these are what is drawn
+    smooth_motion: bool,
+    shown_zoom: f32,
+    shown_pan: [f32; 2],
+    shown_scroll: f32,
+    last_motion_step: Option<Instant>,
+
+    // Trackpad momentum: speed of the last gesture, and that of the fling after its release
+    touch_velocity: f32,
+    last_touch_scroll: Option<Instant>,
+    fling_velocity: f32,
 }
 
 impl Renderer {
@@ -80,12 +543,25 @@ impl Renderer {
             .find(|f| f.is_srgb())
             .unwrap_or(surface_caps.formats[0]);
 
+        // Fifo is always available; FASTVIEW_VSYNC=off asks for Mailbox where supported
+        let vsync_off =
+            std::env::var("FASTVIEW_VSYNC").is_ok_and(|v| v.eq_ignore_ascii_case("off"));
+        let present_mode = if vsync_off
+            && surface_caps
+                .present_modes
+                .contains(&wgpu::PresentMode::Mailbox)
+        {
+            wgpu::PresentMode::Mailbox
+        } else {
+            wgpu::PresentMode::Fifo
+        };
+
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
             format: surface_format,
             width: if width == 0 { 1 } else { width },
             height: if height == 0 { 1 } else { height },
-            present_mode: wgpu::PresentMode::Fifo,
+            present_mode,
             alpha_mode: surface_caps.alpha_modes[0],
             view_formats: vec![],
             desired_maximum_frame_latency: 2,
@@ -119,6 +595,29 @@ impl Renderer {
                 label: Some("texture_bind_group_layout"),
             });
 
+        let layers_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2Array,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+                label: Some("layers_bind_group_layout"),
+            });
+
         let params_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 entries: &[wgpu::BindGroupLayoutEntry {
@@ -179,6 +678,56 @@ impl Renderer {
             cache: None,
         });
 
+        let grid_shader = device.create_shader_module(wgpu::include_wgsl!("grid.wgsl"));
+
+        let grid_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Grid Pipeline Layout"),
+            bind_group_layouts: &[&layers_bind_group_layout, &params_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let grid_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Grid Pipeline"),
+            layout: Some(&grid_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &grid_shader,
+                entry_point: Some("vs_grid"),
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<GridInstance>() as u64,
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    attributes: &GridInstance::ATTRIBUTES,
+                }],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &grid_shader,
+                entry_point: Some("fs_grid"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        });
+
         let params = Params {
             image_size: [1.0, 1.0],
             window_size: [width as f32, height as f32],
@@ -186,7 +735,7 @@ impl Renderer {
             zoom: 1.0,
             is_grid_item: 0.0,
             is_selected: 0.0,
-            _pad: 0.0,
+            background: 0.0,
             _pad2: [0.0; 2],
         };
 
@@ -206,21 +755,10 @@ impl Renderer {
             label: Some("params_bind_group"),
         });
 
-        let overlay_params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Overlay Params Buffer"),
-            size: std::mem::size_of::<Params>() as u64,
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-
-        let overlay_params_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &params_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: overlay_params_buffer.as_entire_binding(),
-            }],
-            label: Some("overlay_params_bind_group"),
-        });
+        let overlay = Overlay::new(&device, &params_bind_group_layout, "overlay_params");
+        let status_bar = Overlay::new(&device, &params_bind_group_layout, "status_bar_params");
+        let scrollbar = Overlay::new(&device, &params_bind_group_layout, "scrollbar_params");
+        let breadcrumb = Overlay::new(&device, &params_bind_group_layout, "breadcrumb_params");
 
         let sampler_linear = device.create_sampler(&wgpu::SamplerDescriptor {
             address_mode_u: wgpu::AddressMode::ClampToEdge,
@@ -231,6 +769,16 @@ impl Renderer {
             ..Default::default()
         });
 
+        // Grid thumbnails are minified a lot, so they blend between mip levels
+        let sampler_grid = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
         let sampler_nearest = device.create_sampler(&wgpu::SamplerDescriptor {
             address_mode_u: wgpu::AddressMode::ClampToEdge,
             address_mode_v: wgpu::AddressMode::ClampToEdge,
@@ -240,11 +788,13 @@ impl Renderer {
             ..Default::default()
         });
 
-        let diffuse_bind_group = Self::create_texture_bind_group(
+        let max_texture_size = device.limits().max_texture_dimension_2d;
+        let diffuse_texture =
+            Self::create_image_texture(&device, &queue, &RgbaImage::new(1, 1), max_texture_size);
+        let diffuse_bind_group = Self::create_bind_group(
             &device,
-            &queue,
             &texture_bind_group_layout,
-            &RgbaImage::new(1, 1),
+            &diffuse_texture,
             &sampler_linear,
         );
 
@@ -253,24 +803,72 @@ impl Renderer {
             queue,
             surface,
             config,
+            present_modes: surface_caps.present_modes,
             render_pipeline,
+            grid_pipeline,
+            diffuse_texture,
+            max_texture_size,
+            detail_source: None,
+            detail_tile: None,
             diffuse_bind_group,
             texture_bind_group_layout,
+            layers_bind_group_layout,
             params_bind_group_layout,
             params,
             params_buffer,
             params_bind_group,
-            overlay_params_buffer,
-            overlay_params_bind_group,
+            params_uploaded: Uploaded::default(),
             grid_items: Vec::new(),
             grid_scroll: 0.0,
+            grid_layout: GridLayout::default(),
+            grid_layers: None,
+            texture_pool: Vec::new(),
             sampler_linear,
             sampler_nearest,
+            sampler_grid,
             is_nearest: false,
-            overlay_bind_group: None,
-            overlay_size: None,
-            overlay_rect: None,
+            background: Background::default(),
+            overlay,
+            status_bar,
+            scrollbar,
+            breadcrumb,
+            scrollbar_alpha: 0,
+            scrollbar_woken: None,
+            scrollbar_drawn_at: 0.0,
+            compare: None,
+            compare_split: 0.5,
+            compare_synced: true,
+            smooth_motion: true,
+            shown_zoom: params.zoom,
+            shown_pan: params.pan,
+            shown_scroll: 0.0,
+            last_motion_step: None,
+            touch_velocity: 0.0,
+            last_touch_scroll: None,
+            fling_velocity: 0.0,
+        }
+    }
+
+    pub fn get_background(&self) -> Background {
+        self.background
+    }
+
+    pub fn set_background(&mut self, background: Background) {
+        self.background = background;
+    }
+
+    pub fn get_present_mode(&self) -> wgpu::PresentMode {
+        self.config.present_mode
+    }
+
+    /// Reconfigures the surface with `mode`. Returns false if the surface doesn't support it.
+    pub fn set_present_mode(&mut self, mode: wgpu::PresentMode) -> bool {
+        if !self.present_modes.contains(&mode) {
+            return false;
         }
+        self.config.present_mode = mode;
+        self.surface.configure(&self.device, &self.config);
+        true
     }
 
     pub fn resize(&mut self, width: u32, height: u32) {
@@ -281,69 +879,419 @@ impl Renderer {
         self.config.height = height;
         self.params.window_size = [width as f32, height as f32];
         self.surface.configure(&self.device, &self.config);
+        self.clamp_pan();
     }
 
-    pub fn update_texture(&mut self, img: &RgbaImage) {
-        let dimensions = img.dimensions();
-        self.params.image_size = [dimensions.0 as f32, dimensions.1 as f32];
+    /// Shows `img` in single view, centered. Taking ownership lets an image too big for
+    /// the GPU be kept at full resolution for zooming in without copying it.
+    pub fn update_texture(&mut self, img: RgbaImage) {
         self.params.pan = [0.0, 0.0];
+        self.replace_image(Cow::Owned(img));
+        self.snap_motion();
+    }
 
-        let sampler = if self.is_nearest {
-            &self.sampler_nearest
-        } else {
-            &self.sampler_linear
-        };
-        self.diffuse_bind_group = Self::create_texture_bind_group(
+    /// Replaces the single-view texture while keeping the current zoom and pan.
+    /// Used for animation frames, which stay with the caller; an oversized one is copied.
+    pub fn update_frame(&mut self, img: &RgbaImage) {
+        self.replace_image(Cow::Borrowed(img));
+    }
+
+    fn replace_image(&mut self, img: Cow<'_, RgbaImage>) {
+        let dimensions = img.dimensions();
+        self.params.image_size = [dimensions.0 as f32, dimensions.1 as f32];
+        self.clamp_pan();
+
+        self.diffuse_texture =
+            Self::create_image_texture(&self.device, &self.queue, &img, self.max_texture_size);
+        self.detail_tile = None;
+        self.detail_source =
+            (dimensions.0.max(dimensions.1) > self.max_texture_size).then(|| img.into_owned());
+        self.diffuse_bind_group = Self::create_bind_group(
             &self.device,
-            &self.queue,
             &self.texture_bind_group_layout,
-            img,
-            sampler,
+            &self.diffuse_texture,
+            self.image_sampler(),
         );
     }
 
-    pub fn set_filtering(&mut self, nearest: bool, img: Option<&RgbaImage>) {
-        self.is_nearest = nearest;
-        if let Some(image) = img {
-            self.update_texture(image);
+    /// Sampler for the single-view and compare images; the grid always filters linearly.
+    fn image_sampler(&self) -> &wgpu::Sampler {
+        if self.is_nearest {
+            &self.sampler_nearest
+        } else {
+            &self.sampler_linear
         }
     }
 
-    pub fn set_overlay(&mut self, img: Option<&RgbaImage>, rect: Option<[f32; 4]>) {
-        if let Some(img) = img {
-            let size = [img.width() as f32, img.height() as f32];
-            self.overlay_size = Some(size);
-            self.overlay_rect = rect;
-            self.overlay_bind_group = Some(Self::create_texture_bind_group(
+    /// Switches the single-view and compare images between nearest and linear
+    /// sampling without uploading them again.
+    pub fn set_filtering(&mut self, nearest: bool) {
+        if self.is_nearest == nearest {
+            return;
+        }
+        self.is_nearest = nearest;
+        self.diffuse_bind_group = Self::create_bind_group(
+            &self.device,
+            &self.texture_bind_group_layout,
+            &self.diffuse_texture,
+            self.image_sampler(),
+        );
+        if let Some(compare) = &self.compare {
+            let bind_group = Self::create_bind_group(
                 &self.device,
-                &self.queue,
                 &self.texture_bind_group_layout,
-                img,
-                &self.sampler_linear,
-            ));
-        } else {
-            self.overlay_bind_group = None;
-            self.overlay_size = None;
-            self.overlay_rect = None;
+                &compare.texture,
+                self.image_sampler(),
+            );
+            if let Some(compare) = &mut self.compare {
+                compare.texture_bind_group = bind_group;
+            }
+        }
+        if let Some(tile) = &self.detail_tile {
+            let bind_group = Self::create_bind_group(
+                &self.device,
+                &self.texture_bind_group_layout,
+                &tile.texture,
+                self.image_sampler(),
+            );
+            if let Some(tile) = &mut self.detail_tile {
+                tile.texture_bind_group = bind_group;
+            }
         }
     }
 
-    pub fn add_grid_item(&mut self, path: PathBuf, is_directory: bool, img: Option<&RgbaImage>) {
-        let placeholder = RgbaImage::new(1, 1);
-        let actual_img = img.unwrap_or(&placeholder);
+    /// Uploads the full-resolution pixels around the visible part of a
+    /// downscaled image when the zoom calls for them, or drops them when not.
+    /// Keeps the current tile while the view stays inside it.
+    fn update_detail_tile(&mut self, zoom: f32, pan: [f32; 2]) {
+        let Some(source) = &self.detail_source else {
+            return;
+        };
+        let (width, height) = source.dimensions();
+        // Up to this zoom the downscaled texture has every pixel the screen can show
+        let texture_scale = self.max_texture_size as f32 / width.max(height) as f32;
+        let visible = visible_region(self.params.image_size, self.params.window_size, pan, zoom);
+        let Some(visible) = visible.filter(|_| zoom > texture_scale) else {
+            self.detail_tile = None;
+            return;
+        };
+        if let Some(tile) = &self.detail_tile {
+            if region_contains(tile.region, visible) {
+                return;
+            }
+        }
+        let region = detail_region(visible, (width, height), self.max_texture_size);
+        if self
+            .detail_tile
+            .as_ref()
+            .is_some_and(|tile| tile.region == region)
+        {
+            return;
+        }
 
-        let sampler = &self.sampler_linear;
-        let texture_bind_group = Self::create_texture_bind_group(
+        let [x, y, tile_width, tile_height] = region;
+        let pixels = image::imageops::crop_imm(source, x, y, tile_width, tile_height).to_image();
+        let texture =
+            Self::create_image_texture(&self.device, &self.queue, &pixels, self.max_texture_size);
+        let texture_bind_group = Self::create_bind_group(
             &self.device,
-            &self.queue,
             &self.texture_bind_group_layout,
-            actual_img,
-            sampler,
+            &texture,
+            self.image_sampler(),
         );
+        let (params_buffer, params_bind_group) = match self.detail_tile.take() {
+            Some(tile) => (tile.params_buffer, tile.params_bind_group),
+            None => self.create_params_binding("detail_tile"),
+        };
+        self.detail_tile = Some(DetailTile {
+            region,
+            texture,
+            texture_bind_group,
+            params_buffer,
+            params_bind_group,
+            params_uploaded: Uploaded::default(),
+        });
+    }
 
-        let params_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Grid Item Params Buffer"),
-            size: std::mem::size_of::<Params>() as u64,
+    /// Places the detail tile's `region` where that part of the image is drawn by `shown`.
+    fn detail_params(&self, region: [u32; 4], shown: &Params) -> Params {
+        let [x, y, width, height] = region.map(|v| v as f32);
+        let [img_w, img_h] = self.params.image_size;
+        Params {
+            image_size: [width, height],
+            pan: [
+                shown.pan[0] + (x + width / 2.0 - img_w / 2.0) * shown.zoom,
+                shown.pan[1] + (y + height / 2.0 - img_h / 2.0) * shown.zoom,
+            ],
+            // Single view: marks a tile, which leaves the rest of the window alone
+            _pad2: [1.0, 0.0],
+            ..*shown
+        }
+    }
+
+    pub fn set_overlay(&mut self, img: Option<&RgbaImage>, rect: Option<[f32; 4]>) {
+        Self::update_overlay(
+            &self.device,
+            &self.queue,
+            &self.texture_bind_group_layout,
+            &self.sampler_linear,
+            &mut self.overlay,
+            img.zip(rect),
+        );
+    }
+
+    /// Shows `img` at `rect` over every view mode, or hides the status bar.
+    pub fn set_status_bar(&mut self, img: Option<&RgbaImage>, rect: [f32; 4]) {
+        Self::update_overlay(
+            &self.device,
+            &self.queue,
+            &self.texture_bind_group_layout,
+            &self.sampler_linear,
+            &mut self.status_bar,
+            img.map(|img| (img, rect)),
+        );
+    }
+
+    /// Shows `img` at `rect` above the grid; single view never draws it.
+    pub fn set_breadcrumb(&mut self, img: &RgbaImage, rect: [f32; 4]) {
+        Self::update_overlay(
+            &self.device,
+            &self.queue,
+            &self.texture_bind_group_layout,
+            &self.sampler_linear,
+            &mut self.breadcrumb,
+            Some((img, rect)),
+        );
+    }
+
+    fn update_overlay(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        overlay: &mut Overlay,
+        content: Option<(&RgbaImage, [f32; 4])>,
+    ) {
+        let Some((img, rect)) = content else {
+            overlay.texture = None;
+            return;
+        };
+        overlay.rect = rect;
+        if let Some((texture, _)) = &overlay.texture {
+            let size = texture.size();
+            if (size.width, size.height) == img.dimensions() {
+                Self::write_texture_levels(queue, texture, 0, img, &[]);
+                return;
+            }
+        }
+        let texture = Self::create_texture(device, img.dimensions(), 1);
+        Self::write_texture_levels(queue, &texture, 0, img, &[]);
+        let bind_group = Self::create_bind_group(device, layout, &texture, sampler);
+        overlay.texture = Some((texture, bind_group));
+    }
+
+    /// Appends `count` cells without GPU resources; `retain_grid_resources`
+    /// allocates them on demand. The loader is told what each cell shows.
+    pub fn add_grid_items(&mut self, count: usize) {
+        self.grid_items.extend((0..count).map(|_| GridItem {
+            image_size: [1.0, 1.0],
+            requested: false,
+            retained: false,
+            load_failed: false,
+            thumbnail: None,
+        }));
+    }
+
+    /// Frees GPU resources of items outside `keep` and lets items inside hold them.
+    /// Returns the indices in `keep` whose thumbnail still has to be requested.
+    pub fn retain_grid_resources(&mut self, keep: std::ops::Range<usize>) -> Vec<usize> {
+        let keep = keep.start.min(self.grid_items.len())..keep.end.min(self.grid_items.len());
+
+        let mut needed = Vec::new();
+        for i in 0..self.grid_items.len() {
+            let retained = keep.contains(&i);
+            self.grid_items[i].retained = retained;
+            if !retained {
+                if let Some(thumbnail) = self.grid_items[i].thumbnail.take() {
+                    self.release_thumbnail(thumbnail);
+                }
+                continue;
+            }
+            let item = &mut self.grid_items[i];
+            if item.thumbnail.is_none() && !item.requested {
+                item.requested = true;
+                needed.push(i);
+            }
+        }
+        needed
+    }
+
+    /// Drops the item's thumbnail so the next `retain_grid_resources` requests it again.
+    pub fn reload_grid_item(&mut self, index: usize) {
+        let Some(item) = self.grid_items.get_mut(index) else {
+            return;
+        };
+        item.requested = false;
+        item.load_failed = false;
+        if let Some(thumbnail) = item.thumbnail.take() {
+            self.release_thumbnail(thumbnail);
+        }
+    }
+
+    /// Shows the item as broken instead of loading while it has no error card.
+    pub fn mark_grid_item_failed(&mut self, index: usize) {
+        if let Some(item) = self.grid_items.get_mut(index) {
+            item.requested = false;
+            item.load_failed = true;
+        }
+    }
+
+    /// Returns a thumbnail's layer or texture for use by another item.
+    fn release_thumbnail(&mut self, thumbnail: GridThumbnail) {
+        match thumbnail {
+            GridThumbnail::Layer(layer) => {
+                if let Some(layers) = &mut self.grid_layers {
+                    layers.free.push(layer);
+                }
+            }
+            GridThumbnail::Texture(texture) => {
+                if self.texture_pool.len() < TEXTURE_POOL_LIMIT {
+                    self.texture_pool.push(*texture);
+                }
+            }
+        }
+    }
+
+    fn grid_layer_limit(&self) -> u32 {
+        GRID_LAYER_LIMIT.min(self.device.limits().max_texture_array_layers)
+    }
+
+    fn create_grid_layers(&self) -> GridLayers {
+        let count = GRID_LAYER_CHUNK.min(self.grid_layer_limit());
+        let (texture, bind_group) = self.create_layer_texture(count);
+        let (view_buffer, view_bind_group) = self.create_params_binding("grid_layers");
+
+        GridLayers {
+            texture,
+            bind_group,
+            // Popped from the back, so low layers are handed out first
+            free: (1..count).rev().collect(),
+            instance_buffer: Self::create_instance_buffer(&self.device, 0),
+            instance_capacity: 0,
+            instances_uploaded: Uploaded::default(),
+            view_buffer,
+            view_bind_group,
+            view_uploaded: Uploaded::default(),
+        }
+    }
+
+    /// A free layer of the array texture, which is first created or doubled in size if
+    /// needed. None once it has GRID_LAYER_LIMIT layers and all are taken.
+    fn take_grid_layer(&mut self) -> Option<u32> {
+        if self.grid_layers.is_none() {
+            self.grid_layers = Some(self.create_grid_layers());
+        }
+        let layers = self.grid_layers.as_mut()?;
+        if let Some(layer) = layers.free.pop() {
+            return Some(layer);
+        }
+
+        let count = layers.texture.depth_or_array_layers();
+        let grown = (count * 2).min(self.grid_layer_limit());
+        if grown <= count {
+            return None;
+        }
+        let (texture, bind_group) = self.create_layer_texture(grown);
+        let old = &self.grid_layers.as_ref()?.texture;
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("grid_layers_grow"),
+            });
+        for mip_level in 0..texture.mip_level_count() {
+            let edge = (GRID_LAYER_SIZE >> mip_level).max(1);
+            encoder.copy_texture_to_texture(
+                wgpu::ImageCopyTexture {
+                    texture: old,
+                    mip_level,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                wgpu::ImageCopyTexture {
+                    texture: &texture,
+                    mip_level,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                wgpu::Extent3d {
+                    width: edge,
+                    height: edge,
+                    depth_or_array_layers: count,
+                },
+            );
+        }
+        self.queue.submit(Some(encoder.finish()));
+
+        let layers = self.grid_layers.as_mut()?;
+        layers.texture = texture;
+        layers.bind_group = bind_group;
+        layers.free.extend((count + 1..grown).rev());
+        Some(count)
+    }
+
+    fn create_layer_texture(&self, count: u32) -> (wgpu::Texture, wgpu::BindGroup) {
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width: GRID_LAYER_SIZE,
+                height: GRID_LAYER_SIZE,
+                depth_or_array_layers: count,
+            },
+            mip_level_count: GRID_LAYER_SIZE.ilog2() + 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            // COPY_SRC so the layers can be carried over when the array grows
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_DST
+                | wgpu::TextureUsages::COPY_SRC,
+            label: Some("grid_layers"),
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.layers_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler_grid),
+                },
+            ],
+            label: Some("grid_layers_bind_group"),
+        });
+        (texture, bind_group)
+    }
+
+    fn create_instance_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("grid_instance_buffer"),
+            size: (capacity.max(1) * std::mem::size_of::<GridInstance>()) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Each draw in a frame needs its own uniform buffer, since writes land before the submit.
+    fn create_params_binding(&self, label: &str) -> (wgpu::Buffer, wgpu::BindGroup) {
+        let params_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&format!("{}_params_buffer", label)),
+            size: std::mem::size_of::<Params>() as u64,
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
@@ -354,61 +1302,385 @@ impl Renderer {
                 binding: 0,
                 resource: params_buffer.as_entire_binding(),
             }],
-            label: Some("grid_item_params_bind_group"),
+            label: Some(&format!("{}_params_bind_group", label)),
         });
+        (params_buffer, params_bind_group)
+    }
+
+    /// Shows `img` right of a divider next to the current image, or leaves compare mode.
+    pub fn set_compare_image(&mut self, img: Option<&RgbaImage>) {
+        let Some(img) = img else {
+            self.compare = None;
+            return;
+        };
+
+        let texture =
+            Self::create_image_texture(&self.device, &self.queue, img, self.max_texture_size);
+        let texture_bind_group = Self::create_bind_group(
+            &self.device,
+            &self.texture_bind_group_layout,
+            &texture,
+            self.image_sampler(),
+        );
+        let image_size = [img.width() as f32, img.height() as f32];
+
+        if let Some(compare) = &mut self.compare {
+            compare.texture = texture;
+            compare.texture_bind_group = texture_bind_group;
+            compare.image_size = image_size;
+            return;
+        }
 
-        self.grid_items.push(GridItem {
-            path,
-            is_directory,
+        let (params_buffer, params_bind_group) = self.create_params_binding("compare");
+        let (divider_params_buffer, divider_params_bind_group) =
+            self.create_params_binding("compare_divider");
+        let divider_bind_group = Self::create_texture_bind_group(
+            &self.device,
+            &self.queue,
+            &self.texture_bind_group_layout,
+            &RgbaImage::from_pixel(1, 1, image::Rgba([255, 255, 255, 255])),
+            &self.sampler_linear,
+        );
+        self.compare = Some(CompareView {
+            texture,
             texture_bind_group,
+            image_size,
             params_buffer,
             params_bind_group,
-            image_size: [actual_img.width() as f32, actual_img.height() as f32],
+            params_uploaded: Uploaded::default(),
+            divider_bind_group,
+            divider_params_buffer,
+            divider_params_bind_group,
+            divider_uploaded: Uploaded::default(),
         });
     }
 
+    pub fn is_comparing(&self) -> bool {
+        self.compare.is_some()
+    }
+
+    pub fn get_compare_split(&self) -> f32 {
+        self.compare_split
+    }
+
+    pub fn set_compare_split(&mut self, split: f32) {
+        self.compare_split = split.clamp(0.02, 0.98);
+    }
+
+    pub fn is_compare_synced(&self) -> bool {
+        self.compare_synced
+    }
+
+    /// When synced, the second image follows the first one's zoom and pan, scaled so
+    /// both cover the same width. Otherwise it stays fitted to the window.
+    pub fn set_compare_synced(&mut self, synced: bool) {
+        self.compare_synced = synced;
+    }
+
+    fn compare_params(&self, image_size: [f32; 2]) -> Params {
+        let [win_w, win_h] = self.params.window_size;
+        let (zoom, pan) = if self.compare_synced {
+            let scale = self.params.image_size[0] / image_size[0].max(1.0);
+            (self.shown_zoom * scale, self.shown_pan)
+        } else {
+            let fit = (win_w / image_size[0]).min(win_h / image_size[1]).min(1.0);
+            (fit, [0.0, 0.0])
+        };
+        Params {
+            image_size,
+            zoom,
+            pan,
+            background: self.background.shader_value(),
+            ..self.params
+        }
+    }
+
     pub fn clear_grid(&mut self) {
-        self.grid_items.clear();
+        // Thumbnails all share one size, so the next folder can reuse these textures
+        let items = std::mem::take(&mut self.grid_items);
+        for thumbnail in items.into_iter().filter_map(|item| item.thumbnail) {
+            self.release_thumbnail(thumbnail);
+        }
         self.grid_scroll = 0.0;
+        self.shown_scroll = 0.0;
+        self.stop_fling();
     }
 
     pub fn scroll_grid(&mut self, dy: f32) {
-        let grid_size = 250.0;
-        let spacing = 20.0;
-        let window_width = self.params.window_size[0];
-        let window_height = self.params.window_size[1];
+        self.stop_fling();
+        self.grid_scroll += dy;
+        self.clamp_grid_scroll();
+    }
 
-        let cols = (window_width / (grid_size + spacing)).floor().max(1.0) as u32;
-        let rows = (self.grid_items.len() as f32 / cols as f32).ceil();
-        let content_height = rows * (grid_size + spacing) + spacing;
+    /// Scrolls the grid by a trackpad's pixel delta, 1:1 without easing, and
+    /// tracks the gesture's speed for the fling once the fingers lift.
+    pub fn touch_scroll_grid(&mut self, dy: f32) {
+        let now = Instant::now();
+        let since_last = self
+            .last_touch_scroll
+            .map(|last| now.duration_since(last))
+            .filter(|&gap| gap < FLING_GAP);
+        self.touch_velocity = match since_last {
+            Some(gap) if !gap.is_zero() => {
+                // Smoothed, since individual deltas arrive unevenly
+                let velocity = dy / gap.as_secs_f32();
+                self.touch_velocity * 0.2 + velocity * 0.8
+            }
+            Some(_) => self.touch_velocity,
+            None => 0.0,
+        };
+        self.last_touch_scroll = Some(now);
+        self.drag_grid(dy);
+    }
 
-        self.grid_scroll += dy;
-        let max_scroll = (content_height - window_height).max(0.0);
-        self.grid_scroll = self.grid_scroll.clamp(-max_scroll, 0.0);
+    /// Ends a trackpad gesture, carrying on with its momentum if it was a flick.
+    pub fn release_touch_scroll(&mut self) {
+        let recent = self
+            .last_touch_scroll
+            .take()
+            .is_some_and(|last| last.elapsed() < FLING_GAP);
+        if recent && self.touch_velocity.abs() >= MIN_FLING_VELOCITY {
+            self.fling_velocity = self.touch_velocity;
+        }
+        self.touch_velocity = 0.0;
+    }
+
+    fn stop_fling(&mut self) {
+        self.fling_velocity = 0.0;
+    }
+
+    /// Scrolls the grid following the cursor, without easing.
+    pub fn drag_grid(&mut self, dy: f32) {
+        self.scroll_grid(dy);
+        self.shown_scroll = self.grid_scroll;
+    }
+
+    fn clamp_grid_scroll(&mut self) {
+        self.grid_scroll = self.grid_scroll.clamp(-self.max_grid_scroll(), 0.0);
     }
 
+    fn max_grid_scroll(&self) -> f32 {
+        let [window_width, window_height] = self.params.window_size;
+        let content_height = self
+            .grid_layout
+            .content_height(self.grid_items.len(), window_width);
+        (content_height - window_height).max(0.0)
+    }
+
+    /// Top edge and length of the scrollbar track along the right edge.
+    fn scrollbar_track(&self) -> (f32, f32) {
+        let track = (self.params.window_size[1] - 2.0 * SCROLLBAR_MARGIN).max(0.0);
+        (SCROLLBAR_MARGIN, track)
+    }
+
+    /// Drawn scrollbar thumb as `[x, y, width, height]`, or None if the grid fits the window.
+    pub fn scrollbar_thumb(&self) -> Option<[f32; 4]> {
+        let max_scroll = self.max_grid_scroll();
+        if max_scroll <= 0.0 {
+            return None;
+        }
+        let [window_width, window_height] = self.params.window_size;
+        let (top, track) = self.scrollbar_track();
+        let content_height = window_height + max_scroll;
+        let length = (track * window_height / content_height)
+            .max(SCROLLBAR_MIN_THUMB)
+            .min(track);
+        let progress = (-self.shown_scroll / max_scroll).clamp(0.0, 1.0);
+        Some([
+            window_width - SCROLLBAR_WIDTH - SCROLLBAR_MARGIN,
+            top + progress * (track - length),
+            SCROLLBAR_WIDTH,
+            length,
+        ])
+    }
+
+    /// Scrolls the grid, without easing, so the scrollbar thumb starts at `thumb_top`.
+    pub fn scroll_grid_to_thumb(&mut self, thumb_top: f32) {
+        let Some([_, _, _, length]) = self.scrollbar_thumb() else {
+            return;
+        };
+        let (top, track) = self.scrollbar_track();
+        let room = track - length;
+        let progress = if room > 0.0 {
+            ((thumb_top - top) / room).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        self.stop_fling();
+        self.grid_scroll = -progress * self.max_grid_scroll();
+        self.shown_scroll = self.grid_scroll;
+        self.wake_scrollbar();
+    }
+
+    /// Shows the scrollbar again for a while, as scrolling does.
+    pub fn wake_scrollbar(&mut self) {
+        self.scrollbar_woken = Some(Instant::now());
+    }
+
+    /// When the scrollbar starts fading out, if it is showing.
+    pub fn scrollbar_fade_start(&self) -> Option<Instant> {
+        self.scrollbar_woken.map(|woken| woken + SCROLLBAR_IDLE)
+    }
+
+    fn scrollbar_opacity(&self, now: Instant) -> f32 {
+        let Some(fade_start) = self.scrollbar_fade_start() else {
+            return 0.0;
+        };
+        if now < fade_start {
+            return 1.0;
+        }
+        1.0 - (now - fade_start).as_secs_f32() / SCROLLBAR_FADE.as_secs_f32()
+    }
+
+    pub fn get_grid_layout(&self) -> GridLayout {
+        self.grid_layout
+    }
+
+    pub fn set_grid_cell_size(&mut self, cell: f32) {
+        self.grid_layout.cell = cell.clamp(GridLayout::MIN_CELL, GridLayout::MAX_CELL);
+        self.clamp_grid_scroll();
+    }
+
+    /// Jumps straight to `zoom`, skipping any easing.
     pub fn set_zoom(&mut self, zoom: f32) {
         self.params.zoom = zoom;
+        self.clamp_pan();
+        self.snap_motion();
+    }
+
+    /// Puts the image back in the middle of the window.
+    pub fn center_image(&mut self) {
+        self.params.pan = [0.0, 0.0];
+        self.shown_pan = self.params.pan;
     }
 
     pub fn get_zoom(&self) -> f32 {
         self.params.zoom
     }
 
-    pub fn zoom(&mut self, amount: f32) {
+    /// Zooms by `amount` steps. With an `anchor` in screen pixels, the image point
+    /// under it stays fixed; otherwise zoom is around the image center.
+    pub fn zoom(&mut self, amount: f32, anchor: Option<[f32; 2]>) {
         let zoom_factor = 1.1f32.powf(amount);
-        self.params.zoom *= zoom_factor;
-        self.params.zoom = self.params.zoom.clamp(0.01, 100.0);
+        self.zoom_to(self.params.zoom * zoom_factor, anchor);
+    }
+
+    /// Sets an absolute zoom level, keeping the image point under `anchor` fixed.
+    pub fn zoom_to(&mut self, zoom: f32, anchor: Option<[f32; 2]>) {
+        let old_zoom = self.params.zoom;
+        self.params.zoom = zoom.clamp(0.01, 100.0);
+
+        if let Some(anchor) = anchor {
+            let scale = self.params.zoom / old_zoom;
+            self.params.pan = anchored_pan(self.params.pan, self.params.window_size, anchor, scale);
+        }
+        self.clamp_pan();
     }
 
     pub fn pan(&mut self, dx: f32, dy: f32) {
         // dx, dy are in pixels - pass directly to shader which divides by window_size
         self.params.pan[0] += dx;
         self.params.pan[1] += dy;
+        self.clamp_pan();
+        // Dragging follows the cursor directly
+        self.shown_pan = self.params.pan;
+    }
+
+    pub fn set_smooth_motion(&mut self, enabled: bool) {
+        self.smooth_motion = enabled;
+        if !enabled {
+            self.snap_motion();
+        }
+    }
+
+    pub fn is_smooth_motion(&self) -> bool {
+        self.smooth_motion
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.shown_zoom != self.params.zoom
+            || self.shown_pan != self.params.pan
+            || self.shown_scroll != self.grid_scroll
+            || self.fling_velocity != 0.0
+            // Until a frame finds the scrollbar fully faded and hides it
+            || self
+                .scrollbar_fade_start()
+                .is_some_and(|start| Instant::now() >= start)
+    }
+
+    fn snap_motion(&mut self) {
+        self.shown_zoom = self.params.zoom;
+        self.shown_pan = self.params.pan;
+        self.shown_scroll = self.grid_scroll;
+        self.last_motion_step = None;
+    }
+
+    /// Advances a trackpad fling by `dt` seconds, stopping once it is slow or
+    /// reaches either end of the grid.
+    fn step_fling(&mut self, dt: f32) {
+        let before = self.grid_scroll;
+        self.grid_scroll += self.fling_velocity * dt;
+        self.clamp_grid_scroll();
+        self.shown_scroll = self.grid_scroll;
+        self.fling_velocity *= (-dt / FLING_TIME_CONSTANT).exp();
+        let stuck = self.grid_scroll == before && dt > 0.0;
+        if self.fling_velocity.abs() < MIN_FLING_VELOCITY || stuck {
+            self.stop_fling();
+        }
+    }
+
+    /// Eases the drawn zoom, pan and scroll toward their targets. Returns true
+    /// while a redraw is needed; every value lands exactly on its target.
+    pub fn animate(&mut self, now: Instant) -> bool {
+        if !self.is_animating() {
+            self.last_motion_step = None;
+            return false;
+        }
+        let dt = self
+            .last_motion_step
+            .map(|last| now.duration_since(last).as_secs_f32())
+            .unwrap_or(1.0 / 60.0);
+        if self.fling_velocity != 0.0 {
+            self.step_fling(dt);
+        }
+        if !self.smooth_motion {
+            self.snap_motion();
+            // Momentum is kept even without easing, so keep stepping it
+            if self.fling_velocity != 0.0 {
+                self.last_motion_step = Some(now);
+            }
+            return true;
+        }
+        self.last_motion_step = Some(now);
+        let t = 1.0 - (-dt / MOTION_TIME_CONSTANT).exp();
+
+        // Zoom and pan share `t` so the zoom anchor stays put during the animation
+        let [win_w, win_h] = self.params.window_size;
+        let zoom_error = (self.params.zoom - self.shown_zoom).abs() * win_w.max(win_h);
+        let pan_error = (self.params.pan[0] - self.shown_pan[0])
+            .abs()
+            .max((self.params.pan[1] - self.shown_pan[1]).abs());
+        if zoom_error.max(pan_error) < MOTION_SETTLE_PIXELS {
+            self.shown_zoom = self.params.zoom;
+            self.shown_pan = self.params.pan;
+        } else {
+            self.shown_zoom += (self.params.zoom - self.shown_zoom) * t;
+            self.shown_pan[0] += (self.params.pan[0] - self.shown_pan[0]) * t;
+            self.shown_pan[1] += (self.params.pan[1] - self.shown_pan[1]) * t;
+        }
+
+        if (self.grid_scroll - self.shown_scroll).abs() < MOTION_SETTLE_PIXELS {
+            self.shown_scroll = self.grid_scroll;
+        } else {
+            self.shown_scroll += (self.grid_scroll - self.shown_scroll) * t;
+        }
+        true
     }
 
-    pub fn set_pan(&mut self, x: f32, y: f32) {
-        self.params.pan = [x, y];
+    fn clamp_pan(&mut self) {
+        let [limit_x, limit_y] = pan_limit(&self.params);
+        self.params.pan[0] = self.params.pan[0].clamp(-limit_x, limit_x);
+        self.params.pan[1] = self.params.pan[1].clamp(-limit_y, limit_y);
     }
 
     pub fn set_view_mode(&mut self, is_grid: bool) {
@@ -438,30 +1710,105 @@ impl Renderer {
         let rendered_width = img_size[0] * zoom;
         let rendered_height = img_size[1] * zoom;
 
-        // Calculate image position (centered with pan applied)
-        // Pan is in clip space (-1 to 1), convert to pixels
-        let pan_x_pixels = pan[0] / 2.0 * win_size[0];
-        let pan_y_pixels = pan[1] / 2.0 * win_size[1];
-
-        let left = (win_size[0] - rendered_width) / 2.0 + pan_x_pixels;
-        let top = (win_size[1] - rendered_height) / 2.0 + pan_y_pixels;
+        // Calculate image position (centered with pan applied, pan is in pixels)
+        let left = (win_size[0] - rendered_width) / 2.0 + pan[0];
+        let top = (win_size[1] - rendered_height) / 2.0 + pan[1];
         let right = left + rendered_width;
         let bottom = top + rendered_height;
 
         Some([left, top, right, bottom])
     }
 
+    /// Puts the thumbnail in a layer of the shared array texture when it fits and one is
+    /// free. Otherwise writes into the item's own texture, or a pooled one of the same size;
+    /// a new texture is only created for unseen dimensions.
     pub fn update_grid_item_texture(&mut self, index: usize, img: &RgbaImage) {
-        if let Some(item) = self.grid_items.get_mut(index) {
-            item.texture_bind_group = Self::create_texture_bind_group(
+        let Some(item) = self.grid_items.get_mut(index) else {
+            return;
+        };
+        item.requested = false;
+        // Scrolled out of range while loading; it is requested again when it comes back
+        if !item.retained {
+            return;
+        }
+        let previous = item.thumbnail.take();
+        let dimensions = img.dimensions();
+
+        // Layers have a fixed size and mip count, so only full-size thumbnails go there
+        let fits_layer = dimensions.0.max(dimensions.1) == GRID_LAYER_SIZE;
+        let layer = match &previous {
+            Some(GridThumbnail::Layer(layer)) if fits_layer => Some(*layer),
+            _ if fits_layer => self.take_grid_layer(),
+            _ => None,
+        };
+
+        let thumbnail = match (layer, previous) {
+            (Some(layer), previous) => {
+                match previous {
+                    Some(GridThumbnail::Layer(old)) if old == layer => {}
+                    Some(old) => self.release_thumbnail(old),
+                    None => {}
+                }
+                if let Some(layers) = &self.grid_layers {
+                    let padded = pad_to_layer(img);
+                    let mips = generate_mips(&padded);
+                    Self::write_texture_levels(&self.queue, &layers.texture, layer, &padded, &mips);
+                }
+                GridThumbnail::Layer(layer)
+            }
+            (None, Some(GridThumbnail::Texture(texture))) => GridThumbnail::Texture(Box::new(
+                self.reuse_grid_texture(Some(*texture), img, &generate_mips(img)),
+            )),
+            (None, previous) => {
+                if let Some(old) = previous {
+                    self.release_thumbnail(old);
+                }
+                let mips = generate_mips(img);
+                GridThumbnail::Texture(Box::new(self.reuse_grid_texture(None, img, &mips)))
+            }
+        };
+
+        let item = &mut self.grid_items[index];
+        item.thumbnail = Some(thumbnail);
+        item.image_size = [dimensions.0 as f32, dimensions.1 as f32];
+    }
+
+    /// Uploads `img` into `current` or a pooled texture of its size, creating one if neither fits.
+    fn reuse_grid_texture(
+        &mut self,
+        current: Option<GridTexture>,
+        img: &RgbaImage,
+        mips: &[RgbaImage],
+    ) -> GridTexture {
+        let dimensions = img.dimensions();
+        let reused = match current {
+            Some(texture) if texture.dimensions() == dimensions => Some(texture),
+            _ => self
+                .texture_pool
+                .iter()
+                .position(|t| t.dimensions() == dimensions)
+                .map(|pos| self.texture_pool.swap_remove(pos)),
+        };
+        let grid_texture = reused.unwrap_or_else(|| {
+            let texture = Self::create_texture(&self.device, dimensions, 1 + mips.len() as u32);
+            let bind_group = Self::create_bind_group(
                 &self.device,
-                &self.queue,
                 &self.texture_bind_group_layout,
-                img,
-                &self.sampler_linear,
+                &texture,
+                &self.sampler_grid,
             );
-            item.image_size = [img.width() as f32, img.height() as f32];
-        }
+            let (params_buffer, params_bind_group) = self.create_params_binding("grid_item");
+            GridTexture {
+                texture,
+                bind_group,
+                params_buffer,
+                params_bind_group,
+                params_uploaded: Uploaded::default(),
+            }
+        });
+
+        Self::write_texture_levels(&self.queue, &grid_texture.texture, 0, img, mips);
+        grid_texture
     }
 
     fn create_texture_bind_group(
@@ -471,39 +1818,100 @@ impl Renderer {
         img: &RgbaImage,
         sampler: &wgpu::Sampler,
     ) -> wgpu::BindGroup {
-        let dimensions = img.dimensions();
-        let texture_size = wgpu::Extent3d {
-            width: dimensions.0,
-            height: dimensions.1,
-            depth_or_array_layers: 1,
-        };
-        let texture = device.create_texture(&wgpu::TextureDescriptor {
-            size: texture_size,
-            mip_level_count: 1,
+        Self::create_texture_bind_group_with_mips(device, queue, layout, img, &[], sampler)
+    }
+
+    fn create_texture_bind_group_with_mips(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
+        img: &RgbaImage,
+        mips: &[RgbaImage],
+        sampler: &wgpu::Sampler,
+    ) -> wgpu::BindGroup {
+        let texture = Self::create_texture(device, img.dimensions(), 1 + mips.len() as u32);
+        Self::write_texture_levels(queue, &texture, 0, img, mips);
+        Self::create_bind_group(device, layout, &texture, sampler)
+    }
+
+    /// Uploads `img` as a texture, downscaled if either side exceeds `max_size`.
+    /// The shaders sample in normalized coordinates, so callers keep using the
+    /// original dimensions for layout and zoom.
+    fn create_image_texture(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        img: &RgbaImage,
+        max_size: u32,
+    ) -> wgpu::Texture {
+        let img = fit_texture_limit(img, max_size);
+        let texture = Self::create_texture(device, img.dimensions(), 1);
+        Self::write_texture_levels(queue, &texture, 0, &img, &[]);
+        texture
+    }
+
+    fn create_texture(
+        device: &wgpu::Device,
+        (width, height): (u32, u32),
+        mip_level_count: u32,
+    ) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
             format: wgpu::TextureFormat::Rgba8UnormSrgb,
             usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
             label: Some("diffuse_texture"),
             view_formats: &[],
-        });
+        })
+    }
 
-        queue.write_texture(
-            wgpu::ImageCopyTexture {
-                texture: &texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            img,
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(4 * dimensions.0),
-                rows_per_image: Some(dimensions.1),
-            },
-            texture_size,
-        );
+    /// Uploads `img` to mip level 0 of `layer` and `mips` to the levels after it.
+    fn write_texture_levels(
+        queue: &wgpu::Queue,
+        texture: &wgpu::Texture,
+        layer: u32,
+        img: &RgbaImage,
+        mips: &[RgbaImage],
+    ) {
+        for (mip_level, level) in std::iter::once(img).chain(mips).enumerate() {
+            let (width, height) = level.dimensions();
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture,
+                    mip_level: mip_level as u32,
+                    origin: wgpu::Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: layer,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                level,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * width),
+                    rows_per_image: Some(height),
+                },
+                wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+    }
 
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        texture: &wgpu::Texture,
+        sampler: &wgpu::Sampler,
+    ) -> wgpu::BindGroup {
         let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
         device.create_bind_group(&wgpu::BindGroupDescriptor {
             layout,
@@ -521,7 +1929,14 @@ impl Renderer {
         })
     }
 
-    pub fn render(&mut self, is_grid: bool, selected_index: Option<usize>) {
+    /// Draws a frame. In the grid, `selected_index` is the keyboard cursor and
+    /// `multi_selected` the items picked for batch operations.
+    pub fn render(
+        &mut self,
+        is_grid: bool,
+        selected_index: Option<usize>,
+        multi_selected: &HashSet<usize>,
+    ) {
         let frame = match self.surface.get_current_texture() {
             Ok(frame) => frame,
             Err(_) => return,
@@ -532,12 +1947,70 @@ impl Renderer {
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        // Buffer uploads this frame; zero once the view has settled
+        let mut uploads = 0;
 
         if !is_grid {
+            self.scrollbar_woken = None;
             self.params.is_grid_item = 0.0;
             self.params.is_selected = 0.0;
-            self.queue
-                .write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(&self.params));
+            let shown = Params {
+                zoom: self.shown_zoom,
+                pan: self.shown_pan,
+                background: self.background.shader_value(),
+                ..self.params
+            };
+            let clear_color = if self.background == Background::White {
+                wgpu::Color::WHITE
+            } else {
+                wgpu::Color::BLACK
+            };
+            uploads += self.params_uploaded.write(
+                &self.queue,
+                &self.params_buffer,
+                bytemuck::bytes_of(&shown),
+            ) as u32;
+            self.update_detail_tile(shown.zoom, shown.pan);
+            if let Some(region) = self.detail_tile.as_ref().map(|tile| tile.region) {
+                let tile_params = self.detail_params(region, &shown);
+                if let Some(tile) = &mut self.detail_tile {
+                    uploads += tile.params_uploaded.write(
+                        &self.queue,
+                        &tile.params_buffer,
+                        bytemuck::bytes_of(&tile_params),
+                    ) as u32;
+                }
+            }
+
+            let split_x = match &self.compare {
+                Some(_) => (self.compare_split * self.config.width as f32) as u32,
+                None => self.config.width,
+            };
+            if let Some(compare) = &self.compare {
+                let right = self.compare_params(compare.image_size);
+                let divider = Params {
+                    image_size: [1.0, 1.0],
+                    window_size: self.params.window_size,
+                    pan: [split_x as f32 - 1.0, 0.0],
+                    zoom: 2.0,
+                    is_grid_item: 1.0,
+                    is_selected: 0.0,
+                    background: 0.0,
+                    _pad2: [self.params.window_size[1], 0.0],
+                };
+                if let Some(compare) = &mut self.compare {
+                    uploads += compare.params_uploaded.write(
+                        &self.queue,
+                        &compare.params_buffer,
+                        bytemuck::bytes_of(&right),
+                    ) as u32;
+                    uploads += compare.divider_uploaded.write(
+                        &self.queue,
+                        &compare.divider_params_buffer,
+                        bytemuck::bytes_of(&divider),
+                    ) as u32;
+                }
+            }
             {
                 let mut rp = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                     label: None,
@@ -545,7 +2018,7 @@ impl Renderer {
                         view: &view,
                         resolve_target: None,
                         ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                            load: wgpu::LoadOp::Clear(clear_color),
                             store: wgpu::StoreOp::Store,
                         },
                     })],
@@ -554,59 +2027,136 @@ impl Renderer {
                     occlusion_query_set: None,
                 });
                 rp.set_pipeline(&self.render_pipeline);
+
+                rp.set_scissor_rect(0, 0, split_x, self.config.height);
                 rp.set_bind_group(0, &self.diffuse_bind_group, &[]);
                 rp.set_bind_group(1, &self.params_bind_group, &[]);
                 rp.draw(0..3, 0..1);
-            }
+                if let Some(tile) = &self.detail_tile {
+                    rp.set_bind_group(0, &tile.texture_bind_group, &[]);
+                    rp.set_bind_group(1, &tile.params_bind_group, &[]);
+                    rp.draw(0..3, 0..1);
+                }
 
-            // Render overlay if present
-            if let Some(ref overlay_bg) = self.overlay_bind_group {
-                if let Some(rect) = self.overlay_rect {
-                    let overlay_x = rect[0];
-                    let overlay_y = rect[1];
-                    let overlay_width = rect[2];
-                    let overlay_height = rect[3];
-
-                    let overlay_params = Params {
-                        image_size: [1.0, 1.0], // 1:1 map, no aspect correction in shader
-                        window_size: self.params.window_size,
-                        pan: [overlay_x, overlay_y],
-                        zoom: overlay_width,
-                        is_grid_item: 1.0,
-                        is_selected: 0.0,
-                        _pad: 0.0,
-                        _pad2: [overlay_height, 0.0], // Pass height for non-square quad
-                    };
-
-                    self.queue.write_buffer(
-                        &self.overlay_params_buffer,
+                if let Some(compare) = &self.compare {
+                    rp.set_scissor_rect(
+                        split_x,
                         0,
-                        bytemuck::bytes_of(&overlay_params),
+                        self.config.width - split_x,
+                        self.config.height,
                     );
+                    rp.set_bind_group(0, &compare.texture_bind_group, &[]);
+                    rp.set_bind_group(1, &compare.params_bind_group, &[]);
+                    rp.draw(0..3, 0..1);
+
+                    rp.set_scissor_rect(0, 0, self.config.width, self.config.height);
+                    rp.set_bind_group(0, &compare.divider_bind_group, &[]);
+                    rp.set_bind_group(1, &compare.divider_params_bind_group, &[]);
+                    rp.draw(0..3, 0..1);
+                }
+            }
 
-                    {
-                        let mut rp = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                            label: None,
-                            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                                view: &view,
-                                resolve_target: None,
-                                ops: wgpu::Operations {
-                                    load: wgpu::LoadOp::Load,
-                                    store: wgpu::StoreOp::Store,
-                                },
-                            })],
-                            depth_stencil_attachment: None,
-                            timestamp_writes: None,
-                            occlusion_query_set: None,
-                        });
-                        rp.set_pipeline(&self.render_pipeline);
-                        rp.set_bind_group(0, overlay_bg, &[]);
-                        rp.set_bind_group(1, &self.overlay_params_bind_group, &[]);
-                        rp.draw(0..3, 0..1);
+            uploads += self.overlay.draw(
+                &self.queue,
+                &self.render_pipeline,
+                self.params.window_size,
+                &mut encoder,
+                &view,
+            );
+        } else {
+            let layout = self.grid_layout;
+            let cols = layout.columns(self.params.window_size[0]);
+
+            let visible = layout.visible_range(self.shown_scroll, self.params.window_size);
+            let visible =
+                visible.start.min(self.grid_items.len())..visible.end.min(self.grid_items.len());
+
+            // Layered thumbnails and placeholders are batched; the rest draw one by one
+            let mut instances = Vec::new();
+            let mut separate = Vec::new();
+            for i in visible {
+                let item = &mut self.grid_items[i];
+                let [x, y] = layout.item_origin(i, cols);
+                let y = y + self.shown_scroll;
+
+                if y + layout.cell < 0.0 || y > self.params.window_size[1] {
+                    continue;
+                }
+
+                let mut selected = if Some(i) == selected_index { 1.0 } else { 0.0 };
+                if multi_selected.contains(&i) {
+                    selected += 2.0;
+                }
+                let (layer, image_size) = match &mut item.thumbnail {
+                    Some(GridThumbnail::Texture(texture)) => {
+                        let p = Params {
+                            image_size: item.image_size,
+                            window_size: self.params.window_size,
+                            pan: [x, y],
+                            zoom: layout.cell,
+                            is_grid_item: 1.0,
+                            is_selected: selected,
+                            background: 0.0,
+                            _pad2: [0.0; 2],
+                        };
+                        uploads += texture.params_uploaded.write(
+                            &self.queue,
+                            &texture.params_buffer,
+                            bytemuck::bytes_of(&p),
+                        ) as u32;
+                        separate.push(i);
+                        continue;
                     }
+                    Some(GridThumbnail::Layer(layer)) => (*layer, item.image_size),
+                    None => (0, [1.0, 1.0]),
+                };
+                let status = match &item.thumbnail {
+                    Some(_) => GridInstance::LOADED,
+                    None if item.load_failed => GridInstance::FAILED,
+                    None => GridInstance::LOADING,
+                };
+                instances.push(GridInstance {
+                    origin: [x, y],
+                    image_size,
+                    uv_scale: [
+                        image_size[0] / GRID_LAYER_SIZE as f32,
+                        image_size[1] / GRID_LAYER_SIZE as f32,
+                    ],
+                    cell: layout.cell,
+                    selected,
+                    layer,
+                    status,
+                });
+            }
+
+            if !instances.is_empty() && self.grid_layers.is_none() {
+                self.grid_layers = Some(self.create_grid_layers());
+            }
+            if let Some(layers) = &mut self.grid_layers {
+                if instances.len() > layers.instance_capacity {
+                    layers.instance_capacity = instances.len().next_power_of_two();
+                    layers.instance_buffer =
+                        Self::create_instance_buffer(&self.device, layers.instance_capacity);
+                    layers.instances_uploaded = Uploaded::default();
                 }
+                uploads += layers.instances_uploaded.write(
+                    &self.queue,
+                    &layers.instance_buffer,
+                    bytemuck::cast_slice(&instances),
+                ) as u32;
+                let grid_view = [
+                    self.params.window_size[0],
+                    self.params.window_size[1],
+                    0.0,
+                    0.0,
+                ];
+                uploads += layers.view_uploaded.write(
+                    &self.queue,
+                    &layers.view_buffer,
+                    bytemuck::cast_slice(&grid_view),
+                ) as u32;
             }
-        } else {
+
             let mut rp = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: None,
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
@@ -626,45 +2176,81 @@ impl Renderer {
                 timestamp_writes: None,
                 occlusion_query_set: None,
             });
-            rp.set_pipeline(&self.render_pipeline);
-
-            let grid_size = 250.0;
-            let spacing = 20.0;
-            let cols = (self.params.window_size[0] / (grid_size + spacing))
-                .floor()
-                .max(1.0) as u32;
-
-            for (i, item) in self.grid_items.iter().enumerate() {
-                let col = (i as u32) % cols;
-                let row = (i as u32) / cols;
-
-                let x = spacing + (col as f32) * (grid_size + spacing);
-                let y = spacing + (row as f32) * (grid_size + spacing) + self.grid_scroll;
 
-                if y + grid_size < 0.0 || y > self.params.window_size[1] {
-                    continue;
+            if let Some(layers) = &self.grid_layers {
+                if !instances.is_empty() {
+                    rp.set_pipeline(&self.grid_pipeline);
+                    rp.set_bind_group(0, &layers.bind_group, &[]);
+                    rp.set_bind_group(1, &layers.view_bind_group, &[]);
+                    rp.set_vertex_buffer(0, layers.instance_buffer.slice(..));
+                    rp.draw(0..6, 0..instances.len() as u32);
                 }
+            }
 
-                let p = Params {
-                    image_size: item.image_size,
-                    window_size: self.params.window_size,
-                    pan: [x, y],
-                    zoom: grid_size,
-                    is_grid_item: 1.0,
-                    is_selected: if Some(i) == selected_index { 1.0 } else { 0.0 },
-                    _pad: 0.0,
-                    _pad2: [0.0; 2],
+            rp.set_pipeline(&self.render_pipeline);
+            for i in separate {
+                let Some(GridThumbnail::Texture(texture)) = &self.grid_items[i].thumbnail else {
+                    continue;
                 };
+                rp.set_bind_group(0, &texture.bind_group, &[]);
+                rp.set_bind_group(1, &texture.params_bind_group, &[]);
+                rp.draw(0..3, 0..1);
+            }
+            drop(rp);
 
-                self.queue
-                    .write_buffer(&item.params_buffer, 0, bytemuck::bytes_of(&p));
+            uploads += self.breadcrumb.draw(
+                &self.queue,
+                &self.render_pipeline,
+                self.params.window_size,
+                &mut encoder,
+                &view,
+            );
 
-                rp.set_bind_group(0, &item.texture_bind_group, &[]);
-                rp.set_bind_group(1, &item.params_bind_group, &[]);
-                rp.draw(0..3, 0..1);
+            if self.shown_scroll != self.scrollbar_drawn_at {
+                self.scrollbar_drawn_at = self.shown_scroll;
+                self.wake_scrollbar();
+            }
+            let opacity = self.scrollbar_opacity(Instant::now());
+            if let Some(thumb) = self.scrollbar_thumb().filter(|_| opacity > 0.0) {
+                let alpha = (opacity * 160.0).round() as u8;
+                if alpha != self.scrollbar_alpha || self.scrollbar.texture.is_none() {
+                    self.scrollbar_alpha = alpha;
+                    Self::update_overlay(
+                        &self.device,
+                        &self.queue,
+                        &self.texture_bind_group_layout,
+                        &self.sampler_linear,
+                        &mut self.scrollbar,
+                        Some((
+                            &RgbaImage::from_pixel(1, 1, image::Rgba([220, 220, 220, alpha])),
+                            thumb,
+                        )),
+                    );
+                }
+                self.scrollbar.rect = thumb;
+                uploads += self.scrollbar.draw(
+                    &self.queue,
+                    &self.render_pipeline,
+                    self.params.window_size,
+                    &mut encoder,
+                    &view,
+                );
+            } else {
+                self.scrollbar_woken = None;
             }
         }
 
+        uploads += self.status_bar.draw(
+            &self.queue,
+            &self.render_pipeline,
+            self.params.window_size,
+            &mut encoder,
+            &view,
+        );
+
+        if uploads > 0 {
+            log::trace!("Frame uploaded {} buffers", uploads);
+        }
         self.queue.submit(std::iter::once(encoder.finish()));
         frame.present();
     }
@@ -674,30 +2260,409 @@ impl Renderer {
     }
 
     pub fn scroll_to_item(&mut self, index: usize) {
-        let grid_size = 250.0;
-        let spacing = 20.0;
-        let window_height = self.params.window_size[1];
-        let cols = (self.params.window_size[0] / (grid_size + spacing))
-            .floor()
-            .max(1.0) as u32;
+        self.stop_fling();
+        self.grid_scroll =
+            self.grid_layout
+                .scroll_to_show(index, self.grid_scroll, self.params.window_size);
+        self.clamp_grid_scroll();
+    }
+}
+
+/// Pan that keeps the image point under screen position `anchor` in place when
+/// the zoom is multiplied by `scale`.
+fn anchored_pan(pan: [f32; 2], window_size: [f32; 2], anchor: [f32; 2], scale: f32) -> [f32; 2] {
+    // The image center sits at window_size / 2 + pan in screen pixels
+    [0, 1].map(|axis| {
+        let center = window_size[axis] / 2.0;
+        let offset = anchor[axis] - (center + pan[axis]);
+        anchor[axis] - center - offset * scale
+    })
+}
 
-        let row = index as u32 / cols;
-        let item_top = row as f32 * (grid_size + spacing) + spacing;
-        let item_bottom = item_top + grid_size;
+/// Largest pan offset per axis that keeps the image covering the window.
+/// Axes where the image is smaller than the window get 0, keeping it centered.
+fn pan_limit(params: &Params) -> [f32; 2] {
+    let [img_w, img_h] = params.image_size;
+    let [win_w, win_h] = params.window_size;
+    let zoom = params.zoom;
+    [
+        ((img_w * zoom - win_w) / 2.0).max(0.0),
+        ((img_h * zoom - win_h) / 2.0).max(0.0),
+    ]
+}
 
-        // If above current view
-        if item_top < -self.grid_scroll {
-            self.grid_scroll = -item_top + spacing;
+/// Part of the image on screen, in image pixels (x, y, width, height), for an
+/// image drawn centered at `zoom` and shifted by `pan`; None if it is all off screen.
+fn visible_region(
+    image_size: [f32; 2],
+    window_size: [f32; 2],
+    pan: [f32; 2],
+    zoom: f32,
+) -> Option<[u32; 4]> {
+    let mut region = [0; 4];
+    for axis in 0..2 {
+        let left = window_size[axis] / 2.0 + pan[axis] - image_size[axis] * zoom / 2.0;
+        let start = (-left / zoom).floor().clamp(0.0, image_size[axis]);
+        let end = ((window_size[axis] - left) / zoom)
+            .ceil()
+            .clamp(0.0, image_size[axis]);
+        if end <= start {
+            return None;
         }
-        // If below current view
-        else if item_bottom > -self.grid_scroll + window_height {
-            self.grid_scroll = -item_bottom + window_height - spacing;
+        region[axis] = start as u32;
+        region[axis + 2] = (end - start) as u32;
+    }
+    Some(region)
+}
+
+fn region_contains(outer: [u32; 4], inner: [u32; 4]) -> bool {
+    outer[0] <= inner[0]
+        && outer[1] <= inner[1]
+        && outer[0] + outer[2] >= inner[0] + inner[2]
+        && outer[1] + outer[3] >= inner[1] + inner[3]
+}
+
+/// The region to upload for `visible`: grown by half its size on every side so
+/// small pans don't need a new tile, then kept within the image and `max_size`.
+fn detail_region(visible: [u32; 4], (width, height): (u32, u32), max_size: u32) -> [u32; 4] {
+    let mut region = [0; 4];
+    for (axis, limit) in [width, height].into_iter().enumerate() {
+        let length = (visible[axis + 2] * 2).min(max_size).min(limit);
+        let center = visible[axis] + visible[axis + 2] / 2;
+        region[axis] = center.saturating_sub(length / 2).min(limit - length);
+        region[axis + 2] = length;
+    }
+    region
+}
+
+/// Size a `width` by `height` image is scaled down to so its longest side is
+/// `max_size`, keeping the aspect ratio; None if it already fits.
+fn fitted_texture_size(width: u32, height: u32, max_size: u32) -> Option<(u32, u32)> {
+    let longest = width.max(height);
+    if longest <= max_size {
+        return None;
+    }
+    let scale = max_size as f64 / longest as f64;
+    let fit = |side: u32| ((side as f64 * scale).round() as u32).clamp(1, max_size);
+    Some((fit(width), fit(height)))
+}
+
+/// `img` itself if both sides fit within `max_size`, otherwise a copy scaled
+/// down to fit with its aspect ratio kept.
+fn fit_texture_limit(img: &RgbaImage, max_size: u32) -> Cow<'_, RgbaImage> {
+    let (width, height) = img.dimensions();
+    let Some((fitted_width, fitted_height)) = fitted_texture_size(width, height, max_size) else {
+        return Cow::Borrowed(img);
+    };
+    log::info!(
+        "Downscaling {}x{} image to {}x{} to fit the GPU texture limit",
+        width,
+        height,
+        fitted_width,
+        fitted_height
+    );
+    Cow::Owned(image::imageops::resize(
+        img,
+        fitted_width,
+        fitted_height,
+        image::imageops::FilterType::Triangle,
+    ))
+}
+
+/// `img` extended to a whole grid layer by repeating its last row and column.
+/// Filtering at its edges, and every smaller mip, would otherwise blend in
+/// whatever thumbnail used the layer before.
+fn pad_to_layer(img: &RgbaImage) -> Cow<'_, RgbaImage> {
+    let (width, height) = img.dimensions();
+    if (width, height) == (GRID_LAYER_SIZE, GRID_LAYER_SIZE) {
+        return Cow::Borrowed(img);
+    }
+    Cow::Owned(RgbaImage::from_fn(
+        GRID_LAYER_SIZE,
+        GRID_LAYER_SIZE,
+        |x, y| *img.get_pixel(x.min(width - 1), y.min(height - 1)),
+    ))
+}
+
+/// Successively halved copies of `img` down to 1x1, excluding `img` itself.
+fn generate_mips(img: &RgbaImage) -> Vec<RgbaImage> {
+    let mut mips: Vec<RgbaImage> = Vec::new();
+    let (mut width, mut height) = img.dimensions();
+    while width > 1 || height > 1 {
+        width = (width / 2).max(1);
+        height = (height / 2).max(1);
+        let source = mips.last().unwrap_or(img);
+        mips.push(image::imageops::resize(
+            source,
+            width,
+            height,
+            image::imageops::FilterType::Triangle,
+        ));
+    }
+    mips
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oversized_image_is_sized_to_the_texture_limit() {
+        assert_eq!(
+            fitted_texture_size(10_000, 10_000, 8192),
+            Some((8192, 8192))
+        );
+        assert_eq!(fitted_texture_size(8192, 100, 8192), None);
+    }
+
+    #[test]
+    fn downscaling_keeps_the_aspect_ratio() {
+        assert_eq!(fitted_texture_size(10_000, 2_500, 4096), Some((4096, 1024)));
+        assert_eq!(fitted_texture_size(30, 20_000, 4096), Some((6, 4096)));
+        // Never thinner than a pixel
+        assert_eq!(fitted_texture_size(1, 20_000, 4096), Some((1, 4096)));
+    }
+
+    #[test]
+    fn oversized_image_is_downscaled_and_keeps_its_pixels() {
+        let img = RgbaImage::from_pixel(100, 100, image::Rgba([200, 100, 50, 255]));
+        let fitted = fit_texture_limit(&img, 64);
+        assert!(matches!(fitted, Cow::Owned(_)));
+        assert_eq!(fitted.dimensions(), (64, 64));
+        assert_eq!(fitted.get_pixel(32, 32), &image::Rgba([200, 100, 50, 255]));
+    }
+
+    #[test]
+    fn image_within_the_limit_is_borrowed() {
+        let img = RgbaImage::new(64, 10);
+        assert!(matches!(fit_texture_limit(&img, 64), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn horizontal_steps_stop_at_the_row_ends() {
+        // 10 items in 4 columns: rows 0-3, 4-7 and a short last row 8-9
+        assert_eq!(GridLayout::step(5, 1, 0, 4, 10), 6);
+        assert_eq!(GridLayout::step(7, 1, 0, 4, 10), 7);
+        assert_eq!(GridLayout::step(4, -1, 0, 4, 10), 4);
+        assert_eq!(GridLayout::step(9, 1, 0, 4, 10), 9);
+    }
+
+    #[test]
+    fn vertical_steps_keep_the_column() {
+        assert_eq!(GridLayout::step(1, 0, 1, 4, 10), 5);
+        assert_eq!(GridLayout::step(5, 0, -1, 4, 10), 1);
+        // The first and last rows don't wrap
+        assert_eq!(GridLayout::step(2, 0, -1, 4, 10), 2);
+        assert_eq!(GridLayout::step(9, 0, 1, 4, 10), 9);
+        // Below a column the short last row lacks, the last item is the landing spot
+        assert_eq!(GridLayout::step(7, 0, 1, 4, 10), 9);
+        // Page moves clamp to the last row
+        assert_eq!(GridLayout::step(0, 0, 5, 4, 10), 8);
+    }
+
+    #[test]
+    fn steps_tolerate_stale_indices_and_empty_grids() {
+        assert_eq!(GridLayout::step(0, 1, 0, 4, 0), 0);
+        assert_eq!(GridLayout::step(25, 0, 0, 4, 10), 9);
+        assert_eq!(GridLayout::step(3, 0, 1, 0, 10), 4);
+    }
+
+    /// 100 pixel cells 10 apart, below a 30 pixel header
+    fn layout() -> GridLayout {
+        GridLayout {
+            cell: 100.0,
+            spacing: 10.0,
+            top: 30.0,
+        }
+    }
+
+    #[test]
+    fn hit_test_finds_the_cell_under_the_cursor() {
+        let layout = layout();
+        // 450 pixels fit 4 columns of 110
+        assert_eq!(layout.item_at(15.0, 45.0, 0.0, 450.0), Some(0));
+        assert_eq!(layout.item_at(345.0, 45.0, 0.0, 450.0), Some(3));
+        assert_eq!(layout.item_at(125.0, 155.0, 0.0, 450.0), Some(5));
+        // Scrolled down by a row, the same spot is one row further
+        assert_eq!(layout.item_at(125.0, 155.0, -110.0, 450.0), Some(9));
+    }
+
+    #[test]
+    fn hit_test_misses_outside_the_columns_and_above_the_grid() {
+        let layout = layout();
+        assert_eq!(layout.item_at(5.0, 45.0, 0.0, 450.0), None);
+        // 500 pixels still fit only 4 columns, leaving a strip on the right
+        assert_eq!(layout.item_at(470.0, 45.0, 0.0, 500.0), None);
+        assert_eq!(layout.item_at(15.0, 20.0, 0.0, 450.0), None);
+    }
+
+    #[test]
+    fn visible_range_covers_partly_shown_rows() {
+        let layout = layout();
+        // Rows start at 40, 150, 260...; a 300 pixel window shows rows 0-2
+        assert_eq!(layout.visible_range(0.0, [450.0, 300.0]), 0..12);
+        // Scrolled 200 pixels, row 0 has left the top of the window
+        assert_eq!(layout.visible_range(-200.0, [450.0, 300.0]), 4..20);
+    }
+
+    #[test]
+    fn fit_modes_pick_the_matching_side() {
+        // A 4000x1000 panorama in a 1000x800 window
+        let (image, window) = ([4000.0, 1000.0], [1000.0, 800.0]);
+        assert_eq!(FitMode::FitWhole.zoom_for(image, window, false), 0.25);
+        assert_eq!(FitMode::FitWidth.zoom_for(image, window, false), 0.25);
+        assert_eq!(FitMode::FitHeight.zoom_for(image, window, false), 0.8);
+        assert_eq!(FitMode::FillWindow.zoom_for(image, window, false), 0.8);
+    }
+
+    #[test]
+    fn small_images_are_enlarged_only_with_upscale() {
+        let (image, window) = ([200.0, 100.0], [1000.0, 800.0]);
+        assert_eq!(FitMode::FitWhole.zoom_for(image, window, false), 1.0);
+        assert_eq!(FitMode::FitWhole.zoom_for(image, window, true), 5.0);
+        assert_eq!(FitMode::FillWindow.zoom_for(image, window, true), 8.0);
+    }
+
+    #[test]
+    fn pan_is_limited_to_the_image_overhang() {
+        let mut params = Params::zeroed();
+        params.image_size = [2000.0, 500.0];
+        params.window_size = [1000.0, 800.0];
+        params.zoom = 1.0;
+        // 500 pixels hang over each side horizontally; vertically it is centered
+        assert_eq!(pan_limit(&params), [500.0, 0.0]);
+        params.zoom = 2.0;
+        assert_eq!(pan_limit(&params), [1500.0, 100.0]);
+        params.zoom = 0.25;
+        assert_eq!(pan_limit(&params), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn visible_region_is_the_part_of_the_image_on_screen() {
+        let (image, window) = ([4000.0, 3000.0], [1000.0, 800.0]);
+        assert_eq!(
+            visible_region(image, window, [0.0, 0.0], 1.0),
+            Some([1500, 1100, 1000, 800])
+        );
+        // Panning right shows more of the left side
+        assert_eq!(
+            visible_region(image, window, [200.0, 0.0], 2.0),
+            Some([1650, 1300, 500, 400])
+        );
+        assert_eq!(
+            visible_region(image, window, [0.0, 0.0], 0.1),
+            Some([0, 0, 4000, 3000])
+        );
+        assert_eq!(visible_region(image, window, [5000.0, 0.0], 1.0), None);
+    }
+
+    #[test]
+    fn detail_region_grows_around_the_view_within_bounds() {
+        assert_eq!(
+            detail_region([1500, 1100, 1000, 800], (4000, 3000), 8192),
+            [1000, 700, 2000, 1600]
+        );
+        // Pushed back inside the image at its edges
+        assert_eq!(
+            detail_region([3500, 0, 500, 400], (4000, 3000), 8192),
+            [3000, 0, 1000, 800]
+        );
+        // Never larger than the texture limit, staying centered on the view
+        assert_eq!(
+            detail_region([0, 0, 3000, 3000], (4000, 3000), 2048),
+            [476, 476, 2048, 2048]
+        );
+    }
+
+    #[test]
+    fn zooming_keeps_the_point_under_the_cursor_still() {
+        let window = [1000.0, 800.0];
+        // Doubling the zoom at the center needs no pan
+        assert_eq!(
+            anchored_pan([0.0, 0.0], window, [500.0, 400.0], 2.0),
+            [0.0, 0.0]
+        );
+        // 100 pixels right of the centered image's middle, doubling moves that point
+        // to 200 pixels right unless the image shifts 100 pixels left
+        assert_eq!(
+            anchored_pan([0.0, 0.0], window, [600.0, 400.0], 2.0),
+            [-100.0, 0.0]
+        );
+
+        // Checked by mapping the anchor to image space before and after
+        let (pan, anchor, zoom) = ([40.0, -30.0], [120.0, 650.0], 0.5);
+        let to_image = |pan: [f32; 2], zoom: f32| {
+            [0, 1].map(|axis| (anchor[axis] - window[axis] / 2.0 - pan[axis]) / zoom)
+        };
+        let panned = anchored_pan(pan, window, anchor, 3.0);
+        let before = to_image(pan, zoom);
+        let after = to_image(panned, zoom * 3.0);
+        for axis in 0..2 {
+            assert!(
+                (before[axis] - after[axis]).abs() < 1e-3,
+                "{before:?} {after:?}"
+            );
         }
+    }
+
+    #[test]
+    fn mip_chain_halves_down_to_one_pixel() {
+        let layer = RgbaImage::new(GRID_LAYER_SIZE, GRID_LAYER_SIZE);
+        let mips = generate_mips(&layer);
+        // Together with the image itself, one level per mip the layer array is made with
+        assert_eq!(1 + mips.len() as u32, GRID_LAYER_SIZE.ilog2() + 1);
+        assert_eq!(mips[0].dimensions(), (128, 128));
+        assert_eq!(mips.last().unwrap().dimensions(), (1, 1));
+
+        // A non-square thumbnail keeps halving its long side once the short one is 1
+        let sizes: Vec<_> = generate_mips(&RgbaImage::new(300, 120))
+            .iter()
+            .map(|mip| mip.dimensions())
+            .collect();
+        assert_eq!(
+            sizes,
+            [
+                (150, 60),
+                (75, 30),
+                (37, 15),
+                (18, 7),
+                (9, 3),
+                (4, 1),
+                (2, 1),
+                (1, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn selection_stays_in_view_when_the_columns_change() {
+        let layout = layout();
+        // Item 9 is on the third row of 4 columns, its bottom at 360
+        let scroll = layout.scroll_to_show(9, 0.0, [450.0, 300.0]);
+        assert_eq!(scroll, -70.0);
+        assert_eq!(layout.scroll_to_show(9, scroll, [450.0, 300.0]), scroll);
+        // Narrowed to 2 columns it moves down to the fifth row, bottom at 580
+        assert_eq!(layout.scroll_to_show(9, scroll, [230.0, 300.0]), -290.0);
+        // Widened to 8 columns it is on the second row, above the view
+        assert_eq!(layout.scroll_to_show(9, -290.0, [900.0, 300.0]), -110.0);
+    }
+
+    #[test]
+    fn wide_thumbnail_is_padded_with_its_bottom_row() {
+        let half = GRID_LAYER_SIZE / 2;
+        let wide = RgbaImage::from_fn(GRID_LAYER_SIZE, half, |x, y| {
+            image::Rgba([x as u8, y as u8, 0, 255])
+        });
+        let padded = pad_to_layer(&wide);
+        assert_eq!(padded.dimensions(), (GRID_LAYER_SIZE, GRID_LAYER_SIZE));
+        assert_eq!(padded.get_pixel(5, half - 1), wide.get_pixel(5, half - 1));
+        // Rows below the image repeat its last one rather than showing stale texels
+        assert_eq!(
+            padded.get_pixel(5, GRID_LAYER_SIZE - 1),
+            wide.get_pixel(5, half - 1)
+        );
 
-        // Clamp scroll
-        let rows = (self.grid_items.len() as f32 / cols as f32).ceil();
-        let content_height = rows * (grid_size + spacing) + spacing;
-        let max_scroll = (content_height - window_height).max(0.0);
-        self.grid_scroll = self.grid_scroll.clamp(-max_scroll, 0.0);
+        let full = RgbaImage::new(GRID_LAYER_SIZE, GRID_LAYER_SIZE);
+        assert!(matches!(pad_to_layer(&full), Cow::Borrowed(_)));
     }
 }
@@ -5,7 +5,7 @@ struct Params {
     zoom: f32,
     is_grid_item: f32,
     is_selected: f32,
-    _pad: f32,
+    background: f32, // Single view: 0 black, 1 white, 2 checkerboard
     _pad2: vec2<f32>, // Pad to 48 bytes (12 floats)
 };
 
@@ -44,12 +44,13 @@ fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
         let clip_y = 1.0 - (pixel_pos.y / params.window_size.y) * 2.0;
         out.clip_position = vec4<f32>(clip_x, clip_y, 0.0, 1.0);
         
+        // Letterboxed: the long side spans the cell, the short one is centered
         let aspect = params.image_size.x / params.image_size.y;
         var uv = base_uv;
         if (aspect > 1.0) {
-            uv.x = (uv.x - 0.5) * (params.image_size.y / params.image_size.x) + 0.5;
-        } else if (aspect < 1.0) {
             uv.y = (uv.y - 0.5) * (params.image_size.x / params.image_size.y) + 0.5;
+        } else if (aspect < 1.0) {
+            uv.x = (uv.x - 0.5) * (params.image_size.y / params.image_size.x) + 0.5;
         }
         out.uv = uv; // Quad is already flipped in clip_y calculation
     } else {
@@ -72,6 +73,20 @@ var t_diffuse: texture_2d<f32>;
 @group(0) @binding(1)
 var s_diffuse: sampler;
 
+// Grid cell `color` under the cursor and multi-selection outlines.
+fn with_grid_outline(quad_uv: vec2<f32>, color: vec4<f32>) -> vec4<f32> {
+    // Bit 0 is the keyboard cursor, bit 1 membership of the multi-selection
+    let flags = u32(params.is_selected + 0.5);
+    let edge = min(min(quad_uv.x, 1.0 - quad_uv.x), min(quad_uv.y, 1.0 - quad_uv.y)) * params.zoom;
+    if ((flags & 1u) != 0u && edge < 2.0) {
+        return vec4<f32>(1.0, 0.8, 0.1, 1.0); // Vibrant orange for selection
+    }
+    if ((flags & 2u) != 0u && edge < 4.0) {
+        return vec4<f32>(0.25, 0.6, 1.0, 1.0); // Blue for multi-selected items
+    }
+    return color;
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     if (params.is_grid_item > 0.5) {
@@ -82,18 +97,34 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
 
     if (in.uv.x < 0.0 || in.uv.x > 1.0 || in.uv.y < 0.0 || in.uv.y > 1.0) {
         if (params.is_grid_item > 0.5) {
-            return vec4<f32>(0.05, 0.05, 0.06, 1.0);
+            // Letterbox bars still carry the outline along the cell edge
+            return with_grid_outline(in.quad_uv, vec4<f32>(0.05, 0.05, 0.06, 1.0));
+        }
+        if (params._pad2.x > 0.5) {
+            discard; // Detail tile: only covers its own part of the image
+        }
+        if (params.background > 0.5 && params.background < 1.5) {
+            return vec4<f32>(1.0, 1.0, 1.0, 1.0);
         }
         return vec4<f32>(0.0, 0.0, 0.0, 1.0);
     }
     
     var color = textureSample(t_diffuse, s_diffuse, in.uv);
+
+    if (params.is_grid_item < 0.5 && params.background > 1.5) {
+        // Two-gray checkerboard in 8px screen squares behind transparent pixels
+        let square = floor(in.clip_position.xy / 8.0);
+        let odd = (square.x + square.y) - 2.0 * floor((square.x + square.y) / 2.0);
+        let checker = select(0.6, 0.35, odd > 0.5);
+        color = vec4<f32>(mix(vec3<f32>(checker), color.rgb, color.a), 1.0);
+    } else if (params.is_grid_item < 0.5 && params._pad2.x > 0.5) {
+        // A detail tile is drawn over the image itself, so it blends with the background here
+        let backdrop = select(0.0, 1.0, params.background > 0.5);
+        color = vec4<f32>(mix(vec3<f32>(backdrop), color.rgb, color.a), 1.0);
+    }
     
-    if (params.is_grid_item > 0.5 && params.is_selected > 0.5) {
-        let border = 2.0 / params.zoom;
-        if (in.quad_uv.x < border || in.quad_uv.x > (1.0 - border) || in.quad_uv.y < border || in.quad_uv.y > (1.0 - border)) {
-            return vec4<f32>(1.0, 0.8, 0.1, 1.0); // Vibrant orange for selection
-        }
+    if (params.is_grid_item > 0.5) {
+        return with_grid_outline(in.quad_uv, color);
     }
     
     return color;
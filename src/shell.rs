@@ -0,0 +1,264 @@
+use std::borrow::Cow;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+use std::thread;
+
+/// Opens the system file manager with `path` selected. Linux file managers
+/// share no way to select a file, so there the containing folder opens instead.
+pub fn reveal_in_file_manager(path: &Path) -> io::Result<()> {
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        use std::os::windows::process::CommandExt;
+        // Explorer parses its own command line and needs the quotes after the comma
+        let mut command = Command::new("explorer");
+        command.raw_arg(format!("/select,\"{}\"", path.display()));
+        command
+    };
+
+    #[cfg(target_os = "macos")]
+    let mut command = {
+        let mut command = Command::new("open");
+        command.arg("-R").arg(path);
+        command
+    };
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let mut command = {
+        let folder = if path.is_dir() {
+            path
+        } else {
+            path.parent().unwrap_or(path)
+        };
+        let mut command = Command::new("xdg-open");
+        command.arg(folder);
+        command
+    };
+
+    spawn_detached(&mut command)
+}
+
+/// Opens `path` in the editor named by `FASTVIEW_EDITOR`, or the system's
+/// default application when it is unset. The variable is a command line in
+/// which `{}` stands for the file, e.g. `gimp {}` or
+/// `"C:\Program Files\GIMP 2\bin\gimp-2.10.exe" "{}"`; without a `{}` the
+/// file is appended. Words are split on spaces outside double quotes and
+/// passed to the program directly, so paths with spaces need no escaping.
+pub fn open_with_editor(path: &Path) -> io::Result<()> {
+    let template = std::env::var("FASTVIEW_EDITOR").unwrap_or_default();
+    let mut words = split_command_line(&template).into_iter();
+    let Some(program) = words.next() else {
+        return open_with_default_app(path);
+    };
+
+    let file = plain_path(path);
+    let mut command = Command::new(program);
+    let mut placed = false;
+    for word in words {
+        placed |= word.contains("{}");
+        command.arg(word.replace("{}", &file));
+    }
+    if !placed {
+        command.arg(file.as_ref());
+    }
+    spawn_detached(&mut command)
+}
+
+fn open_with_default_app(path: &Path) -> io::Result<()> {
+    #[cfg(target_os = "windows")]
+    let mut command = Command::new("explorer");
+    #[cfg(target_os = "macos")]
+    let mut command = Command::new("open");
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let mut command = Command::new("xdg-open");
+
+    command.arg(plain_path(path).as_ref());
+    spawn_detached(&mut command)
+}
+
+/// Splits on whitespace, keeping double-quoted runs together without the quotes.
+fn split_command_line(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_word = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            c => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    words
+}
+
+/// Lets the command-line modes print to the console they were started from:
+/// Windows release builds use the GUI subsystem, which gets no console.
+pub fn attach_console() {
+    #[cfg(target_os = "windows")]
+    {
+        use windows_sys::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+        // SAFETY: takes no pointers; failing just means there is no console to attach to
+        unsafe {
+            AttachConsole(ATTACH_PARENT_PROCESS);
+        }
+    }
+}
+
+/// Makes `path` the desktop background. Linux and the BSDs go through gsettings
+/// on GNOME-based desktops; other desktops and platforms report Unsupported.
+/// Blocks until the desktop has answered, so call it off the UI thread.
+pub fn set_wallpaper(path: &Path) -> io::Result<()> {
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::ffi::OsStrExt;
+        use windows_sys::Win32::UI::WindowsAndMessaging::{
+            SystemParametersInfoW, SPIF_SENDCHANGE, SPIF_UPDATEINIFILE, SPI_SETDESKWALLPAPER,
+        };
+
+        let mut wide: Vec<u16> = std::ffi::OsStr::new(plain_path(path).as_ref())
+            .encode_wide()
+            .chain(Some(0))
+            .collect();
+        // SAFETY: `wide` is a NUL-terminated UTF-16 string that outlives the call
+        let ok = unsafe {
+            SystemParametersInfoW(
+                SPI_SETDESKWALLPAPER,
+                0,
+                wide.as_mut_ptr().cast(),
+                SPIF_UPDATEINIFILE | SPIF_SENDCHANGE,
+            )
+        };
+        if ok == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let escaped = path
+            .to_string_lossy()
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"");
+        let script = format!(
+            "tell application \"System Events\" to tell every desktop to set picture to POSIX file \"{}\"",
+            escaped
+        );
+        run(Command::new("osascript").arg("-e").arg(script))
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        use std::os::unix::ffi::OsStrExt;
+
+        // KDE, XFCE and others ignore these keys, so setting them would report a change
+        // that never shows
+        let desktop = std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default();
+        if !uses_gnome_background(&desktop) {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("no wallpaper support for desktop {:?}", desktop),
+            ));
+        }
+
+        let mut uri = "file://".to_string();
+        for &byte in path.as_os_str().as_bytes() {
+            if byte.is_ascii_alphanumeric() || b"/-._~".contains(&byte) {
+                uri.push(byte as char);
+            } else {
+                uri.push_str(&format!("%{:02X}", byte));
+            }
+        }
+        // GNOME 42+ keeps a separate picture for the dark style
+        for key in ["picture-uri", "picture-uri-dark"] {
+            run(Command::new("gsettings")
+                .args(["set", "org.gnome.desktop.background", key])
+                .arg(&uri))?;
+        }
+        Ok(())
+    }
+
+    #[cfg(not(any(unix, target_os = "windows")))]
+    {
+        let _ = path;
+        Err(io::Error::from(io::ErrorKind::Unsupported))
+    }
+}
+
+/// Whether the desktop named by `XDG_CURRENT_DESKTOP`, a colon-separated list,
+/// takes its background from GNOME's `org.gnome.desktop.background` settings.
+#[cfg_attr(any(target_os = "macos", not(unix)), allow(dead_code))]
+fn uses_gnome_background(xdg_current_desktop: &str) -> bool {
+    xdg_current_desktop.split(':').any(|name| {
+        ["GNOME", "Unity", "Budgie"]
+            .iter()
+            .any(|d| name.eq_ignore_ascii_case(d))
+    })
+}
+
+/// `path` as text without the \\?\ prefix of canonical Windows paths,
+/// which most programs reject. UNC paths keep it, since dropping it changes their meaning.
+pub fn plain_path(path: &Path) -> Cow<'_, str> {
+    match path.to_string_lossy() {
+        Cow::Borrowed(text) => Cow::Borrowed(strip_verbatim(text)),
+        Cow::Owned(text) => Cow::Owned(strip_verbatim(&text).to_string()),
+    }
+}
+
+fn strip_verbatim(text: &str) -> &str {
+    match text.strip_prefix(r"\\?\") {
+        Some(local) if !local.starts_with(r"UNC\") => local,
+        _ => text,
+    }
+}
+
+#[cfg(unix)]
+fn run(command: &mut Command) -> io::Result<()> {
+    let status = command.status()?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "{:?} exited with {}",
+            command, status
+        )));
+    }
+    Ok(())
+}
+
+/// Starts `command` without waiting for it. Exit codes are ignored, since
+/// Explorer reports failure even when it opened fine.
+fn spawn_detached(command: &mut Command) -> io::Result<()> {
+    let mut child = command.spawn()?;
+    // Reap the process once it exits so it doesn't linger as a zombie
+    thread::spawn(move || child.wait());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_gnome_based_desktops_take_the_gnome_background() {
+        assert!(uses_gnome_background("GNOME"));
+        assert!(uses_gnome_background("ubuntu:GNOME"));
+        assert!(uses_gnome_background("Budgie:GNOME"));
+        assert!(!uses_gnome_background("KDE"));
+        assert!(!uses_gnome_background("XFCE"));
+        assert!(!uses_gnome_background(""));
+    }
+}